@@ -0,0 +1,54 @@
+//! Pin direction and bus width provider for NetlistDB.
+
+use super::*;
+use netlistdb::LeafPinProvider;
+
+impl LeafPinProvider for Lef {
+    #[inline]
+    fn direction_of(
+        &self,
+        macro_name: &CompactString,
+        pin_name: &CompactString, pin_idx: Option<isize>
+    ) -> Direction {
+        let pin = match pin_idx {
+            Some(i) => self.find_pin(macro_name, &format!("{}[{}]", pin_name, i)),
+            None => self.find_pin(macro_name, pin_name)
+        };
+        pin.map(|p| p.direction).unwrap_or(Direction::Unknown)
+    }
+
+    #[inline]
+    fn width_of(
+        &self,
+        macro_name: &CompactString,
+        pin_name: &CompactString
+    ) -> Option<SVerilogRange> {
+        self.macros.get(macro_name)?.buses.get(pin_name).copied()
+    }
+
+    /// The pins in the order of declaration. The bits of a bus
+    /// like `D[0]` are merged at the first one.
+    ///
+    /// Supply pins (`USE POWER` or `USE GROUND`) are skipped, as
+    /// they never appear in positional connections.
+    fn ordered_pins_of(
+        &self,
+        macro_name: &CompactString
+    ) -> Option<Vec<CompactString>> {
+        let m = self.macros.get(macro_name)?;
+        let mut names = Vec::<CompactString>::with_capacity(m.pins.len());
+        for pin in &m.pins {
+            if matches!(pin.usage.as_deref(), Some("POWER" | "GROUND")) {
+                continue
+            }
+            let name = match split_bit(&pin.name) {
+                Some((base, _)) if m.buses.contains_key(base) => base,
+                _ => pin.name.as_str()
+            };
+            if !names.iter().any(|n| n.as_str() == name) {
+                names.push(name.into());
+            }
+        }
+        Some(names)
+    }
+}
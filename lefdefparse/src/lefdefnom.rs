@@ -0,0 +1,399 @@
+use super::*;
+use nom::{
+    error::{ ErrorKind, ParseError, ContextError, FromExternalError, context },
+    combinator::{ value, map, map_res, opt, cut, not, verify, recognize },
+    branch::alt,
+    multi::{ many0, many0_count, many1, many_till },
+    sequence::{ delimited, pair, preceded, terminated, tuple },
+    // we do not plan to stream LEF/DEF. so we
+    // only include completes.
+    character::complete::{ char, multispace0 },
+    bytes::complete::{ tag, take_till, take_till1, is_not },
+};
+
+/// the nom error type, which records the expected token and the
+/// innermost construct being parsed.
+///
+/// it is created frequently when backtracking, so it must not
+/// allocate.
+#[derive(Debug)]
+pub(crate) struct NomError<I> {
+    input: I,
+    code: ErrorKind,
+    expected_kw: Option<&'static str>,
+    expected: Option<&'static str>,
+    construct: Option<&'static str>,
+}
+
+impl<I> ParseError<I> for NomError<I> {
+    fn from_error_kind(input: I, code: ErrorKind) -> Self {
+        NomError {
+            input, code,
+            expected_kw: None, expected: None, construct: None
+        }
+    }
+
+    fn append(_: I, _: ErrorKind, other: Self) -> Self {
+        other
+    }
+}
+
+impl<I> ContextError<I> for NomError<I> {
+    fn add_context(_: I, ctx: &'static str, mut other: Self) -> Self {
+        if other.construct.is_none() {
+            other.construct = Some(ctx);
+        }
+        other
+    }
+}
+
+impl<I, E> FromExternalError<I, E> for NomError<I> {
+    fn from_external_error(input: I, code: ErrorKind, _: E) -> Self {
+        NomError::from_error_kind(input, code)
+    }
+}
+
+type IResult<I, O> = nom::IResult<I, O, NomError<I>>;
+
+fn u82str_lossy(i: &[u8]) -> CompactString {
+    CompactString::from(String::from_utf8_lossy(i))
+}
+
+/// comment: starts with # and ends with a line.
+pub fn skip_whitespace_and_comment(mut i: &[u8]) -> IResult<&[u8], ()> {
+    (i, _) = multispace0(i)?;
+    while i.first() == Some(&b'#') {
+        (i, _) = opt(is_not("\r\n"))(i)?;
+        (i, _) = multispace0(i)?;
+    }
+    Ok((i, ()))
+}
+
+/// LEF/DEF are made of whitespace-separated tokens.
+/// a token is a quoted string, a `;`, or a run of other
+/// characters.
+fn token(i: &[u8]) -> IResult<&[u8], &[u8]> {
+    delimited(skip_whitespace_and_comment, alt((
+        recognize(tuple((char('"'), take_till(|c| c == b'"'), char('"')))),
+        tag(";"),
+        take_till1(|c: u8| c.is_ascii_whitespace() || c == b';')
+    )), skip_whitespace_and_comment)(i)
+}
+
+/// a specific keyword token.
+fn kw<'a>(k: &'static str) -> impl FnMut(&'a [u8]) -> IResult<&'a [u8], &'a [u8]> {
+    let mut inner = verify(token, move |t: &[u8]| t == k.as_bytes());
+    move |i| inner(i).map_err(|e| e.map(|e| NomError {
+        expected_kw: Some(k), expected: None, ..e
+    }))
+}
+
+/// a higher-order parser that reports `what` as expected, if
+/// the inner parser fails without consuming anything other than
+/// spaces.
+fn expecting<'a, F, O>(what: &'static str, mut inner: F) ->
+impl FnMut(&'a [u8]) -> IResult<&'a [u8], O>
+where F: FnMut(&'a [u8]) -> IResult<&'a [u8], O> {
+    move |i| inner(i).map_err(|e| e.map(|e| {
+        let start = skip_whitespace_and_comment(i).map_or(i, |(j, _)| j);
+        match e.input.len() >= start.len() {
+            true => NomError { expected_kw: None, expected: Some(what), ..e },
+            false => e
+        }
+    }))
+}
+
+/// a token other than the given ones.
+fn token_except<'a>(ks: &'static [&'static str]) -> impl FnMut(&'a [u8]) -> IResult<&'a [u8], &'a [u8]> {
+    verify(token, move |t: &[u8]| !ks.iter().any(|k| t == k.as_bytes()))
+}
+
+/// the name after `END`, which must match the one at the start
+/// of a block.
+fn end_name<'a>(name: &[u8], i: &'a [u8]) -> IResult<&'a [u8], &'a [u8]> {
+    expecting("the name of the block", verify(token, |t: &[u8]| t == name))(i)
+}
+
+fn ident(i: &[u8]) -> IResult<&[u8], CompactString> {
+    map(token_except(&[";"]), u82str_lossy)(i)
+}
+
+fn int(i: &[u8]) -> IResult<&[u8], i64> {
+    expecting("an integer", map_res(token, |t| u82str_lossy(t).parse::<i64>()))(i)
+}
+
+fn float(i: &[u8]) -> IResult<&[u8], f64> {
+    expecting("a number", map_res(token, |t| u82str_lossy(t).parse::<f64>()))(i)
+}
+
+/// skip a statement that is not `END`, up to the `;`.
+fn skip_statement(i: &[u8]) -> IResult<&[u8], ()> {
+    value((), preceded(not(kw("END")), many_till(token, kw(";"))))(i)
+}
+
+/// skip a block ending with `END <k>`, like `UNITS ... END UNITS`.
+fn skip_block<'a>(k: &'static str) -> impl FnMut(&'a [u8]) -> IResult<&'a [u8], ()> {
+    value((), preceded(kw(k), cut(many_till(token, pair(kw("END"), kw(k))))))
+}
+
+/// skip a block ending with a bare `END`, like `OBS ... END`.
+fn skip_bare_block<'a>(k: &'static str) -> impl FnMut(&'a [u8]) -> IResult<&'a [u8], ()> {
+    value((), preceded(kw(k), cut(many_till(token, kw("END")))))
+}
+
+/// skip a named block ending with `END <name>`,
+/// like `LAYER M1 ... END M1`.
+fn skip_named_block(i: &[u8]) -> IResult<&[u8], ()> {
+    let (i, _) = alt((
+        kw("LAYER"), kw("VIA"), kw("VIARULE"), kw("SITE"),
+        kw("NONDEFAULTRULE")
+    ))(i)?;
+    let (i, name) = cut(token)(i)?;
+    let (i, _) = cut(many_till(token, pair(
+        kw("END"), |i| end_name(name, i)
+    )))(i)?;
+    Ok((i, ()))
+}
+
+fn lef_pin(i: &[u8]) -> IResult<&[u8], LefPin> {
+    let (i, name) = preceded(kw("PIN"), cut(ident))(i)?;
+    let mut direction = Direction::Unknown;
+    let mut usage = None;
+    let (i, _) = many0_count(alt((
+        map(delimited(kw("DIRECTION"), cut(token), cut(many_till(token, kw(";")))),
+            |d| direction = match d {
+                b"INPUT" => Direction::I,
+                b"OUTPUT" => Direction::O,
+                _ => Direction::InOut
+            }),
+        map(delimited(kw("USE"), cut(ident), cut(kw(";"))),
+            |u| usage = Some(u)),
+        skip_bare_block("PORT"),
+        skip_statement
+    )))(i)?;
+    let (i, _) = cut(pair(
+        kw("END"), |i| end_name(name.as_bytes(), i)
+    ))(i)?;
+    Ok((i, LefPin { name, direction, usage }))
+}
+
+fn lef_macro(i: &[u8]) -> IResult<&[u8], LefMacro> {
+    let (i, name) = preceded(kw("MACRO"), cut(ident))(i)?;
+    let mut class = None;
+    let mut size = None;
+    let mut pins = Vec::new();
+    let (i, _) = many0_count(alt((
+        map(preceded(kw("CLASS"), cut(many_till(ident, kw(";")))),
+            |(c, _)| class = Some(c.join(" ").into())),
+        map(tuple((kw("SIZE"), cut(float), cut(kw("BY")), cut(float), cut(kw(";")))),
+            |(_, w, _, h, _)| size = Some((w, h))),
+        map(context("pin", lef_pin), |p| pins.push(p)),
+        skip_bare_block("OBS"),
+        skip_bare_block("DENSITY"),
+        skip_statement
+    )))(i)?;
+    let (i, _) = cut(pair(
+        kw("END"), |i| end_name(name.as_bytes(), i)
+    ))(i)?;
+    let mut buses = HashMap::<CompactString, SVerilogRange>::new();
+    for (base, idx) in pins.iter().filter_map(|p| split_bit(&p.name)) {
+        buses.entry(base.into())
+            .and_modify(|SVerilogRange(l, r)| {
+                *l = (*l).max(idx);
+                *r = (*r).min(idx);
+            })
+            .or_insert(SVerilogRange(idx, idx));
+    }
+    Ok((i, LefMacro { name, class, size, pins, buses }))
+}
+
+fn lef(i: &[u8]) -> IResult<&[u8], Lef> {
+    let mut macros = HashMap::new();
+    let (i, _) = many0_count(alt((
+        map(context("macro", lef_macro), |m| {
+            macros.insert(m.name.clone(), m);
+        }),
+        skip_named_block,
+        skip_block("UNITS"),
+        skip_block("PROPERTYDEFINITIONS"),
+        skip_block("SPACING"),
+        skip_statement
+    )))(i)?;
+    let (i, _) = opt(pair(kw("END"), kw("LIBRARY")))(i)?;
+    Ok((i, Lef { macros }))
+}
+
+/// a DEF option key and the following tokens.
+type DefOption<'a> = (&'a [u8], Vec<&'a [u8]>);
+
+/// a DEF option like `+ PLACED ( 0 0 ) N`.
+fn def_option(i: &[u8]) -> IResult<&[u8], DefOption<'_>> {
+    preceded(kw("+"), cut(pair(token, many0(token_except(&["+", ";"])))))(i)
+}
+
+/// interpret a placement option.
+fn def_placement(key: &[u8], args: &[&[u8]]) -> Option<DefPlacement> {
+    let status = match key {
+        b"PLACED" => DefPlacementStatus::Placed,
+        b"FIXED" => DefPlacementStatus::Fixed,
+        b"COVER" => DefPlacementStatus::Cover,
+        _ => return None
+    };
+    let [b"(", x, y, b")", orient, ..] = args else { return None };
+    let parse = |t: &[u8]| u82str_lossy(t).parse::<i64>().ok();
+    use DefOrient::*;
+    Some(DefPlacement {
+        status, x: parse(x)?, y: parse(y)?,
+        orient: match *orient {
+            b"N" => N, b"S" => S, b"E" => E, b"W" => W,
+            b"FN" => FN, b"FS" => FS, b"FE" => FE, b"FW" => FW,
+            _ => return None
+        }
+    })
+}
+
+/// a placement option that fails to be interpreted is an error.
+fn def_checked_placement(
+    (key, args): DefOption<'_>
+) -> Result<Option<DefPlacement>, ()> {
+    match def_placement(key, &args) {
+        None if matches!(key, b"PLACED" | b"FIXED" | b"COVER") => Err(()),
+        p => Ok(p)
+    }
+}
+
+fn def_component(i: &[u8]) -> IResult<&[u8], DefComponent> {
+    let (i, (name, macro_name)) = preceded(kw("-"), cut(pair(ident, ident)))(i)?;
+    let (i, (placements, _)) = cut(many_till(
+        expecting("a valid placement",
+                  map_res(def_option, def_checked_placement)), kw(";")
+    ))(i)?;
+    Ok((i, DefComponent {
+        name, macro_name,
+        placement: placements.into_iter().flatten().next()
+    }))
+}
+
+fn def_pin(i: &[u8]) -> IResult<&[u8], DefPin> {
+    let (i, name) = preceded(kw("-"), cut(ident))(i)?;
+    let mut pin = DefPin {
+        name, net: None, direction: None, placement: None
+    };
+    let (i, _) = cut(many_till(expecting("a valid option", map_res(def_option, |(key, args)| {
+        match (key, &args[..]) {
+            (b"NET", [net]) => pin.net = Some(u82str_lossy(net)),
+            (b"DIRECTION", [d]) => pin.direction = Some(match *d {
+                b"INPUT" => Direction::I,
+                b"OUTPUT" => Direction::O,
+                _ => Direction::InOut
+            }),
+            _ => if let Some(p) = def_checked_placement((key, args))? {
+                pin.placement.get_or_insert(p);
+            }
+        }
+        Ok::<_, ()>(())
+    })), kw(";")))(i)?;
+    Ok((i, pin))
+}
+
+fn def_net(i: &[u8]) -> IResult<&[u8], DefNet> {
+    let (i, name) = preceded(kw("-"), cut(ident))(i)?;
+    let (i, conns) = many0(delimited(
+        kw("("),
+        cut(pair(ident, ident)),
+        cut(pair(many0(token_except(&[")", ";"])), kw(")")))
+    ))(i)?;
+    let (i, _) = cut(many_till(def_option, kw(";")))(i)?;
+    Ok((i, DefNet { name, conns }))
+}
+
+/// a DEF section like `COMPONENTS 10 ; ... END COMPONENTS`.
+fn def_section<'a, O>(
+    k: &'static str, item: impl FnMut(&'a [u8]) -> IResult<&'a [u8], O>
+) -> impl FnMut(&'a [u8]) -> IResult<&'a [u8], Vec<O>> {
+    delimited(
+        pair(kw(k), cut(pair(int, kw(";")))),
+        cut(many0(item)),
+        cut(pair(kw("END"), kw(k)))
+    )
+}
+
+fn def(i: &[u8]) -> IResult<&[u8], Def> {
+    let mut design = CompactString::new("");
+    let mut dbu_per_micron = None;
+    let mut die_area = Vec::new();
+    let mut components = Vec::new();
+    let mut pins = Vec::new();
+    let mut nets = Vec::new();
+    let (i, _) = many0_count(alt((
+        map(delimited(kw("DESIGN"), cut(ident), cut(kw(";"))),
+            |d| design = d),
+        map(delimited(tuple((kw("UNITS"), kw("DISTANCE"), kw("MICRONS"))),
+                      cut(int), cut(kw(";"))),
+            |u| dbu_per_micron = Some(u as u32)),
+        map(delimited(kw("DIEAREA"), cut(many1(delimited(
+            kw("("), pair(int, int), kw(")")
+        ))), cut(kw(";"))), |pts| die_area = pts),
+        map(def_section("COMPONENTS", context("component", def_component)),
+            |c| components = c),
+        map(def_section("PINS", context("pin", def_pin)), |p| pins = p),
+        map(def_section("NETS", context("net", def_net)), |n| nets = n),
+        alt((
+            skip_block("VIAS"), skip_block("SPECIALNETS"),
+            skip_block("NONDEFAULTRULES"), skip_block("REGIONS"),
+            skip_block("GROUPS"), skip_block("BLOCKAGES"),
+            skip_block("FILLS"), skip_block("PROPERTYDEFINITIONS"),
+            skip_block("STYLES"), skip_block("SCANCHAINS"),
+            skip_block("SLOTS"), skip_block("PINPROPERTIES"),
+        )),
+        skip_statement
+    )))(i)?;
+    let (i, _) = cut(pair(kw("END"), kw("DESIGN")))(i)?;
+    Ok((i, Def {
+        design, dbu_per_micron, die_area, components, pins, nets
+    }))
+}
+
+/// convert a nom error to a structured one.
+fn convert_error(source: &[u8], e: nom::Err<NomError<&[u8]>>) -> LefDefParseError {
+    match e {
+        nom::Err::Incomplete(_) => LefDefParseError::at(
+            source, source.len(), None, "unexpected end of input".into()),
+        nom::Err::Error(e) | nom::Err::Failure(e) => {
+            // the errors are reported at the failed token,
+            // after the spaces and comments.
+            let input = skip_whitespace_and_comment(e.input)
+                .map_or(e.input, |(i, _)| i);
+            let offset = source.len() - input.len();
+            let message = match (e.expected_kw, e.expected, e.code) {
+                (Some(k), _, _) => format!("expected `{}`", k),
+                (_, Some(what), _) => format!("expected {}", what),
+                _ if input.is_empty() => "unexpected end of input".into(),
+                (_, _, ErrorKind::Complete) => "unexpected input".into(),
+                (_, _, code) => format!("unexpected input ({})", code.description())
+            };
+            LefDefParseError::at(source, offset, e.construct, message)
+        }
+    }
+}
+
+/// run a parser to the end of input.
+fn parse_all<'a, O>(
+    parser: impl FnMut(&'a [u8]) -> IResult<&'a [u8], O>, i: &'a [u8]
+) -> Result<O, LefDefParseError> {
+    let (rem, o) = terminated(parser, skip_whitespace_and_comment)(i)
+        .map_err(|e| convert_error(i, e))?;
+    if !rem.is_empty() {
+        return Err(convert_error(i, nom::Err::Error(
+            NomError::from_error_kind(rem, ErrorKind::Complete))))
+    }
+    Ok(o)
+}
+
+pub(crate) fn parse_lef(i: &[u8]) -> Result<Lef, LefDefParseError> {
+    parse_all(lef, i)
+}
+
+pub(crate) fn parse_def(i: &[u8]) -> Result<Def, LefDefParseError> {
+    parse_all(def, i)
+}
@@ -0,0 +1,228 @@
+//! A LEF/DEF parser written in Rust.
+//!
+//! Only the parts related to the netlist are interpreted:
+//! macros and pins in LEF, and components, pins and nets in DEF.
+//! Other statements (layers, vias, routing, special nets, etc.)
+//! are skipped.
+//!
+//! # Usage
+//!
+//! ```
+//! use lefdefparse::{Lef, Def};
+//!
+//! let lef = Lef::parse_str(r#"
+//! MACRO INV
+//!   CLASS CORE ;
+//!   SIZE 0.4 BY 1.2 ;
+//!   PIN A DIRECTION INPUT ; END A
+//!   PIN Y DIRECTION OUTPUT ; END Y
+//! END INV
+//! END LIBRARY
+//! "#).expect("lef parse error");
+//! let def = Def::parse_str(r#"
+//! DESIGN top ;
+//! UNITS DISTANCE MICRONS 1000 ;
+//! COMPONENTS 1 ;
+//!   - u1 INV + PLACED ( 0 0 ) N ;
+//! END COMPONENTS
+//! PINS 2 ;
+//!   - a + NET a + DIRECTION INPUT ;
+//!   - y + NET y + DIRECTION OUTPUT ;
+//! END PINS
+//! NETS 2 ;
+//!   - a ( PIN a ) ( u1 A ) ;
+//!   - y ( u1 Y ) ( PIN y ) ;
+//! END NETS
+//! END DESIGN
+//! "#).expect("def parse error");
+//! let placed = def.to_netlistdb(&lef).expect("netlistdb error");
+//! assert_eq!(placed.db.num_cells, 2);
+//! ```
+
+use compact_str::CompactString;
+use std::collections::HashMap;
+use netlistdb::Direction;
+use sverilogparse::SVerilogRange;
+
+/// The macros in a LEF library.
+#[derive(Debug, Clone, Default)]
+pub struct Lef {
+    /// Macros by their names.
+    pub macros: HashMap<CompactString, LefMacro>,
+}
+
+/// A LEF macro.
+#[derive(Debug, Clone)]
+pub struct LefMacro {
+    /// Macro name. E.g. `NAND2_X1`.
+    pub name: CompactString,
+    /// Macro class. E.g. `CORE` or `PAD INOUT`.
+    pub class: Option<CompactString>,
+    /// Macro size (width, height) in microns.
+    pub size: Option<(f64, f64)>,
+    /// Pins in the order they appear.
+    /// Bus bits are separate pins, like `D[0]`.
+    pub pins: Vec<LefPin>,
+    /// The bus ranges, inferred from the pin names like `D[0]`.
+    pub buses: HashMap<CompactString, SVerilogRange>,
+}
+
+/// A LEF macro pin.
+#[derive(Debug, Clone)]
+pub struct LefPin {
+    /// Pin name. E.g. `A` or `D[0]`.
+    pub name: CompactString,
+    /// Pin direction. `FEEDTHRU` pins are regarded as
+    /// [Direction::InOut].
+    pub direction: Direction,
+    /// Pin usage. E.g. `SIGNAL` or `POWER`.
+    pub usage: Option<CompactString>,
+}
+
+/// The netlist and placement in a DEF file.
+#[derive(Debug, Clone, Default)]
+pub struct Def {
+    /// Design name.
+    pub design: CompactString,
+    /// Database units per micron.
+    pub dbu_per_micron: Option<u32>,
+    /// Die area polygon, in database units.
+    pub die_area: Vec<(i64, i64)>,
+    /// Components (cell instances).
+    pub components: Vec<DefComponent>,
+    /// Top-level I/O pins.
+    pub pins: Vec<DefPin>,
+    /// Signal nets.
+    pub nets: Vec<DefNet>,
+}
+
+/// A DEF component.
+#[derive(Debug, Clone)]
+pub struct DefComponent {
+    /// Component name. E.g. `u_core/u1`.
+    pub name: CompactString,
+    /// Macro name. E.g. `NAND2_X1`.
+    pub macro_name: CompactString,
+    /// Placement, if placed.
+    pub placement: Option<DefPlacement>,
+}
+
+/// A DEF top-level pin.
+#[derive(Debug, Clone)]
+pub struct DefPin {
+    /// Pin name. E.g. `clk` or `a[3]`.
+    pub name: CompactString,
+    /// The net it is on.
+    pub net: Option<CompactString>,
+    /// Pin direction, if specified.
+    pub direction: Option<Direction>,
+    /// Placement, if placed.
+    pub placement: Option<DefPlacement>,
+}
+
+/// A DEF net.
+#[derive(Debug, Clone)]
+pub struct DefNet {
+    /// Net name.
+    pub name: CompactString,
+    /// Connections of (component name, pin name).
+    /// The component name is `PIN` for top-level pins.
+    pub conns: Vec<(CompactString, CompactString)>,
+}
+
+/// The placement of a component or pin, in database units.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DefPlacement {
+    pub status: DefPlacementStatus,
+    pub x: i64,
+    pub y: i64,
+    pub orient: DefOrient,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DefPlacementStatus {
+    Placed,
+    Fixed,
+    Cover
+}
+
+/// The orientations in DEF.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DefOrient {
+    N, S, E, W, FN, FS, FE, FW
+}
+
+mod lefdefnom;
+/// A LEF/DEF parse error, with the location and a snippet of
+/// the source line.
+pub type LefDefParseError = sverilogparse::LocatedParseError;
+mod leaf_pin;
+mod netlist;
+pub use netlist::PlacedNetlistDB;
+
+impl Lef {
+    /// Parses a string of LEF code, and returns a [Result], indicating successful parse result or an error with location.
+    #[inline]
+    pub fn parse_str(s: &str) -> Result<Lef, LefDefParseError> {
+        lefdefnom::parse_lef(s.as_bytes())
+    }
+
+    /// Parses a u8 slice of LEF code, and returns a [Result], indicating successful parse result or an error with location.
+    #[inline]
+    pub fn parse_u8slice(s: &[u8]) -> Result<Lef, LefDefParseError> {
+        lefdefnom::parse_lef(s)
+    }
+
+    /// Parses a LEF file at the specific path, and returns a [Result], indicating successful parse result or an error with location.
+    #[inline]
+    pub fn parse_file(path: impl AsRef<std::path::Path>) -> Result<Lef, LefDefParseError> {
+        let path = path.as_ref();
+        let s = match std::fs::read(path) {
+            Ok(s) => s,
+            Err(e) => return Err(LefDefParseError::other(format!("{}", e))
+                                 .with_path(path))
+        };
+        Lef::parse_u8slice(&s).map_err(|e| e.with_path(path))
+    }
+
+    /// Find a pin of a macro. Bus bits are named like `D[0]`.
+    #[inline]
+    pub fn find_pin(
+        &self, macro_name: &str, pin_name: &str
+    ) -> Option<&LefPin> {
+        self.macros.get(macro_name)?.pins.iter()
+            .find(|p| p.name == pin_name)
+    }
+}
+
+impl Def {
+    /// Parses a string of DEF code, and returns a [Result], indicating successful parse result or an error with location.
+    #[inline]
+    pub fn parse_str(s: &str) -> Result<Def, LefDefParseError> {
+        lefdefnom::parse_def(s.as_bytes())
+    }
+
+    /// Parses a u8 slice of DEF code, and returns a [Result], indicating successful parse result or an error with location.
+    #[inline]
+    pub fn parse_u8slice(s: &[u8]) -> Result<Def, LefDefParseError> {
+        lefdefnom::parse_def(s)
+    }
+
+    /// Parses a DEF file at the specific path, and returns a [Result], indicating successful parse result or an error with location.
+    #[inline]
+    pub fn parse_file(path: impl AsRef<std::path::Path>) -> Result<Def, LefDefParseError> {
+        let path = path.as_ref();
+        let s = match std::fs::read(path) {
+            Ok(s) => s,
+            Err(e) => return Err(LefDefParseError::other(format!("{}", e))
+                                 .with_path(path))
+        };
+        Def::parse_u8slice(&s).map_err(|e| e.with_path(path))
+    }
+}
+
+/// split a bus bit name like `D[3]` into `D` and 3.
+pub(crate) fn split_bit(name: &str) -> Option<(&str, isize)> {
+    let (base, idx) = name.strip_suffix(']')?.rsplit_once('[')?;
+    Some((base, idx.parse().ok()?))
+}
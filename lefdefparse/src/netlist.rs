@@ -0,0 +1,212 @@
+//! Building NetlistDB from DEF.
+
+use super::*;
+use std::collections::HashSet;
+use netlistdb::{NetlistDB, HierName, LeafPinProvider};
+use sverilogparse::{
+    SVerilog, SVerilogModule, SVerilogPortDef, SVerilogWireDef,
+    WireDefType, SVerilogAssign, SVerilogCell, Wirexpr, WirexprBasic
+};
+
+/// A [NetlistDB] built from DEF, with the placement of cells.
+#[derive(Debug)]
+pub struct PlacedNetlistDB {
+    /// The netlist database.
+    pub db: NetlistDB,
+    /// The placement of each cell, indexed by cell id.
+    /// It is None for the top-level cell and unplaced components.
+    pub cell2placement: Vec<Option<DefPlacement>>,
+}
+
+type BitName = (CompactString, Option<isize>);
+
+/// split a name like `n[3]` into a bus bit, unless the base
+/// name is also used as a scalar.
+fn bit_name(name: &str, scalars: &HashSet<&str>) -> BitName {
+    match split_bit(name) {
+        Some((base, idx)) if !scalars.contains(base) => (base.into(), Some(idx)),
+        _ => (name.into(), None)
+    }
+}
+
+fn bit_ref((name, idx): &BitName) -> WirexprBasic {
+    match idx {
+        None => WirexprBasic::Full(name.clone()),
+        Some(i) => WirexprBasic::SingleBit(name.clone(), *i)
+    }
+}
+
+/// group bus bits by their names, in the order of their first
+/// appearance. returns the name, the range, and the index of
+/// the first appearance.
+fn group_ranges<'a>(
+    bits: impl Iterator<Item = &'a BitName>
+) -> Vec<(CompactString, Option<SVerilogRange>, usize)> {
+    let mut groups = Vec::<(CompactString, Option<SVerilogRange>, usize)>::new();
+    let mut name2group = HashMap::<&CompactString, usize>::new();
+    for (i, (name, idx)) in bits.enumerate() {
+        let g = *name2group.entry(name).or_insert_with(|| {
+            groups.push((name.clone(), idx.map(|idx| SVerilogRange(idx, idx)), i));
+            groups.len() - 1
+        });
+        if let (Some(SVerilogRange(l, r)), Some(idx)) = (&mut groups[g].1, idx) {
+            *l = (*l).max(*idx);
+            *r = (*r).min(*idx);
+        }
+    }
+    groups
+}
+
+impl Def {
+    /// Convert the DEF netlist into a flat structural verilog
+    /// module, with the bus widths of macro pins queried from
+    /// the library.
+    ///
+    /// Nets and pins named like `n[3]` become bus bits.
+    /// The unconnected bits of a partially connected macro bus pin
+    /// are connected to new dangling wires, named like
+    /// `lefdef_unconnected_0`.
+    /// Special nets are not included.
+    pub fn to_sverilog(&self, lib: &impl LeafPinProvider) -> Option<SVerilog> {
+        let scalars = self.pins.iter().map(|p| p.name.as_str())
+            .chain(self.nets.iter().map(|n| n.name.as_str()))
+            .filter(|name| split_bit(name).is_none())
+            .collect::<HashSet<_>>();
+        let port_bits = self.pins.iter()
+            .map(|p| bit_name(&p.name, &scalars))
+            .collect::<Vec<_>>();
+        let net_bits = self.nets.iter()
+            .map(|n| bit_name(&n.name, &scalars))
+            .collect::<Vec<_>>();
+
+        let mut ports = Vec::new();
+        let mut defs = Vec::new();
+        let mut assigns = Vec::new();
+        let mut port_names = HashSet::new();
+        for (name, width, i) in group_ranges(port_bits.iter()) {
+            let typ = match self.pins[i].direction {
+                Some(Direction::I) => WireDefType::Input,
+                Some(Direction::O) => WireDefType::Output,
+                _ => WireDefType::InOut
+            };
+            ports.push(SVerilogPortDef::Basic(name.clone()));
+            defs.push(SVerilogWireDef {
+                name: name.clone(), width, typ,
+                signed: false, packed_dims: Vec::new(), unpacked_dims: Vec::new(),
+                attrs: Vec::new()
+            });
+            port_names.insert(name);
+        }
+        for (name, width, _) in group_ranges(net_bits.iter()) {
+            if !port_names.contains(&name) {
+                defs.push(SVerilogWireDef {
+                    name, width, typ: WireDefType::Wire,
+                    signed: false, packed_dims: Vec::new(), unpacked_dims: Vec::new(),
+                    attrs: Vec::new()
+                });
+            }
+        }
+
+        // collect the pins of each component.
+        let comp2id = self.components.iter().enumerate()
+            .map(|(i, c)| (c.name.as_str(), i))
+            .collect::<HashMap<_, _>>();
+        let mut comp2pins = vec![Vec::<(BitName, usize)>::new(); self.components.len()];
+        for (netid, net) in self.nets.iter().enumerate() {
+            for (comp, pin) in &net.conns {
+                match comp.as_str() {
+                    "*" => continue,
+                    "PIN" => {
+                        let port = bit_name(pin, &scalars);
+                        if port != net_bits[netid] {
+                            assigns.push(SVerilogAssign {
+                                lhs: Wirexpr::Basic(bit_ref(&net_bits[netid])),
+                                rhs: Wirexpr::Basic(bit_ref(&port)).into(),
+                                attrs: Vec::new()
+                            });
+                        }
+                    }
+                    _ => {
+                        let Some(&compid) = comp2id.get(comp.as_str()) else {
+                            clilog::error!(DEF_COMP, "net {} connects to \
+                                                      unknown component {}",
+                                           net.name, comp);
+                            return None
+                        };
+                        let macro_name = &self.components[compid].macro_name;
+                        let bit = match split_bit(pin) {
+                            Some((base, idx)) if lib.width_of(
+                                macro_name, &base.into()
+                            ).is_some() => (base.into(), Some(idx)),
+                            _ => (pin.clone(), None)
+                        };
+                        comp2pins[compid].push((bit, netid));
+                    }
+                }
+            }
+        }
+
+        // build the cells.
+        let mut num_unconnected = 0;
+        let mut cells = Vec::with_capacity(self.components.len());
+        for (comp, pins) in self.components.iter().zip(comp2pins) {
+            let mut ioports = Vec::new();
+            let bits = pins.iter().map(|(bit, _)| bit);
+            for (name, _, i) in group_ranges(bits) {
+                let Some(range) = lib.width_of(&comp.macro_name, &name) else {
+                    let netid = pins[i].1;
+                    ioports.push((name, Wirexpr::Basic(bit_ref(&net_bits[netid]))));
+                    continue
+                };
+                let expr = range.map(|idx| {
+                    match pins.iter().find(|((n, i), _)| *n == name && *i == Some(idx)) {
+                        Some((_, netid)) => bit_ref(&net_bits[*netid]),
+                        None => {
+                            let wire = CompactString::from(
+                                format!("lefdef_unconnected_{}", num_unconnected));
+                            num_unconnected += 1;
+                            defs.push(SVerilogWireDef {
+                                name: wire.clone(), width: None,
+                                typ: WireDefType::Wire,
+                                signed: false, packed_dims: Vec::new(), unpacked_dims: Vec::new(),
+                                attrs: Vec::new()
+                            });
+                            WirexprBasic::Full(wire)
+                        }
+                    }
+                }).collect();
+                ioports.push((name, Wirexpr::Concat(expr)));
+            }
+            cells.push(SVerilogCell {
+                macro_name: comp.macro_name.clone(),
+                cell_name: comp.name.clone(),
+                params: Vec::new(),
+                ioports,
+                ordered_ioports: Vec::new(),
+                attrs: Vec::new()
+            });
+        }
+
+        Some(SVerilog {
+            modules: vec![(self.design.clone(), SVerilogModule {
+                ports, params: Vec::new(), defs, assigns, cells,
+                attrs: Vec::new()
+            })]
+        })
+    }
+
+    /// Build a [NetlistDB] from the DEF netlist, with the
+    /// placement of each cell.
+    ///
+    /// See [Def::to_sverilog] for how the netlist is converted.
+    pub fn to_netlistdb(&self, lib: &impl LeafPinProvider) -> Option<PlacedNetlistDB> {
+        let sverilog = self.to_sverilog(lib)?;
+        let db = NetlistDB::from_sverilog(sverilog, Some(&self.design), lib)?;
+        let mut cell2placement = vec![None; db.num_cells];
+        for comp in &self.components {
+            let cellid = db.cellname2id[&HierName::single(comp.name.clone())];
+            cell2placement[cellid] = comp.placement;
+        }
+        Some(PlacedNetlistDB { db, cell2placement })
+    }
+}
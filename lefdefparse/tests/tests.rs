@@ -0,0 +1,149 @@
+//! unit tests for lefdefparse
+
+use lefdefparse::*;
+use netlistdb::{Direction, HierName, LeafPinProvider, NET_DISCONNECTED};
+use sverilogparse::SVerilogRange;
+
+const LEF_SIMPLE: &str = include_str!("simple.lef");
+const DEF_SIMPLE: &str = include_str!("simple.def");
+
+#[test]
+fn test_lef() {
+  clilog::init_stdout_simple_trace();
+  let lef = Lef::parse_str(LEF_SIMPLE).expect("parse error");
+  println!("Parsed is: {lef:#?}");
+  assert_eq!(lef.macros.len(), 3);
+  let nand2 = &lef.macros["NAND2"];
+  assert_eq!(nand2.class.as_deref(), Some("CORE"));
+  assert_eq!(nand2.size, Some((0.6, 1.2)));
+  assert_eq!(nand2.pins.iter().map(|p| p.name.as_str()).collect::<Vec<_>>(),
+             ["A", "B", "Y"]);
+  assert_eq!(nand2.pins[0].usage.as_deref(), Some("SIGNAL"));
+  assert_eq!(lef.macros["PAD"].class.as_deref(), Some("PAD INOUT"));
+  assert_eq!(lef.find_pin("PAD", "C").unwrap().direction, Direction::O);
+
+  assert_eq!(lef.direction_of(&"REG4".into(), &"Q".into(), Some(2)), Direction::O);
+  assert_eq!(lef.direction_of(&"REG4".into(), &"Q".into(), Some(4)), Direction::Unknown);
+  assert_eq!(lef.direction_of(&"PAD".into(), &"PAD".into(), None), Direction::InOut);
+  assert_eq!(lef.width_of(&"REG4".into(), &"D".into()), Some(SVerilogRange(3, 0)));
+  assert_eq!(lef.width_of(&"REG4".into(), &"CK".into()), None);
+  assert_eq!(lef.ordered_pins_of(&"REG4".into()).unwrap(), ["CK", "D", "Q"]);
+  assert_eq!(lef.ordered_pins_of(&"NOPE".into()), None);
+  // supply pins are not in positional connections.
+  assert_eq!(lef.ordered_pins_of(&"PAD".into()).unwrap(), ["PAD", "I", "C"]);
+}
+
+#[test]
+fn test_def() {
+  clilog::init_stdout_simple_trace();
+  let def = Def::parse_str(DEF_SIMPLE).expect("parse error");
+  println!("Parsed is: {def:#?}");
+  assert_eq!(def.design, "top");
+  assert_eq!(def.dbu_per_micron, Some(1000));
+  assert_eq!(def.die_area, [(0, 0), (10000, 10000)]);
+  assert_eq!(def.components.len(), 3);
+  assert_eq!(def.components[1].placement, Some(DefPlacement {
+    status: DefPlacementStatus::Placed, x: 1000, y: 2400, orient: DefOrient::FS
+  }));
+  assert_eq!(def.components[2].placement, None);
+  assert_eq!(def.pins.len(), 6);
+  assert_eq!(def.pins[0].placement.unwrap().y, 5100);
+  assert_eq!(def.pins[4].net.as_deref(), Some("n_q1"));
+  assert_eq!(def.nets.len(), 7);
+  assert_eq!(def.nets[4].conns.len(), 2);
+
+  assert!(Def::parse_str("DESIGN a ; COMPONENTS 1 ; - u0 X + PLACED ( 0 ) N ; \
+                          END COMPONENTS END DESIGN").is_err());
+}
+
+#[test]
+fn test_netlistdb() {
+  clilog::init_stdout_simple_trace();
+  let lef = Lef::parse_str(LEF_SIMPLE).expect("parse error");
+  let def = Def::parse_str(DEF_SIMPLE).expect("parse error");
+  println!("Verilog: {}", def.to_sverilog(&lef).unwrap());
+  let PlacedNetlistDB { db, cell2placement } = def.to_netlistdb(&lef)
+    .expect("error building netlistdb");
+  println!("The database: {db:#?}");
+  assert_eq!(db.name, "top");
+  assert_eq!(db.num_cells, 4);
+  assert!(db.pindirect.iter().all(|d| *d != Direction::Unknown));
+
+  let u0 = db.cellname2id[&HierName::single("u0".into())];
+  assert_eq!(cell2placement[u0].unwrap().orient, DefOrient::FS);
+  let r0 = db.cellname2id[&HierName::single("r0".into())];
+  assert_eq!(cell2placement[r0], None);
+  assert_eq!(cell2placement[0], None);
+
+  // bus bits of pins and nets.
+  let pin = |cell: &str, name: &str, idx| db.pinname2id[&(
+    match cell { "" => HierName::empty(), c => HierName::single(c.into()) },
+    name.into(), idx)];
+  assert_eq!(db.pin2net[pin("r0", "D", Some(3))], db.pin2net[pin("", "d", Some(1))]);
+  assert_eq!(db.pin2net[pin("r0", "Q", Some(1))], db.pin2net[pin("", "q", Some(1))]);
+  assert_eq!(db.pin2net[pin("u0", "B", None)], db.pin2net[pin("r0", "D", Some(0))]);
+  // unconnected bus bits are dangling.
+  assert_eq!(db.net2pin.len(db.pin2net[pin("r0", "D", Some(1))]), 1);
+  assert_ne!(db.pin2net[pin("", "q", Some(2))], NET_DISCONNECTED);
+}
+
+#[test]
+fn test_parse_error() {
+  clilog::init_stdout_simple_trace();
+  let e = Def::parse_str("DESIGN a ;\nCOMPONENTS 1 ;\n  - u0 X + PLACED ( 0 ) N ;\n\
+                          END COMPONENTS\nEND DESIGN").unwrap_err();
+  println!("{e}");
+  assert_eq!((e.line, e.column), (3, 10));
+  assert_eq!(e.construct, Some("component"));
+  assert_eq!(e.message, "expected a valid placement");
+  assert_eq!(e.path, None);
+
+  let e = Lef::parse_str("MACRO INV\n  PIN A DIRECTION INPUT ;\nEND INV\n").unwrap_err();
+  println!("{e}");
+  assert_eq!((e.line, e.column), (3, 5));
+  assert_eq!(e.construct, Some("pin"));
+  assert_eq!(e.message, "expected the name of the block");
+
+  let e = Def::parse_str("DESIGN a ;\nCOMPONENTS 1 ;\nEND COMPONENTS\n").unwrap_err();
+  assert_eq!(e.line, 4);
+  assert_eq!(e.message, "expected `END`");
+
+  // the file path is kept in the errors.
+  let path = std::env::temp_dir().join("lefdefparse_test_parse_error.lef");
+  std::fs::write(&path, "MACRO INV\n  SIZE 0.4 BY x ;\nEND INV\n").unwrap();
+  let e = Lef::parse_file(&path).unwrap_err();
+  std::fs::remove_file(&path).unwrap();
+  println!("{e}");
+  assert_eq!(e.path.as_deref(), Some(path.as_path()));
+  assert_eq!((e.line, e.column), (2, 15));
+  assert_eq!(e.construct, Some("macro"));
+  assert_eq!(e.message, "expected a number");
+  assert!(format!("{e}").contains(&format!("{}:2:15", path.display())));
+
+  let e = Def::parse_file("no/such/file.def").unwrap_err();
+  assert_eq!(e.path.as_deref(), Some(std::path::Path::new("no/such/file.def")));
+  assert_eq!(e.line, 0);
+}
+
+#[test]
+fn test_positional() {
+  clilog::init_stdout_simple_trace();
+  let lef = Lef::parse_str(LEF_SIMPLE).expect("parse error");
+  let db = netlistdb::NetlistDB::from_sverilog_source("
+module top (pad, a, y);
+   inout pad;
+   input a;
+   output y;
+   PAD p0 ( pad, a, y );
+endmodule
+", None, &lef).expect("error building netlistdb");
+  let pin = |name: &str| db.pinname2id[&(
+    HierName::single("p0".into()), name.into(), None)];
+  let port = |name: &str| db.pinname2id[&(HierName::empty(), name.into(), None)];
+  assert_eq!(db.pin2net[pin("PAD")], db.pin2net[port("pad")]);
+  assert_eq!(db.pin2net[pin("I")], db.pin2net[port("a")]);
+  assert_eq!(db.pin2net[pin("C")], db.pin2net[port("y")]);
+  assert_eq!(db.pindirect[pin("C")], Direction::O);
+  assert!(!db.pinname2id.contains_key(&(
+    HierName::single("p0".into()), "VDD".into(), None)));
+}
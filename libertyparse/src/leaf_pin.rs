@@ -0,0 +1,44 @@
+//! Pin direction and bus width provider for NetlistDB.
+
+use super::*;
+use netlistdb::LeafPinProvider;
+
+impl LeafPinProvider for Liberty {
+    #[inline]
+    fn direction_of(
+        &self,
+        macro_name: &CompactString,
+        pin_name: &CompactString, pin_idx: Option<isize>
+    ) -> Direction {
+        let Some(pin) = self.find_pin(macro_name, pin_name) else {
+            return Direction::Unknown
+        };
+        match pin_idx {
+            Some(i) => pin.bits.iter()
+                .find(|(j, _)| *j == i)
+                .map(|(_, bit)| bit.direction)
+                .unwrap_or(pin.direction),
+            None => pin.direction
+        }
+    }
+
+    #[inline]
+    fn width_of(
+        &self,
+        macro_name: &CompactString,
+        pin_name: &CompactString
+    ) -> Option<SVerilogRange> {
+        self.find_pin(macro_name, pin_name)?.width
+    }
+
+    /// The pins in the order of declaration.
+    #[inline]
+    fn ordered_pins_of(
+        &self,
+        macro_name: &CompactString
+    ) -> Option<Vec<CompactString>> {
+        Some(self.cells.get(macro_name)?.pins.iter()
+             .map(|p| p.name.clone())
+             .collect())
+    }
+}
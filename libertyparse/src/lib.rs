@@ -0,0 +1,211 @@
+//! A Liberty (.lib) library parser written in Rust.
+//!
+//! # Usage
+//!
+//! Just pass a `&str` to [Liberty::parse_str]. Example:
+//! ```
+//! use libertyparse::Liberty;
+//!
+//! let lib = Liberty::parse_str(r#"
+//! library (simple) {
+//!   cell (INV) {
+//!     area : 1.0 ;
+//!     pin (A) { direction : input ; capacitance : 0.002 ; }
+//!     pin (Y) { direction : output ; function : "!A" ; }
+//!   }
+//! }
+//! "#).expect("parse error");
+//! assert_eq!(lib.cells["INV"].pins.len(), 2);
+//! ```
+//!
+//! The parsed [Liberty] implements
+//! [LeafPinProvider](netlistdb::LeafPinProvider), so it can be
+//! passed to [NetlistDB](netlistdb::NetlistDB) constructors.
+
+use compact_str::CompactString;
+use std::collections::HashMap;
+use netlistdb::Direction;
+use sverilogparse::SVerilogRange;
+
+/// A generic Liberty group, like `cell (NAND2) { ... }`.
+///
+/// This is the syntax tree of a Liberty file, without any
+/// interpretation. See [Liberty] for the interpreted library.
+#[derive(Debug, Clone)]
+pub struct LibertyGroup {
+    /// The group type. E.g. `cell`.
+    pub kind: CompactString,
+    /// The group arguments. E.g. `NAND2`.
+    pub args: Vec<CompactString>,
+    /// Simple and complex attributes in the group body.
+    pub attrs: Vec<(CompactString, LibertyValue)>,
+    /// Sub-groups in the group body.
+    pub groups: Vec<LibertyGroup>,
+}
+
+/// The value of a Liberty attribute.
+///
+/// Quoted strings are stored without the quotes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LibertyValue {
+    /// E.g. `direction : input ;`.
+    Simple(CompactString),
+    /// E.g. `index_1 ("0.1, 0.2") ;`.
+    Complex(Vec<CompactString>),
+}
+
+/// An interpreted Liberty library.
+#[derive(Debug, Clone)]
+pub struct Liberty {
+    /// Library name.
+    pub name: CompactString,
+    /// Library-level attributes, like units and thresholds.
+    pub attrs: Vec<(CompactString, LibertyValue)>,
+    /// Table templates, like `lu_table_template` and
+    /// `power_lut_template`, by their names.
+    pub templates: HashMap<CompactString, LibertyTemplate>,
+    /// Cells by their names.
+    pub cells: HashMap<CompactString, LibertyCell>,
+}
+
+/// A table template.
+#[derive(Debug, Clone)]
+pub struct LibertyTemplate {
+    /// The table variables. E.g. `input_net_transition`.
+    pub variables: Vec<CompactString>,
+    /// The default indices, one for each variable.
+    pub indices: Vec<Vec<f64>>,
+}
+
+/// A library cell.
+#[derive(Debug, Clone)]
+pub struct LibertyCell {
+    /// Cell name. E.g. `NAND2_X1`.
+    pub name: CompactString,
+    /// Cell area.
+    pub area: Option<f64>,
+    /// Pins and buses in the order they appear.
+    pub pins: Vec<LibertyPin>,
+    /// Other attributes of the cell.
+    pub attrs: Vec<(CompactString, LibertyValue)>,
+}
+
+/// A library cell pin, or a bus of pins.
+#[derive(Debug, Clone)]
+pub struct LibertyPin {
+    /// Pin name. E.g. `A`.
+    pub name: CompactString,
+    /// Bus width if it is a bus.
+    pub width: Option<SVerilogRange>,
+    /// Pin direction. `internal` pins have
+    /// [Direction::Unknown].
+    pub direction: Direction,
+    /// Pin capacitance.
+    pub capacitance: Option<f64>,
+    /// Pin function. E.g. `!(A & B)`.
+    pub function: Option<CompactString>,
+    /// Timing arcs to this pin.
+    pub timings: Vec<LibertyTiming>,
+    /// Internal power of this pin.
+    pub internal_powers: Vec<LibertyInternalPower>,
+    /// For buses, the bits with their own definitions.
+    /// The bits inherit the bus attributes they do not override.
+    pub bits: Vec<(isize, LibertyPin)>,
+    /// Other attributes of the pin.
+    pub attrs: Vec<(CompactString, LibertyValue)>,
+}
+
+/// A timing arc group.
+#[derive(Debug, Clone)]
+pub struct LibertyTiming {
+    /// The related pin(s). E.g. `A`.
+    pub related_pin: Option<CompactString>,
+    /// E.g. `negative_unate`.
+    pub timing_sense: Option<CompactString>,
+    /// E.g. `rising_edge`, `setup_rising`.
+    pub timing_type: Option<CompactString>,
+    /// Tables like `cell_rise` and `rise_transition`.
+    pub tables: Vec<LibertyTable>,
+}
+
+/// An internal power group.
+#[derive(Debug, Clone)]
+pub struct LibertyInternalPower {
+    /// The related pin(s). E.g. `A`.
+    pub related_pin: Option<CompactString>,
+    /// Tables like `rise_power` and `fall_power`.
+    pub tables: Vec<LibertyTable>,
+}
+
+/// A lookup table.
+#[derive(Debug, Clone)]
+pub struct LibertyTable {
+    /// Table type. E.g. `cell_rise`.
+    pub kind: CompactString,
+    /// Template name, if any.
+    /// The template gives default indices.
+    pub template: Option<CompactString>,
+    /// The indices overridden in this table.
+    /// Empty if the template indices are used.
+    pub indices: Vec<Vec<f64>>,
+    /// Table values, one row for each quoted string.
+    pub values: Vec<Vec<f64>>,
+}
+
+mod libertynom;
+/// A Liberty parse error, with the location and a snippet of
+/// the source line.
+pub type LibertyParseError = sverilogparse::LocatedParseError;
+mod library;
+mod leaf_pin;
+
+impl LibertyGroup {
+    /// Parses a string of Liberty code into a syntax tree, and returns a [Result], indicating successful parse result or an error with location.
+    #[inline]
+    pub fn parse_str(s: &str) -> Result<LibertyGroup, LibertyParseError> {
+        libertynom::parse_liberty(s.as_bytes())
+    }
+
+    /// Parses a u8 slice of Liberty code into a syntax tree, and returns a [Result], indicating successful parse result or an error with location.
+    #[inline]
+    pub fn parse_u8slice(s: &[u8]) -> Result<LibertyGroup, LibertyParseError> {
+        libertynom::parse_liberty(s)
+    }
+}
+
+impl Liberty {
+    /// Parses a string of Liberty code, and returns a [Result], indicating successful parse result or an error with location.
+    #[inline]
+    pub fn parse_str(s: &str) -> Result<Liberty, LibertyParseError> {
+        Liberty::from_group(&LibertyGroup::parse_str(s)?)
+            .map_err(LibertyParseError::other)
+    }
+
+    /// Parses a u8 slice of Liberty code, and returns a [Result], indicating successful parse result or an error with location.
+    #[inline]
+    pub fn parse_u8slice(s: &[u8]) -> Result<Liberty, LibertyParseError> {
+        Liberty::from_group(&LibertyGroup::parse_u8slice(s)?)
+            .map_err(LibertyParseError::other)
+    }
+
+    /// Parses a Liberty file at the specific path, and returns a [Result], indicating successful parse result or an error with location.
+    #[inline]
+    pub fn parse_file(path: impl AsRef<std::path::Path>) -> Result<Liberty, LibertyParseError> {
+        let path = path.as_ref();
+        let s = match std::fs::read(path) {
+            Ok(s) => s,
+            Err(e) => return Err(LibertyParseError::other(format!("{}", e))
+                                 .with_path(path))
+        };
+        Liberty::parse_u8slice(&s).map_err(|e| e.with_path(path))
+    }
+
+    /// Find a pin or bus of a cell.
+    #[inline]
+    pub fn find_pin(
+        &self, macro_name: &str, pin_name: &str
+    ) -> Option<&LibertyPin> {
+        self.cells.get(macro_name)?.pins.iter()
+            .find(|p| p.name == pin_name)
+    }
+}
@@ -0,0 +1,203 @@
+use super::*;
+use nom::{
+    error::{ ErrorKind, ParseError, ContextError, context },
+    combinator::{ map, opt, cut },
+    branch::alt,
+    multi::{ many0, separated_list0 },
+    sequence::{ delimited, pair, preceded, terminated, tuple },
+    // we do not plan to stream liberty. so we
+    // only include completes.
+    character::complete::{ char, multispace0 },
+    bytes::complete::{ tag, take_until, take_till, take_till1, is_not },
+};
+
+/// the nom error type, which records the expected character and
+/// the innermost construct being parsed.
+///
+/// it is created frequently when backtracking, so it must not
+/// allocate.
+#[derive(Debug)]
+pub(crate) struct NomError<I> {
+    input: I,
+    code: ErrorKind,
+    expected_char: Option<char>,
+    construct: Option<&'static str>,
+}
+
+impl<I> ParseError<I> for NomError<I> {
+    fn from_error_kind(input: I, code: ErrorKind) -> Self {
+        NomError { input, code, expected_char: None, construct: None }
+    }
+
+    fn append(_: I, _: ErrorKind, other: Self) -> Self {
+        other
+    }
+
+    fn from_char(input: I, c: char) -> Self {
+        NomError {
+            expected_char: Some(c),
+            ..NomError::from_error_kind(input, ErrorKind::Char)
+        }
+    }
+}
+
+impl<I> ContextError<I> for NomError<I> {
+    fn add_context(_: I, ctx: &'static str, mut other: Self) -> Self {
+        if other.construct.is_none() {
+            other.construct = Some(ctx);
+        }
+        other
+    }
+}
+
+type IResult<I, O> = nom::IResult<I, O, NomError<I>>;
+
+fn u82str_lossy(i: &[u8]) -> CompactString {
+    CompactString::from(String::from_utf8_lossy(i))
+}
+
+/// comment: `/* */` or `//` until the line end.
+/// line continuations (`\` before a line end) are also
+/// regarded as whitespaces.
+pub fn skip_whitespace_and_comment(mut i: &[u8]) -> IResult<&[u8], ()> {
+    (i, _) = multispace0(i)?;
+    while !i.is_empty() {
+        let r = match i[0] {
+            b'/' => alt((
+                map(pair(tag("//"), opt(is_not("\r\n"))), |_| ()),
+                map(tuple((tag("/*"), take_until("*/"), tag("*/"))), |_| ())
+            ))(i) as IResult<&[u8], ()>,
+            b'\\' => map(tuple((char('\\'), take_till(|c| {
+                c == b'\n' || !(c as char).is_whitespace()
+            }), char('\n'))), |_| ())(i),
+            _ => break
+        };
+        i = match r {
+            Ok((i, ())) => i,
+            Err(_) => break
+        };
+        (i, _) = multispace0(i)?;
+    }
+    Ok((i, ()))
+}
+
+/// a higher-order parser transforming a parser to one that
+/// eats up all spaces.
+pub fn ws<'a, F, O>(inner: F) ->
+impl FnMut(&'a [u8]) -> IResult<&'a [u8], O>
+where F: FnMut(&'a [u8]) -> IResult<&'a [u8], O> {
+    delimited(skip_whitespace_and_comment,
+              inner,
+              skip_whitespace_and_comment)
+}
+
+/// a quoted string, without the quotes.
+fn quoted(i: &[u8]) -> IResult<&[u8], CompactString> {
+    map(delimited(char('"'), take_till(|c| c == b'"'), cut(char('"'))),
+        u82str_lossy)(i)
+}
+
+/// group type and attribute names.
+fn name(i: &[u8]) -> IResult<&[u8], CompactString> {
+    map(take_till1(|c| {
+        (c as char).is_whitespace() || b":;(){},\"\\".contains(&c)
+    }), u82str_lossy)(i)
+}
+
+/// an argument of a group or a complex attribute.
+fn arg(i: &[u8]) -> IResult<&[u8], CompactString> {
+    alt((
+        quoted,
+        map(take_till1(|c| {
+            (c as char).is_whitespace() || b";(){},\"\\".contains(&c)
+        }), u82str_lossy)
+    ))(i)
+}
+
+/// the value of a simple attribute. unquoted values extend to
+/// the line end, as they can be expressions with spaces.
+fn simple_value(i: &[u8]) -> IResult<&[u8], CompactString> {
+    alt((
+        quoted,
+        map(take_till1(|c| b";{}\r\n".contains(&c)),
+            |s: &[u8]| u82str_lossy(s.trim_ascii_end()))
+    ))(i)
+}
+
+/// a parsed statement in a group body.
+enum Statement {
+    Attr(CompactString, LibertyValue),
+    Group(LibertyGroup)
+}
+
+fn statement(i: &[u8]) -> IResult<&[u8], Statement> {
+    let (i, kind) = ws(name)(i)?;
+    // simple attribute
+    let (i, simple) = opt(context("attribute", preceded(
+        ws(char(':')),
+        cut(terminated(ws(simple_value), opt(ws(char(';')))))
+    )))(i)?;
+    if let Some(v) = simple {
+        return Ok((i, Statement::Attr(kind, LibertyValue::Simple(v))))
+    }
+    // group or complex attribute
+    let (i, (args, body)) = context("group", pair(
+        delimited(ws(char('(')),
+                  cut(separated_list0(char(','), ws(arg))),
+                  cut(ws(char(')')))),
+        cut(alt((
+            map(delimited(ws(char('{')),
+                          many0(statement),
+                          cut(ws(char('}')))), Some),
+            map(opt(ws(char(';'))), |_| None)
+        )))
+    ))(i)?;
+    let Some(body) = body else {
+        return Ok((i, Statement::Attr(kind, LibertyValue::Complex(args))))
+    };
+    let mut group = LibertyGroup {
+        kind, args, attrs: Vec::new(), groups: Vec::new()
+    };
+    for s in body {
+        match s {
+            Statement::Attr(n, v) => group.attrs.push((n, v)),
+            Statement::Group(g) => group.groups.push(g)
+        }
+    }
+    Ok((i, Statement::Group(group)))
+}
+
+/// convert a nom error to a structured one.
+fn convert_error(source: &[u8], e: nom::Err<NomError<&[u8]>>) -> LibertyParseError {
+    match e {
+        nom::Err::Incomplete(_) => LibertyParseError::at(
+            source, source.len(), None, "unexpected end of input".into()),
+        nom::Err::Error(e) | nom::Err::Failure(e) => {
+            // the errors are reported after the spaces and comments.
+            let input = skip_whitespace_and_comment(e.input)
+                .map_or(e.input, |(i, _)| i);
+            let offset = source.len() - input.len();
+            let message = match (e.expected_char, e.code) {
+                (Some(c), _) => format!("expected `{}`", c),
+                _ if input.is_empty() => "unexpected end of input".into(),
+                (_, ErrorKind::Complete) => "unexpected input".into(),
+                (_, code) => format!("unexpected input ({})", code.description())
+            };
+            LibertyParseError::at(source, offset, e.construct, message)
+        }
+    }
+}
+
+pub(crate) fn parse_liberty(i: &[u8]) -> Result<LibertyGroup, LibertyParseError> {
+    let (rem, s) = statement(i).map_err(|e| convert_error(i, e))?;
+    match s {
+        Statement::Group(group) if rem.is_empty() => Ok(group),
+        Statement::Group(_) => Err(convert_error(i, nom::Err::Error(
+            NomError::from_error_kind(rem, ErrorKind::Complete)))),
+        Statement::Attr(..) => {
+            let start = skip_whitespace_and_comment(i).map_or(i, |(j, _)| j);
+            Err(LibertyParseError::at(
+                i, i.len() - start.len(), None, "expected a group".into()))
+        }
+    }
+}
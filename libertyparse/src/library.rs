@@ -0,0 +1,269 @@
+//! Interpretation of the Liberty syntax tree.
+
+use super::*;
+
+impl LibertyGroup {
+    /// Get the last value of an attribute.
+    #[inline]
+    pub fn attr(&self, name: &str) -> Option<&LibertyValue> {
+        self.attrs.iter().rev()
+            .find(|(n, _)| n.as_str() == name)
+            .map(|(_, v)| v)
+    }
+
+    /// Get the last value of a simple attribute.
+    #[inline]
+    pub fn simple_attr(&self, name: &str) -> Option<&CompactString> {
+        match self.attr(name)? {
+            LibertyValue::Simple(s) => Some(s),
+            LibertyValue::Complex(_) => None
+        }
+    }
+
+    /// Get the sub-groups of a type.
+    #[inline]
+    pub fn groups_of<'a>(
+        &'a self, kind: &'a str
+    ) -> impl Iterator<Item = &'a LibertyGroup> + 'a {
+        self.groups.iter().filter(move |g| g.kind == kind)
+    }
+
+    fn float_attr(&self, name: &str) -> Result<Option<f64>, String> {
+        self.simple_attr(name).map(|s| parse_float(s)).transpose()
+    }
+
+    fn int_attr(&self, name: &str) -> Result<Option<isize>, String> {
+        self.simple_attr(name).map(|s| s.parse::<isize>().map_err(|_| {
+            format!("bad integer {:?} for {}", s, name)
+        })).transpose()
+    }
+
+    /// the first group argument, which is usually a name.
+    fn name_arg(&self) -> Result<&CompactString, String> {
+        self.args.first().ok_or_else(|| {
+            format!("{} group without a name", self.kind)
+        })
+    }
+}
+
+fn parse_float(s: &str) -> Result<f64, String> {
+    s.trim().parse::<f64>().map_err(|_| format!("bad number {:?}", s))
+}
+
+/// parse a list of numbers like `"0.1, 0.2"`.
+fn parse_floats(s: &str) -> Result<Vec<f64>, String> {
+    s.split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|s| !s.is_empty())
+        .map(parse_float)
+        .collect()
+}
+
+/// parse a complex attribute of numbers like
+/// `("0.1, 0.2", "0.3, 0.4")` into rows.
+fn parse_rows(v: &LibertyValue) -> Result<Vec<Vec<f64>>, String> {
+    match v {
+        LibertyValue::Simple(s) => Ok(vec![parse_floats(s)?]),
+        LibertyValue::Complex(rows) => rows.iter()
+            .map(|s| parse_floats(s))
+            .collect()
+    }
+}
+
+/// parse the indices `index_1`, `index_2`, ... of a table
+/// or template.
+fn parse_indices(g: &LibertyGroup) -> Result<Vec<Vec<f64>>, String> {
+    let mut indices = Vec::new();
+    for i in 1.. {
+        let Some(v) = g.attr(&format!("index_{}", i)) else { break };
+        indices.push(parse_rows(v)?.concat());
+    }
+    Ok(indices)
+}
+
+/// split a bus bit name like `D[3]` or `D[3:0]` into
+/// the indices.
+fn parse_bit_name(name: &str) -> Option<SVerilogRange> {
+    let (_, idx) = name.strip_suffix(']')?.split_once('[')?;
+    let (l, r) = idx.split_once(':').unwrap_or((idx, idx));
+    Some(SVerilogRange(l.trim().parse().ok()?, r.trim().parse().ok()?))
+}
+
+fn parse_direction(s: &str) -> Result<Direction, String> {
+    Ok(match s {
+        "input" => Direction::I,
+        "output" => Direction::O,
+        "inout" => Direction::InOut,
+        "internal" => Direction::Unknown,
+        _ => return Err(format!("bad pin direction {:?}", s))
+    })
+}
+
+/// the attributes interpreted in a pin.
+const PIN_ATTRS: [&str; 4] = ["direction", "capacitance", "function", "bus_type"];
+
+impl LibertyTable {
+    fn from_group(g: &LibertyGroup) -> Result<LibertyTable, String> {
+        Ok(LibertyTable {
+            kind: g.kind.clone(),
+            template: g.args.first().cloned(),
+            indices: parse_indices(g)?,
+            values: match g.attr("values") {
+                Some(v) => parse_rows(v)?,
+                None => Vec::new()
+            }
+        })
+    }
+}
+
+/// collect all sub-groups with a `values` attribute as tables.
+fn parse_tables(g: &LibertyGroup) -> Result<Vec<LibertyTable>, String> {
+    g.groups.iter()
+        .filter(|t| t.attr("values").is_some())
+        .map(LibertyTable::from_group)
+        .collect()
+}
+
+impl LibertyPin {
+    /// interpret a pin or bus group, with the bus width and
+    /// the attributes inherited from the bus.
+    fn from_group(
+        g: &LibertyGroup, name: CompactString,
+        width: Option<SVerilogRange>, parent: Option<&LibertyPin>
+    ) -> Result<LibertyPin, String> {
+        let direction = match g.simple_attr("direction") {
+            Some(d) => parse_direction(d)?,
+            None => parent.map(|p| p.direction).unwrap_or(Direction::Unknown)
+        };
+        let mut pin = LibertyPin {
+            name, width, direction,
+            capacitance: match g.float_attr("capacitance")? {
+                Some(c) => Some(c),
+                None => parent.and_then(|p| p.capacitance)
+            },
+            function: g.simple_attr("function").cloned()
+                .or_else(|| parent.and_then(|p| p.function.clone())),
+            timings: Vec::new(),
+            internal_powers: Vec::new(),
+            bits: Vec::new(),
+            attrs: g.attrs.iter()
+                .filter(|(n, _)| !PIN_ATTRS.contains(&n.as_str()))
+                .cloned().collect()
+        };
+        for t in g.groups_of("timing") {
+            pin.timings.push(LibertyTiming {
+                related_pin: t.simple_attr("related_pin").cloned(),
+                timing_sense: t.simple_attr("timing_sense").cloned(),
+                timing_type: t.simple_attr("timing_type").cloned(),
+                tables: parse_tables(t)?
+            });
+        }
+        for p in g.groups_of("internal_power") {
+            pin.internal_powers.push(LibertyInternalPower {
+                related_pin: p.simple_attr("related_pin").cloned(),
+                tables: parse_tables(p)?
+            });
+        }
+        Ok(pin)
+    }
+}
+
+/// find the range of a bus type, defined in the cell or
+/// the library.
+fn bus_type_range(
+    type_name: &str, scopes: &[&LibertyGroup]
+) -> Result<SVerilogRange, String> {
+    let t = scopes.iter()
+        .flat_map(|s| s.groups_of("type"))
+        .find(|t| t.args.first().map(|n| n.as_str()) == Some(type_name))
+        .ok_or_else(|| format!("bus type {} not found", type_name))?;
+    match (t.int_attr("bit_from")?, t.int_attr("bit_to")?) {
+        (Some(from), Some(to)) => Ok(SVerilogRange(from, to)),
+        _ => {
+            let width = t.int_attr("bit_width")?.ok_or_else(|| {
+                format!("bus type {} without width", type_name)
+            })?;
+            match t.simple_attr("downto").map(|s| s.as_str()) {
+                Some("false") => Ok(SVerilogRange(0, width - 1)),
+                _ => Ok(SVerilogRange(width - 1, 0))
+            }
+        }
+    }
+}
+
+impl LibertyCell {
+    fn from_group(g: &LibertyGroup, lib: &LibertyGroup) -> Result<LibertyCell, String> {
+        let mut cell = LibertyCell {
+            name: g.name_arg()?.clone(),
+            area: g.float_attr("area")?,
+            pins: Vec::new(),
+            attrs: g.attrs.iter()
+                .filter(|(n, _)| n.as_str() != "area")
+                .cloned().collect()
+        };
+        for p in &g.groups {
+            match p.kind.as_str() {
+                "pin" => for name in &p.args {
+                    cell.pins.push(LibertyPin::from_group(
+                        p, name.clone(), None, None)?);
+                },
+                "bus" => {
+                    let bus_type = p.simple_attr("bus_type").ok_or_else(|| {
+                        format!("bus {}/{:?} without bus_type",
+                                cell.name, p.args)
+                    })?;
+                    let width = bus_type_range(bus_type, &[g, lib])?;
+                    for name in &p.args {
+                        let mut bus = LibertyPin::from_group(
+                            p, name.clone(), Some(width), None)?;
+                        for b in p.groups_of("pin") {
+                            for bit_name in &b.args {
+                                let range = parse_bit_name(bit_name).ok_or_else(|| {
+                                    format!("bad bus bit {}/{}", cell.name, bit_name)
+                                })?;
+                                let bit = LibertyPin::from_group(
+                                    b, bit_name.clone(), None, Some(&bus))?;
+                                bus.bits.extend(range.map(|i| (i, bit.clone())));
+                            }
+                        }
+                        cell.pins.push(bus);
+                    }
+                }
+                _ => {}
+            }
+        }
+        Ok(cell)
+    }
+}
+
+impl Liberty {
+    /// Interpret a parsed `library` group.
+    pub fn from_group(g: &LibertyGroup) -> Result<Liberty, String> {
+        if g.kind != "library" {
+            return Err(format!("expected a library group, found {}", g.kind))
+        }
+        let mut lib = Liberty {
+            name: g.name_arg()?.clone(),
+            attrs: g.attrs.clone(),
+            templates: HashMap::new(),
+            cells: HashMap::new()
+        };
+        for t in g.groups.iter().filter(|t| t.kind.ends_with("_template")) {
+            let variables = (1..)
+                .map_while(|i| t.simple_attr(&format!("variable_{}", i)).cloned())
+                .collect();
+            lib.templates.insert(t.name_arg()?.clone(), LibertyTemplate {
+                variables, indices: parse_indices(t)?
+            });
+        }
+        for c in g.groups_of("cell") {
+            let cell = LibertyCell::from_group(c, g)?;
+            if lib.cells.contains_key(&cell.name) {
+                clilog::warn!(LIB_DUP_CELL, "duplicate cell {} in library {}, \
+                                            using the last one",
+                              cell.name, lib.name);
+            }
+            lib.cells.insert(cell.name.clone(), cell);
+        }
+        Ok(lib)
+    }
+}
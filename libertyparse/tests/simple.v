@@ -0,0 +1,10 @@
+module top (pad, d, q);
+   inout pad;
+   input [3:0] d;
+   output [3:0] q;
+   wire   ck, n1;
+
+   PAD p0 ( .PAD(pad), .I(n1), .C(ck) );
+   NAND2 u0 ( .A(ck), .B(d[0]), .Y(n1) );
+   REG4 r0 ( .CK(ck), .D(d), .Q(q) );
+endmodule
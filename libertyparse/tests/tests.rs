@@ -0,0 +1,131 @@
+//! unit tests for libertyparse
+
+use libertyparse::*;
+use netlistdb::{NetlistDB, Direction, LeafPinProvider};
+use sverilogparse::SVerilogRange;
+
+const LIBERTY_SIMPLE: &str = include_str!("simple.lib");
+
+#[test]
+fn test_simple() {
+    clilog::init_stdout_simple_trace();
+    let lib = Liberty::parse_str(LIBERTY_SIMPLE).expect("parse error");
+    assert_eq!(lib.name, "simple");
+    assert_eq!(lib.cells.len(), 3);
+    assert!(lib.attrs.contains(&(
+        "capacitive_load_unit".into(),
+        LibertyValue::Complex(vec!["1".into(), "pf".into()]))));
+    assert_eq!(lib.templates["delay_2x2"].variables,
+               ["input_net_transition", "total_output_net_capacitance"]);
+    assert_eq!(lib.templates["power_2"].indices, [[0.01, 0.1]]);
+
+    // pins are kept in the order they appear, with their directions.
+    let pins_of = |cell: &str| lib.cells[cell].pins.iter()
+        .map(|p| (p.name.as_str(), p.direction))
+        .collect::<Vec<_>>();
+    assert_eq!(pins_of("NAND2"), [
+        ("A", Direction::I), ("B", Direction::I), ("Y", Direction::O)]);
+    assert_eq!(pins_of("REG4"), [
+        ("CK", Direction::I), ("D", Direction::I), ("Q", Direction::O)]);
+    assert_eq!(pins_of("PAD"), [
+        ("PAD", Direction::InOut), ("I", Direction::I), ("C", Direction::O)]);
+
+    let nand2 = &lib.cells["NAND2"];
+    assert_eq!(nand2.area, Some(1.5));
+    assert_eq!(nand2.pins[1].capacitance, Some(0.002));
+    let y = &nand2.pins[2];
+    assert_eq!(y.function.as_deref(), Some("!(A & B)"));
+    assert_eq!(y.timings.len(), 1);
+    let timing = &y.timings[0];
+    assert_eq!(timing.related_pin.as_deref(), Some("A"));
+    assert_eq!(timing.timing_sense.as_deref(), Some("negative_unate"));
+    assert_eq!(timing.tables[0].kind, "cell_rise");
+    assert_eq!(timing.tables[0].template.as_deref(), Some("delay_2x2"));
+    assert!(timing.tables[0].indices.is_empty());
+    assert_eq!(timing.tables[0].values, [[0.01, 0.02], [0.03, 0.04]]);
+    assert_eq!(timing.tables[1].indices, [[0.02, 0.2]]);
+    assert_eq!(y.internal_powers[0].tables[0].values, [[0.5, 0.6]]);
+
+    let q = lib.find_pin("REG4", "Q").unwrap();
+    assert_eq!(q.width, Some(SVerilogRange(3, 0)));
+    assert_eq!(q.bits.iter().map(|(i, _)| *i).collect::<Vec<_>>(), [0, 2, 1]);
+    assert_eq!(q.bits[0].1.function.as_deref(), Some("IQN"));
+    assert_eq!(q.bits[1].1.function.as_deref(), Some("IQ"));
+    assert_eq!(q.bits[1].1.capacitance, Some(0.01));
+}
+
+#[test]
+fn test_bad() {
+    clilog::init_stdout_simple_trace();
+    let e = Liberty::parse_str("library (a) { cell (b) { ").unwrap_err();
+    println!("{e}");
+    assert_eq!((e.line, e.column), (1, 26));
+    assert_eq!(e.construct, Some("group"));
+    assert_eq!(e.message, "expected `}`");
+    let e = Liberty::parse_str("library (a) {\n  cell (b) {\n    pin (c, d {\n").unwrap_err();
+    println!("{e}");
+    assert_eq!((e.line, e.column), (3, 15));
+    assert_eq!(e.message, "expected `)`");
+    // errors interpreting the library are not located.
+    let e = Liberty::parse_str("cell (b) { }").unwrap_err();
+    assert_eq!(e.line, 0);
+    assert!(Liberty::parse_str(
+        "library (a) { cell (b) { pin (c) { direction : sideways ; } } }").is_err());
+    assert!(Liberty::parse_str(
+        "library (a) { cell (b) { bus (c) { bus_type : nope ; } } }").is_err());
+}
+
+#[test]
+fn test_netlistdb() {
+    clilog::init_stdout_simple_trace();
+    let lib = Liberty::parse_str(LIBERTY_SIMPLE).expect("parse error");
+    assert_eq!(lib.direction_of(&"REG4".into(), &"Q".into(), Some(1)), Direction::O);
+    assert_eq!(lib.direction_of(&"REG4".into(), &"IQ".into(), None), Direction::Unknown);
+    assert_eq!(lib.width_of(&"REG4".into(), &"D".into()), Some(SVerilogRange(3, 0)));
+    assert_eq!(lib.width_of(&"NAND2".into(), &"A".into()), None);
+
+    let db = NetlistDB::from_sverilog_file(
+        concat!(env!("CARGO_MANIFEST_DIR"), "/tests/simple.v"),
+        None, &lib
+    ).expect("error building netlistdb");
+    println!("The database: {db:#?}");
+    assert_eq!(db.num_cells, 4);
+    assert_eq!(db.num_pins, 9 + 3 + 3 + 9);
+    assert!(db.pindirect.iter().all(|d| *d != Direction::Unknown));
+
+    // positional connections follow the pin order in the library.
+    assert_eq!(lib.ordered_pins_of(&"NAND2".into()).unwrap(), ["A", "B", "Y"]);
+    let db2 = NetlistDB::from_sverilog_source("
+module top (pad, d, q);
+   inout pad;
+   input [3:0] d;
+   output [3:0] q;
+   wire   ck, n1;
+
+   PAD p0 ( pad, n1, ck );
+   NAND2 u0 ( ck, d[0], n1 );
+   REG4 r0 ( ck, d, q );
+endmodule
+", None, &lib).expect("error building netlistdb");
+    assert_eq!(db2.pinnames, db.pinnames);
+    assert_eq!(db2.pin2net, db.pin2net);
+    assert_eq!(db2.pindirect, db.pindirect);
+}
+
+#[test]
+fn test_parse_file_error() {
+    clilog::init_stdout_simple_trace();
+    let path = std::env::temp_dir().join("libertyparse_test_parse_file_error.lib");
+    std::fs::write(&path, "library (a) {\n  cell (b) {\n    area : 1 \n  }\n").unwrap();
+    let e = Liberty::parse_file(&path).unwrap_err();
+    std::fs::remove_file(&path).unwrap();
+    println!("{e}");
+    assert_eq!(e.path.as_deref(), Some(path.as_path()));
+    assert_eq!((e.line, e.column), (5, 1));
+    assert_eq!(e.message, "expected `}`");
+    assert!(format!("{e}").contains(&format!("{}:5:1", path.display())));
+
+    let e = Liberty::parse_file("no/such/file.lib").unwrap_err();
+    assert_eq!(e.path.as_deref(), Some(std::path::Path::new("no/such/file.lib")));
+    assert_eq!(e.line, 0);
+}
@@ -6,3 +6,4 @@ typedef u8 Direction;
 constexpr Direction Direction_I = 0;
 constexpr Direction Direction_O = 1;
 constexpr Direction Direction_Unknown = 2;
+constexpr Direction Direction_InOut = 3;
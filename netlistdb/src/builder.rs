@@ -1,7 +1,6 @@
 use super::*;
 use sverilogparse::*;
 use either::Either;
-use std::borrow::Cow;
 use rayon::prelude::*;
 
 /// Leaf pin direction and width provider trait.
@@ -15,6 +14,9 @@ use rayon::prelude::*;
 pub trait LeafPinProvider {
     /// This function is called from NetlistDB constructor to
     /// query the direction of library cell pins.
+    ///
+    /// Bidirectional pins (e.g., of IO pads) should give
+    /// [Direction::InOut].
     fn direction_of(
         &self,
         macro_name: &CompactString,
@@ -28,6 +30,51 @@ pub trait LeafPinProvider {
         pin_name: &CompactString
     ) -> Option<SVerilogRange>;
 
+    /// This function is called to query the ordered pin list of
+    /// a leaf macro, which is used to resolve positional
+    /// connections like `NAND2 u1 (y, a, b);`.
+    ///
+    /// A bus pin appears once. The default gives no answer.
+    ///
+    /// For LEF and Liberty libraries, this is the order of
+    /// declaration, which is only assumed to match the port
+    /// order of the verilog cell models. Neither format
+    /// guarantees that.
+    #[inline]
+    fn ordered_pins_of(
+        &self,
+        _macro_name: &CompactString
+    ) -> Option<Vec<CompactString>> {
+        None
+    }
+
+    /// This function is called to map a bitwise operator in an
+    /// assign like `assign y = a & b;` to a leaf macro, given as
+    /// the macro name and the pin names of the output and then
+    /// the operands. A synthetic cell of the macro is created
+    /// for each bit of each operator.
+    ///
+    /// The default maps the operators to the gate primitives
+    /// like `and` (see [GatePrimitive]), and gives no macro for
+    /// the conditional operator, which is then lowered into
+    /// `and`, `or` and `not` cells.
+    #[inline]
+    fn assign_op_macro(
+        &self,
+        op: SVerilogOp
+    ) -> Option<(CompactString, Vec<CompactString>)> {
+        let primitive = match op {
+            SVerilogOp::Not => GatePrimitive::Not,
+            SVerilogOp::And => GatePrimitive::And,
+            SVerilogOp::Or => GatePrimitive::Or,
+            SVerilogOp::Xor => GatePrimitive::Xor,
+            SVerilogOp::Xnor => GatePrimitive::Xnor,
+            SVerilogOp::Cond => return None
+        };
+        Some((primitive.name().into(),
+              primitive.pin_names(op.num_operands() + 1)?))
+    }
+
     /// This function allows downstream databases to specify
     /// whether there should be a warning on unspecified
     /// directions when building netlist.
@@ -112,14 +159,189 @@ impl DirectionProvider for NoDirection {
     }
 }
 
+/// A library wrapper that also gives the directions of the
+/// implicit pins of gate primitives (see [GatePrimitive]),
+/// which take precedence over the wrapped library.
+pub(crate) struct WithPrimitives<'l, L>(pub &'l L);
+
+impl<L: LeafPinProvider> WithPrimitives<'_, L> {
+    /// Whether a pin is an implicit pin of a gate primitive,
+    /// and whether it is an output.
+    #[inline]
+    fn primitive_pin(
+        macro_name: &CompactString, pin_name: &CompactString, pin_idx: Option<isize>
+    ) -> Option<bool> {
+        match pin_idx {
+            None => GatePrimitive::from_name(macro_name)?.is_output_pin(pin_name),
+            Some(_) => None
+        }
+    }
+}
+
+impl<L: LeafPinProvider> LeafPinProvider for WithPrimitives<'_, L> {
+    #[inline]
+    fn direction_of(
+        &self,
+        macro_name: &CompactString,
+        pin_name: &CompactString, pin_idx: Option<isize>
+    ) -> Direction {
+        match Self::primitive_pin(macro_name, pin_name, pin_idx) {
+            Some(true) => Direction::O,
+            Some(false) => Direction::I,
+            None => self.0.direction_of(macro_name, pin_name, pin_idx)
+        }
+    }
+
+    #[inline]
+    fn width_of(
+        &self,
+        macro_name: &CompactString,
+        pin_name: &CompactString
+    ) -> Option<SVerilogRange> {
+        match Self::primitive_pin(macro_name, pin_name, None) {
+            Some(_) => None,
+            None => self.0.width_of(macro_name, pin_name)
+        }
+    }
+
+    #[inline]
+    fn ordered_pins_of(
+        &self,
+        macro_name: &CompactString
+    ) -> Option<Vec<CompactString>> {
+        self.0.ordered_pins_of(macro_name)
+    }
+
+    #[inline]
+    fn assign_op_macro(
+        &self,
+        op: SVerilogOp
+    ) -> Option<(CompactString, Vec<CompactString>)> {
+        self.0.assign_op_macro(op)
+    }
+
+    #[inline]
+    fn should_warn_missing_directions(&self) -> bool {
+        self.0.should_warn_missing_directions()
+    }
+}
+
+/// Resolve the positional connections of a cell to named ones,
+/// against the port order of the submodule, the implicit pins
+/// of a gate primitive, or the ordered pins of the leaf macro
+/// given by the library.
+fn resolve_ordered_ioports<'c, S: AsRef<str>>(
+    modules: &Modules<S>,
+    cell: &'c SVerilogCell<S>,
+    macro_name: &CompactString,
+    lib: &impl LeafPinProvider
+) -> Option<Vec<(CompactString, &'c Wirexpr<S>)>> {
+    let names = match (modules.get(macro_name.as_str()), cell.primitive()) {
+        (Some((m, _)), _) => m.ports.iter().map(|port| match port {
+            SVerilogPortDef::Basic(name) => name.as_ref().into(),
+            SVerilogPortDef::Conn(name, _) => name.as_ref().into()
+        }).collect(),
+        (None, Some(primitive)) => match primitive.pin_names(
+            cell.ordered_ioports.len()
+        ) {
+            Some(names) => names,
+            None => {
+                clilog::error!(
+                    NL_SV_ORDER, "gate primitive {} cannot have {} \
+                                  terminals, in cell {}.",
+                    primitive.name(), cell.ordered_ioports.len(),
+                    cell.cell_name.as_ref());
+                return None
+            }
+        },
+        (None, None) => match lib.ordered_pins_of(macro_name) {
+            Some(names) => names,
+            None => {
+                clilog::error!(
+                    NL_SV_ORDER, "the pin order of macro {} is unknown, \
+                                  required for positional connections \
+                                  of cell {}.",
+                    macro_name, cell.cell_name.as_ref());
+                return None
+            }
+        }
+    };
+    if cell.ordered_ioports.len() > names.len() {
+        clilog::error!(
+            NL_SV_ORDER, "cell {} has {} positional connections, \
+                          but macro {} has only {} pins.",
+            cell.cell_name.as_ref(), cell.ordered_ioports.len(),
+            macro_name, names.len());
+        return None
+    }
+    Some(names.into_iter().zip(&cell.ordered_ioports)
+         .filter_map(|(name, expr)| Some((name, expr.as_ref()?)))
+         .collect())
+}
+
+fn pin_assign_literal(net_sets: &mut DisjointSet, l: usize, c: u8) {
+    assert!(c < 4);
+    net_sets.set_value(l, c);
+}
+
+/// A bit of an assign expression being lowered.
+#[derive(Clone, Copy)]
+enum LoweredBit {
+    Const(u8),
+    Pin(usize),
+}
+
+/// Connect two bits of assign expressions.
+#[must_use]
+fn connect_lowered_bits(
+    net_sets: &mut DisjointSet, a: LoweredBit, b: LoweredBit
+) -> Option<()> {
+    use LoweredBit::*;
+    match (a, b) {
+        (Pin(a), Pin(b)) => net_sets.merge(a, b),
+        (Pin(p), Const(c)) | (Const(c), Pin(p)) => {
+            pin_assign_literal(net_sets, p, c);
+        }
+        (Const(_), Const(_)) => {
+            clilog::error!(NL_SV_LIT, "Bad lit-lit assign.");
+            return None
+        }
+    }
+    Some(())
+}
+
+/// The state of lowering the operators in an assign into
+/// synthetic cells.
+///
+/// The cells are named like `$assign$2$0` for the first cell of
+/// the third assign in a module.
+struct AssignLowering<'m> {
+    hier: &'m HierName,
+    hier_prev: Option<Arc<HierName>>,
+    assign_idx: usize,
+    num_cells: usize,
+    attrs: SVerilogAttrs,
+}
+
+impl AssignLowering<'_> {
+    fn next_cell_name(&mut self) -> HierName {
+        let name = format!("$assign${}${}", self.assign_idx, self.num_cells);
+        self.num_cells += 1;
+        HierName {
+            prev: self.hier_prev.clone(),
+            cur: name.into()
+        }
+    }
+}
+
 impl NetlistDB {
     /// Get or insert a logic pin with hier, name and index.
     /// If exists, return index. Otherwise, add it and return it.
     #[inline]
     fn get_or_insert_logic_pin(
-        &mut self, hier: &HierName, name: &CompactString, idx: Option<isize>
+        &mut self, hier: &HierName, name: &str, idx: Option<isize>
     ) -> usize {
-        let k = (hier.clone(), name.clone(), idx);
+        let k = (hier.clone(), name.into(), idx);
         if let Some(i) = self.logicpinname2id.get(&k) {
             return *i
         }
@@ -137,9 +359,9 @@ impl NetlistDB {
     #[inline]
     #[must_use]
     fn try_find_logic_pin(
-        &self, hier: &HierName, name: &CompactString, idx: Option<isize>
+        &self, hier: &HierName, name: &str, idx: Option<isize>
     ) -> Option<usize> {
-        let k = (hier.clone(), name.clone(), idx);
+        let k = (hier.clone(), name.into(), idx);
         let r = self.logicpinname2id.get(&k);
         if r.is_none() {
             clilog::error!(
@@ -153,63 +375,125 @@ impl NetlistDB {
     /// Returns the new cell id.
     #[inline]
     fn insert_cell(
-        &mut self, hier: HierName, macro_name: CompactString
+        &mut self, hier: HierName, macro_name: CompactString,
+        params: Vec<(Option<CompactString>, CompactString)>,
+        attrs: SVerilogAttrs
     ) -> usize {
         let id = self.num_cells;
         self.num_cells += 1;
         self.cellname2id.insert(hier.clone(), id);
         self.celltypes.push(macro_name);
         self.cellnames.push(hier);
+        self.cellparams.push(params);
+        self.cellattrs.push(attrs);
+        id
+    }
+
+    /// Insert a module instance with hier name and module name.
+    /// Returns the new instance id.
+    #[inline]
+    fn insert_hier(
+        &mut self, hier: HierName, module_name: CompactString, parent: usize
+    ) -> usize {
+        let id = self.num_hiers;
+        self.num_hiers += 1;
+        self.hiername2id.insert(hier.clone(), id);
+        self.hiertypes.push(module_name);
+        self.hiernames.push(hier);
+        self.hier2parent.push(parent);
         id
     }
 
+    /// Build the maps of the module instances to their children,
+    /// leaf cells and port logic pins, after all the cells are
+    /// built.
+    #[must_use]
+    fn build_hier_maps<S: AsRef<str>>(
+        &mut self,
+        modules: &Modules<S>
+    ) -> Option<()> {
+        self.cell2hier = self.cellnames.iter()
+            .map(|name| match name.prev.as_deref() {
+                None => 0,
+                Some(prev) => self.hiername2id[prev]
+            })
+            .collect();
+        let mut children = vec![Vec::new(); self.num_hiers];
+        for h in 1..self.num_hiers {
+            children[self.hier2parent[h]].push(h);
+        }
+        self.hier2children = VecCSR::from_sets(children);
+        let mut cells = vec![Vec::new(); self.num_hiers];
+        for cellid in 1..self.num_cells {
+            cells[self.cell2hier[cellid]].push(cellid);
+        }
+        self.hier2cells = VecCSR::from_sets(cells);
+        let ports = (0..self.num_hiers).map(|h| {
+            let (m, mm) = &modules[self.hiertypes[h].as_str()];
+            let mut ports = Vec::new();
+            for port in &m.ports {
+                let name = match port {
+                    SVerilogPortDef::Basic(name) => name.as_ref(),
+                    SVerilogPortDef::Conn(name, _) => name.as_ref()
+                };
+                for w in enum_in_width(mm.port_widths.get(name).copied()) {
+                    ports.push(self.try_find_logic_pin(&self.hiernames[h], name, w)?);
+                }
+            }
+            Some(ports)
+        }).collect::<Option<Vec<_>>>()?;
+        self.hier2ports = VecCSR::from_sets(ports);
+        Some(())
+    }
+
     /// Recursively build (and flatten) the hierarchical modules.
     /// `net_sets` is the disjoint set of logic pins into nets.
     /// `hier` is the current hier name.
+    /// `def_attrs` collects the logic pins of wire definitions
+    /// with attributes, which are later merged into nets.
     /// This call will build all port pins inside it. So no need to
     /// build them outside.
     #[must_use]
-    fn build_modules(
+    fn build_modules<'m, S: AsRef<str>>(
         &mut self,
-        modules: &HashMap<CompactString, (SVerilogModule, ModuleMap)>,
-        (_m_name, m, mm): (&CompactString, &SVerilogModule, &ModuleMap),
+        modules: &'m Modules<'m, S>,
+        (_m_name, m, mm): (&str, &'m SVerilogModule<S>, &'m ModuleMap<'m>),
         net_sets: &mut DisjointSet,
         hier: HierName,
-        lib: &impl LeafPinProvider
+        lib: &impl LeafPinProvider,
+        def_attrs: &mut Vec<(usize, SVerilogAttrs)>
     ) -> Option<()> {
         // create nets/IO logic pins
         for def in &m.defs {
-            for w in enum_in_width(def.width) {
-                let id = self.get_or_insert_logic_pin(&hier, &def.name, w);
+            // wired-logic nets need resolution functions that
+            // we do not model.
+            if matches!(def.typ, WireDefType::Wand | WireDefType::Wor) {
+                clilog::error!(
+                    NL_SV_NETTYPE, "unsupported net type {} of {}/{}.",
+                    def.typ, hier, def.name.as_ref());
+                return None
+            }
+            let attrs = own_attrs(&def.attrs);
+            for w in enum_in_width(def_flat_width(def)) {
+                let id = self.get_or_insert_logic_pin(&hier, def.name.as_ref(), w);
                 self.logicpintypes[id] = LogicPinType::Net;
+                if !attrs.is_empty() {
+                    def_attrs.push((id, attrs.clone()));
+                }
+                // supply nets are tied to constants.
+                if let Some(c) = def.typ.supply_value() {
+                    pin_assign_literal(net_sets, id, c);
+                }
                 // for the top module, the ports are tagged as
                 // LogicPinType::TopPort outside the first invocation.
                 // (in [NetlistDB::from_sverilog]).
             }
         }
 
-        #[must_use]
-        fn pin_assign_literal(
-            net_sets: &mut DisjointSet, l: usize, c: u8
-        ) -> Option<()> {
-            match c {
-                0 => net_sets.set_value(l, false),
-                1 => net_sets.set_value(l, true),
-                2 => {
-                    clilog::warn!(NL_SV_LIT, "X literal unsupported, treating as 0");
-                    // return None
-                    net_sets.set_value(l, false);
-                },
-                3 => {},
-                _ => unreachable!()
-            }
-            Some(())
-        }
-
         // create named ports
         for (name, expr) in m.ports.iter().filter_map(|p| match p {
             SVerilogPortDef::Basic(_) => None,
-            SVerilogPortDef::Conn(name, expr) => Some((name, expr))
+            SVerilogPortDef::Conn(name, expr) => Some((name.as_ref(), expr))
         }) {
             let width = mm.port_widths.get(name).copied();
             for (id, eb) in enum_in_width(width).zip(mm.eval_expr(expr)) {
@@ -217,11 +501,11 @@ impl NetlistDB {
                 use ExprBit::*;
                 match eb {
                     Const(c) => {
-                        pin_assign_literal(net_sets, port_id, c)?;
+                        pin_assign_literal(net_sets, port_id, c);
                     },
                     Var(pname, pidx) => {
                         let pin_id = self.try_find_logic_pin(
-                            &hier, &pname, pidx)?;
+                            &hier, pname, pidx)?;
                         net_sets.merge(port_id, pin_id);
                     }
                 }
@@ -234,40 +518,61 @@ impl NetlistDB {
         };
 
         // recurse into submodules and cells
-        for cell in &m.cells {
+        let hierid = self.hiername2id[&hier];
+        for (cellidx, cell) in m.cells.iter().enumerate() {
             let new_hier = HierName {
                 prev: hier_prev.clone(),
-                cur: cell.cell_name.clone()
+                cur: match mm.gate_names.get(&cellidx) {
+                    Some(name) => name.clone(),
+                    None => cell.cell_name.as_ref().into()
+                }
+            };
+            let macro_name: CompactString = cell.macro_name.as_ref().into();
+            let ordered_ioports = match cell.ordered_ioports.is_empty() {
+                true => Vec::new(),
+                false => resolve_ordered_ioports(modules, cell, &macro_name, lib)?
+            };
+            let ioports = || match cell.ordered_ioports.is_empty() {
+                true => Either::Left(cell.ioports.iter()
+                                     .map(|(name, expr)| (name.as_ref(), expr))),
+                false => Either::Right(ordered_ioports.iter()
+                                       .map(|(name, expr)| (name.as_str(), *expr)))
             };
 
             // build submodule/cell and get ranges of ports
-            let (is_leaf, ioport_ranges) = match modules.get(&cell.macro_name) {
+            let (is_leaf, ioport_ranges) = match modules.get(macro_name.as_str()) {
                 Some((m, mm)) => {
                     // non-leaf.
+                    self.insert_hier(new_hier.clone(), macro_name.clone(), hierid);
                     self.build_modules(
                         modules,
-                        (&cell.macro_name, m, mm),
+                        (&macro_name, m, mm),
                         net_sets,
                         new_hier.clone(),
-                        lib
+                        lib, def_attrs
                     )?;
                     (false,
-                     Either::Left(cell.ioports.iter().map(|(name, _)| {
-                         mm.port_widths.get(name.as_str()).copied()
+                     Either::Left(ioports().map(|(name, _)| {
+                         mm.port_widths.get(name).copied()
                      })))
                 },
                 None => {
                     // leaf cell.
                     self.insert_cell(new_hier.clone(),
-                                     cell.macro_name.clone());
+                                     macro_name.clone(),
+                                     cell.params.iter().map(|(n, v)| (
+                                         n.as_ref().map(|n| n.as_ref().into()),
+                                         v.as_ref().into()
+                                     )).collect(),
+                                     own_attrs(&cell.attrs));
                     (true,
-                     Either::Right(cell.ioports.iter().map(|(macro_pin_name, expr)| {
+                     Either::Right(ioports().map(|(macro_pin_name, expr)| {
                          // mimics CadXX InnoXX:
                          // a leaf cell port is a scalar iff width == 1.
                          match mm.eval_expr_len(expr) {
                              1 => None,
                              _ => {
-                                match lib.width_of(&cell.macro_name, &macro_pin_name) {
+                                match lib.width_of(&macro_name, &macro_pin_name.into()) {
                                     Some(SVerilogRange(left, right))  => Some(SVerilogRange(left, right)),
                                     _ => None
                                 }
@@ -278,26 +583,26 @@ impl NetlistDB {
             };
 
             // connect edges.
-            for (w, (name, expr)) in ioport_ranges.zip(cell.ioports.iter()) {
+            for (w, (name, expr)) in ioport_ranges.zip(ioports()) {
                 for (i, eb) in enum_in_width(w).zip(mm.eval_expr(expr)) {
                     let id = match is_leaf {
                         // if it is a leaf, we insert the pin.
-                        true => self.get_or_insert_logic_pin(&new_hier, &name, i),
+                        true => self.get_or_insert_logic_pin(&new_hier, name, i),
                         // if it is a submodule, the pin should already be ready,
                         // so we assert its existence.
-                        false => self.try_find_logic_pin(&new_hier, &name, i)?
+                        false => self.try_find_logic_pin(&new_hier, name, i)?
                     };
                     if is_leaf {
                         self.logicpintypes[id] = LogicPinType::LeafCellPin;
                     }
                     match eb {
                         ExprBit::Const(c) => {
-                            pin_assign_literal(net_sets, id, c)?;
+                            pin_assign_literal(net_sets, id, c);
                         }
                         ExprBit::Var(pname, pidx) => {
                             // a wire might be used but not defined.
                             let eb_id = self.get_or_insert_logic_pin(
-                                &hier, &pname, pidx);
+                                &hier, pname, pidx);
                             let typ = &mut self.logicpintypes[eb_id];
                             if *typ == LogicPinType::Others {
                                 *typ = LogicPinType::Net;
@@ -310,9 +615,31 @@ impl NetlistDB {
         }
         
         // connect assignments
-        for assign in &m.assigns {
+        for (assign_idx, assign) in m.assigns.iter().enumerate() {
+            let Some(rhs) = assign.rhs.as_wirexpr() else {
+                // lower the operators into cells.
+                let mut lowering = AssignLowering {
+                    hier: &hier, hier_prev: hier_prev.clone(),
+                    assign_idx, num_cells: 0, attrs: own_attrs(&assign.attrs)
+                };
+                let rhs = self.lower_assign_expr(
+                    mm, &assign.rhs, &mut lowering, net_sets, lib)?;
+                let lhs = self.lowered_wire_bits(mm, &hier, &assign.lhs);
+                if lhs.len() != rhs.len() {
+                    clilog::error!(
+                        NL_SV_INCOMP,
+                        "incompatible assign width for `{}`: \
+                         len(LHS) = {}, len(RHS) = {}",
+                        assign, lhs.len(), rhs.len());
+                    return None
+                }
+                for (l, r) in lhs.into_iter().zip(rhs) {
+                    connect_lowered_bits(net_sets, l, r)?;
+                }
+                continue
+            };
             let len_lhs = mm.eval_expr_len(&assign.lhs);
-            let len_rhs = mm.eval_expr_len(&assign.rhs);
+            let len_rhs = mm.eval_expr_len(rhs);
             if len_lhs != len_rhs {
                 clilog::error!(
                     NL_SV_INCOMP,
@@ -323,22 +650,22 @@ impl NetlistDB {
             }
 
             for (lb, rb) in mm.eval_expr(&assign.lhs).zip(
-                mm.eval_expr(&assign.rhs)
+                mm.eval_expr(rhs)
             ) {
                 use ExprBit::*;
                 match (lb, rb) {
                     (Var(nl, il), Var(nr, ir)) => {
-                        let l = self.get_or_insert_logic_pin(&hier, &nl, il);
-                        let r = self.get_or_insert_logic_pin(&hier, &nr, ir);
+                        let l = self.get_or_insert_logic_pin(&hier, nl, il);
+                        let r = self.get_or_insert_logic_pin(&hier, nr, ir);
                         net_sets.merge(l, r);
                     }
                     (Var(nl, il), Const(c)) => {
-                        let l = self.get_or_insert_logic_pin(&hier, &nl, il);
-                        pin_assign_literal(net_sets, l, c)?;
+                        let l = self.get_or_insert_logic_pin(&hier, nl, il);
+                        pin_assign_literal(net_sets, l, c);
                     }
                     (Const(c), Var(nr, ir)) => {
-                        let r = self.get_or_insert_logic_pin(&hier, &nr, ir);
-                        pin_assign_literal(net_sets, r, c)?;
+                        let r = self.get_or_insert_logic_pin(&hier, nr, ir);
+                        pin_assign_literal(net_sets, r, c);
                     }
                     _ => {
                         clilog::error!(NL_SV_LIT, "Bad lit-lit assign.");
@@ -351,6 +678,140 @@ impl NetlistDB {
         Some(())
     }
 
+    /// Get the bits of a wire expression in an assign with
+    /// operators.
+    fn lowered_wire_bits<S: AsRef<str>>(
+        &mut self, mm: &ModuleMap, hier: &HierName, expr: &Wirexpr<S>
+    ) -> Vec<LoweredBit> {
+        mm.eval_expr(expr).map(|eb| match eb {
+            ExprBit::Const(c) => LoweredBit::Const(c),
+            ExprBit::Var(name, idx) => {
+                // a wire might be used but not defined.
+                let id = self.get_or_insert_logic_pin(hier, name, idx);
+                let typ = &mut self.logicpintypes[id];
+                if *typ == LogicPinType::Others {
+                    *typ = LogicPinType::Net;
+                }
+                LoweredBit::Pin(id)
+            }
+        }).collect()
+    }
+
+    /// Create a synthetic leaf cell for one bit of an assign
+    /// operator, and return its output logic pin.
+    #[must_use]
+    fn insert_assign_cell(
+        &mut self, op: SVerilogOp, inputs: &[LoweredBit],
+        lowering: &mut AssignLowering, net_sets: &mut DisjointSet,
+        lib: &impl LeafPinProvider
+    ) -> Option<usize> {
+        let Some((macro_name, pins)) = lib.assign_op_macro(op) else {
+            clilog::error!(
+                NL_SV_ASSIGN_OP,
+                "no macro is given for the operator `{}` in assign.",
+                op.symbol());
+            return None
+        };
+        if pins.len() != inputs.len() + 1 {
+            clilog::error!(
+                NL_SV_ASSIGN_OP,
+                "macro {} for the operator `{}` has {} pins, \
+                 expected {}.",
+                macro_name, op.symbol(), pins.len(), inputs.len() + 1);
+            return None
+        }
+        let name = lowering.next_cell_name();
+        self.insert_cell(name.clone(), macro_name, Vec::new(),
+                         lowering.attrs.clone());
+        let mut output = None;
+        for (pin, input) in pins.iter().zip(
+            std::iter::once(None).chain(inputs.iter().map(Some))
+        ) {
+            let id = self.get_or_insert_logic_pin(&name, pin, None);
+            self.logicpintypes[id] = LogicPinType::LeafCellPin;
+            match input {
+                None => output = Some(id),
+                Some(&input) => connect_lowered_bits(
+                    net_sets, LoweredBit::Pin(id), input)?
+            }
+        }
+        output
+    }
+
+    /// Lower an assign expression into synthetic leaf cells with
+    /// the macros given by [LeafPinProvider::assign_op_macro],
+    /// and return its bits.
+    ///
+    /// The operands of a binary operator should have the same
+    /// width, and the condition of `?:` should have one bit.
+    #[must_use]
+    fn lower_assign_expr<S: AsRef<str>>(
+        &mut self, mm: &ModuleMap, expr: &SVerilogExpr<S>,
+        lowering: &mut AssignLowering, net_sets: &mut DisjointSet,
+        lib: &impl LeafPinProvider
+    ) -> Option<Vec<LoweredBit>> {
+        let (op, operands) = match expr {
+            SVerilogExpr::Wire(w) => {
+                return Some(self.lowered_wire_bits(mm, lowering.hier, w))
+            }
+            SVerilogExpr::Op(op, operands) => (*op, operands)
+        };
+        if operands.len() != op.num_operands() {
+            clilog::error!(
+                NL_SV_ASSIGN_OP,
+                "the operator `{}` has {} operands, expected {}.",
+                op.symbol(), operands.len(), op.num_operands());
+            return None
+        }
+        let mut operands = operands.iter()
+            .map(|e| self.lower_assign_expr(mm, e, lowering, net_sets, lib))
+            .collect::<Option<Vec<_>>>()?;
+        let cond = match op {
+            SVerilogOp::Cond => {
+                let cond = operands.remove(0);
+                if cond.len() != 1 {
+                    clilog::error!(
+                        NL_SV_INCOMP,
+                        "the condition `{}` of `?:` has {} bits, \
+                         expected 1.", expr, cond.len());
+                    return None
+                }
+                Some(cond[0])
+            }
+            _ => None
+        };
+        let width = operands[0].len();
+        if operands.iter().any(|o| o.len() != width) {
+            clilog::error!(
+                NL_SV_INCOMP,
+                "the operands of `{}` have different widths in `{}`.",
+                op.symbol(), expr);
+            return None
+        }
+        use SVerilogOp::*;
+        let mut insert = |db: &mut NetlistDB, op, inputs: &[LoweredBit]| {
+            db.insert_assign_cell(op, inputs, lowering, net_sets, lib)
+                .map(LoweredBit::Pin)
+        };
+        match (cond, lib.assign_op_macro(op)) {
+            // `s ? a : b` into `(s & a) | (~s & b)`.
+            (Some(s), None) => {
+                let not_s = insert(self, Not, &[s])?;
+                (0..width).map(|i| {
+                    let a = insert(self, And, &[s, operands[0][i]])?;
+                    let b = insert(self, And, &[not_s, operands[1][i]])?;
+                    insert(self, Or, &[a, b])
+                }).collect()
+            }
+            _ => (0..width).map(|i| {
+                let inputs = cond.into_iter()
+                    .chain(operands.iter().map(|o| o[i]))
+                    .collect::<Vec<_>>();
+                insert(self, op, &inputs)
+            }).collect()
+        }
+    }
+
     /// Building a netlist database STEP 1: initialize most of the
     /// graph structure using parsed verilog modules starting from
     /// the top-level module.
@@ -360,9 +821,9 @@ impl NetlistDB {
     /// Splitting this out would possibly reduce the code bloat
     /// caused by the polymorphic DirectionProvider.
     /// (This is a premature optimization. Evil.)
-    fn init_graph_from_modules(
-        modules: &HashMap<CompactString, (SVerilogModule, ModuleMap)>,
-        (top_name, top_m, top_mm): (&CompactString, &SVerilogModule, &ModuleMap),
+    fn init_graph_from_modules<S: AsRef<str> + Sync>(
+        modules: &Modules<S>,
+        (top_name, top_m, top_mm): (&str, &SVerilogModule<S>, &ModuleMap),
         lib: &impl LeafPinProvider
     ) -> Option<NetlistDB> {
         let (est_num_cells, est_num_logic_pins) = estimate_size(
@@ -372,7 +833,7 @@ impl NetlistDB {
         let est_num_cells = est_num_cells + 1; // top level
 
         let mut db = NetlistDB {
-            name: top_name.clone(),
+            name: top_name.into(),
             num_cells: 1,
             num_pins: 0,
             num_logic_pins: 0,
@@ -384,10 +845,23 @@ impl NetlistDB {
             portname2pinid: HashMap::new(),
             celltypes: Vec::with_capacity(est_num_cells),
             cellnames: Vec::with_capacity(est_num_cells),
+            cellparams: Vec::with_capacity(est_num_cells),
+            cellattrs: Vec::with_capacity(est_num_cells),
+            cell2hier: Vec::new(),
+            num_hiers: 1,
+            hiername2id: HashMap::new(),
+            hiernames: Vec::new(),
+            hiertypes: Vec::new(),
+            hier2parent: Vec::new(),
+            hier2children: Default::default(),
+            hier2cells: Default::default(),
+            hier2ports: Default::default(),
             logicpintypes: Vec::with_capacity(est_num_logic_pins),
             logicpinnames: Vec::with_capacity(est_num_logic_pins),
+            logicpin2net: Vec::new(),
             pinid2logicpinid: Vec::new(),
             netnames: Vec::new(),
+            netattrs: Vec::new(),
             pinnames: Vec::new(),
             pin2cell: UVec::new(),
             pin2net: UVec::new(),
@@ -397,20 +871,30 @@ impl NetlistDB {
             cell2noutputs: UVec::new(),
             net_zero: None,
             net_one: None,
+            net_x: None,
+            net_z: None,
         };
 
         db.cellname2id.insert(HierName::empty(), 0);
-        db.celltypes.push(top_name.clone());
+        db.celltypes.push(top_name.into());
         db.cellnames.push(HierName::empty());
+        db.cellparams.push(Vec::new());
+        db.cellattrs.push(Vec::new());
+        db.hiername2id.insert(HierName::empty(), 0);
+        db.hiertypes.push(top_name.into());
+        db.hiernames.push(HierName::empty());
+        db.hier2parent.push(0);
 
         let mut net_sets = DisjointSet::with_capacity(est_num_logic_pins);
+        let mut def_attrs = Vec::new();
 
         let time_build_modules = clilog::stimer!("build_modules");
         db.build_modules(
             modules, (top_name, top_m, top_mm),
-            &mut net_sets, HierName::empty(), lib
+            &mut net_sets, HierName::empty(), lib, &mut def_attrs
         )?;
         clilog::finish!(time_build_modules);
+        db.build_hier_maps(modules)?;
 
         if db.num_logic_pins > est_num_logic_pins {
             clilog::warn!(
@@ -424,8 +908,8 @@ impl NetlistDB {
         // set TopPort property for top module ports.
         for port in &top_m.ports {
             let name = match port {
-                SVerilogPortDef::Basic(name) => name,
-                SVerilogPortDef::Conn(name, _) => name
+                SVerilogPortDef::Basic(name) => name.as_ref(),
+                SVerilogPortDef::Conn(name, _) => name.as_ref()
             };
             let w = top_mm.port_widths.get(name).copied();
             for w in enum_in_width(w) {
@@ -436,11 +920,18 @@ impl NetlistDB {
 
         // create net maps.
         // first finalize the disjoint set and compute the sizes.
-        let (num_nets, logicpin2nets, net_zero, net_one) =
+        let (num_nets, logicpin2nets, [net_zero, net_one, net_x, net_z]) =
             net_sets.finalize(db.num_logic_pins)?;
         db.num_nets = num_nets;
         db.net_zero = net_zero;
         db.net_one = net_one;
+        db.net_x = net_x;
+        db.net_z = net_z;
+
+        db.netattrs = vec![Vec::new(); num_nets];
+        for (logic_id, attrs) in def_attrs {
+            merge_attrs(&mut db.netattrs[logicpin2nets[logic_id]], &attrs);
+        }
 
         // finalize pin index and pin-net mapping.
         db.pinid2logicpinid = db.logicpintypes.iter()
@@ -526,18 +1017,12 @@ impl NetlistDB {
                     .collect::<HashMap<_, _>>();
                 let mut netnames = vec![(
                     HierName::empty(), CompactString::new_inline(""), None
-                ); netname2id.len()];
+                ); db.num_nets];
 
                 // find the best name for each net
                 for (netname, id) in &netname2id {
                     let current_name = &mut netnames[*id];
-                    let current_hier_depth = current_name.0.iter().count();
-                    let netname_hier_depth = netname.0.iter().count();
-                    if current_name.1.is_empty()
-                        || netname_hier_depth < current_hier_depth
-                        || (netname_hier_depth == current_hier_depth
-                            && netname.1 < current_name.1)
-                    {
+                    if is_better_net_name(netname, current_name) {
                         *current_name = netname.clone();
                     }
                 }
@@ -553,19 +1038,17 @@ impl NetlistDB {
                 let mut portname2pinid = HashMap::new();
                 for port in &top_m.ports {
                     let (name, expr) = match port {
-                        SVerilogPortDef::Basic(name) => (
-                            name, Cow::Owned(Wirexpr::Basic(
-                                WirexprBasic::Full(name.clone())
-                            ))
-                        ),
-                        SVerilogPortDef::Conn(name, expr) => (
-                            name, Cow::Borrowed(expr)
-                        )
+                        SVerilogPortDef::Basic(name) => (name.as_ref(), None),
+                        SVerilogPortDef::Conn(name, expr) => (name.as_ref(), Some(expr))
                     };
                     let width = top_mm.port_widths.get(name).copied();
-                    for (w, eb) in enum_in_width(width).zip(
-                        top_mm.eval_expr(&expr)
-                    ) {
+                    // a basic port refers to the def of the same width.
+                    let bits = match expr {
+                        None => Either::Left(enum_in_width(width)
+                                             .map(|w| ExprBit::Var(name, w))),
+                        Some(expr) => Either::Right(top_mm.eval_expr(expr))
+                    };
+                    for (w, eb) in enum_in_width(width).zip(bits) {
                         let (pname, pidx) = match eb {
                             ExprBit::Const(_) => continue,
                             ExprBit::Var(pname, pidx) => (pname, pidx)
@@ -573,7 +1056,7 @@ impl NetlistDB {
                         let id = db.try_find_logic_pin(
                             &HierName::empty(), name, w).unwrap();
                         let id = *logicpinid2pinid.get(&id).unwrap();
-                        portname2pinid.insert((pname.clone(), pidx), id);
+                        portname2pinid.insert((pname.into(), pidx), id);
                     }
                 }
                 ret_portname2pinid = Some(portname2pinid);
@@ -589,6 +1072,7 @@ impl NetlistDB {
         db.netname2id = ret_netname2id.unwrap();
         db.netnames = ret_netnames.unwrap();
         db.portname2pinid = ret_portname2pinid.unwrap();
+        db.logicpin2net = logicpin2nets;
 
         clilog::finish!(time_build_public_maps);
         
@@ -602,20 +1086,21 @@ impl NetlistDB {
     ///
     /// There should be a way to specify library pin directions --
     /// through a trait called direction provider.
-    pub fn from_sverilog(
-        sverilog_source: SVerilog,
+    ///
+    /// Both the owned [SVerilog] and the borrowed `SVerilog<&str>`
+    /// (from [SVerilog::parse_borrowed]) are accepted. The names
+    /// are only copied into the database, not into an owned AST.
+    pub fn from_sverilog<S: AsRef<str> + Sync>(
+        sverilog_source: SVerilog<S>,
         top: Option<&str>,
         direction_provider: &impl DirectionProvider
     ) -> Option<NetlistDB> {
-        let SVerilog{modules} = sverilog_source;
-        
-        let modules: HashMap<CompactString, (SVerilogModule, ModuleMap)> =
-            modules.into_iter().map(|(k, v)| {
-                let mm = ModuleMap::from(&v);
-                (k, (v, mm))
-            }).collect();
+        let modules: Modules<S> = sverilog_source.modules.iter()
+            .map(|(k, v)| (k.as_ref(), (v, ModuleMap::from(v))))
+            .collect();
         
         let (top_name, top_m, top_mm) = find_top_module(&modules, top)?;
+        let direction_provider = &WithPrimitives(direction_provider);
         
         let mut db = NetlistDB::init_graph_from_modules(
             &modules,
@@ -629,6 +1114,10 @@ impl NetlistDB {
     }
 
     /// Set the first pin of net2pin.item is driver pin
+    ///
+    /// A net can have at most one output pin, which becomes
+    /// its root. Nets without output pins but with inout pins
+    /// are rooted at the first inout pin.
     #[must_use]
     pub fn post_assign_direction(
         &mut self
@@ -644,11 +1133,16 @@ impl NetlistDB {
                 )
                 .collect::<Vec<usize>>();
             if outs.len() == 0 {
+                let first_inout = (l..r).find(|&i| {
+                    self.pindirect[self.net2pin.items[i]] == Direction::InOut
+                });
+                if let Some(p) = first_inout {
+                    self.net2pin.items.swap(l, p);
+                    continue;
+                }
                 // if this net is not intended to be constant,
                 // we report the error.
-                if Some(i) != self.net_zero &&
-                    Some(i) != self.net_one
-                {
+                if self.net_constant(i).is_none() {
                     num_undriven_nets += 1;
                 }
                 continue;
@@ -672,7 +1166,7 @@ impl NetlistDB {
         self.cell2noutputs = (0..self.num_cells)
             .map(|cellid| {
                 self.cell2pin.iter_set(cellid)
-                    .filter(|&pinid| matches!(self.pindirect[pinid], Direction::O))
+                    .filter(|&pinid| self.pindirect[pinid].is_driver())
                     .count()
             })
             .collect();
@@ -682,9 +1176,9 @@ impl NetlistDB {
     /// Building a netlist database STEP 2:
     /// Assign directions to netlist pins given the macro and the pin type.
     #[must_use]
-    fn assign_direction(
+    fn assign_direction<S: AsRef<str>>(
         &mut self,
-        (_top_name, top_m, top_mm): (&CompactString, &SVerilogModule, &ModuleMap),
+        (_top_name, top_m, top_mm): (&str, &SVerilogModule<S>, &ModuleMap),
         lib: &impl DirectionProvider
     ) -> Option<()> {
         // query the provider for cell pins
@@ -709,10 +1203,10 @@ impl NetlistDB {
             use ExprBit::*;
             let (name, ref_names) = match port {
                 SVerilogPortDef::Basic(name) => {
-                    (name, Either::Left(std::iter::repeat(name)))
+                    (name.as_ref(), Either::Left(std::iter::repeat(name.as_ref())))
                 }
                 SVerilogPortDef::Conn(name, expr) => {
-                    (name, Either::Right(
+                    (name.as_ref(), Either::Right(
                         top_mm.eval_expr(expr).map(|eb| match eb {
                             Const(_) => {
                                 clilog::error!(NL_SV_LIT, "Literal unsupported");
@@ -725,7 +1219,7 @@ impl NetlistDB {
             };
             let width = top_mm.port_widths.get(name).copied();
             for (id, ref_name) in enum_in_width(width).zip(ref_names) {
-                let k = (HierName::empty(), name.clone(), id);
+                let k = (HierName::empty(), CompactString::from(name), id);
                 let deftype = match top_mm.def_types.get(ref_name) {
                     Some(v) => v,
                     None => {
@@ -737,22 +1231,19 @@ impl NetlistDB {
                     }
                 };
                 use WireDefType::*;
-                use Direction::*;
                 let dir = match deftype {
-                    Input => O,  // input port is net output.
-                    Output => I,
-                    InOut => {
-                        clilog::warn!(NL_SV_INOUT, "inout unsupported for pin {}/{}{:?}, treating as unknown. TODO",
-                                      k.0, k.1, k.2);
-                        Unknown
-                    }
-                    Wire => {
+                    Input => Direction::O,  // input port is net output.
+                    Output => Direction::I,
+                    InOut => Direction::InOut,
+                    Wire | Tri | Supply0 | Supply1 => {
                         clilog::error!(
                             NL_SV_REF, "named port connection {} should \
                                         not refer to non-io wire {}.",
                             name, ref_name);
                         return None
                     }
+                    // rejected when building the modules.
+                    Wand | Wor => unreachable!(),
                 };
                 self.pindirect[*self.pinname2id.get(&k).unwrap()] = dir;
             }
@@ -782,10 +1273,28 @@ impl NetlistDB {
         let sverilog = match SVerilog::parse_file(&sverilog_source_path) {
             Ok(sv) => sv,
             Err(e) => {
+                // the error includes the file path.
                 clilog::error!(
-                    NL_SV_PARSE,
-                    "Parse sverilog file {} failed: {}",
-                    sverilog_source_path.as_ref().display(), e);
+                    NL_SV_PARSE, "Parse sverilog file failed:\n{}", e);
+                return None
+            }
+        };
+        NetlistDB::from_sverilog(sverilog, top, direction_provider)
+    }
+
+    /// Convenient shortcut to read from multiple files, parsed
+    /// in parallel. See [SVerilog::parse_files].
+    /// The parameters are similar to [NetlistDB::from_sverilog].
+    pub fn from_sverilog_files<P: AsRef<std::path::Path> + Sync>(
+        sverilog_source_paths: &[P],
+        top: Option<&str>,
+        direction_provider: &impl DirectionProvider
+    ) -> Option<NetlistDB> {
+        let sverilog = match SVerilog::parse_files(sverilog_source_paths) {
+            Ok(sv) => sv,
+            Err(e) => {
+                clilog::error!(
+                    NL_SV_PARSE, "Parse sverilog files failed:\n{}", e);
                 return None
             }
         };
@@ -804,7 +1313,7 @@ impl NetlistDB {
             Err(e) => {
                 clilog::error!(
                     NL_SV_PARSE,
-                    "Parse sverilog source code failed: {}", e);
+                    "Parse sverilog source code failed:\n{}", e);
                 return None
             }
         };
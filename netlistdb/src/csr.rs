@@ -1,6 +1,20 @@
 //! Compressed sparse row (CSR) implementation.
 
-use ulib::UVec;
+use ulib::{UVec, UniversalCopy};
+
+/// Edit a [UVec] through a [Vec] on CPU.
+///
+/// This is useful for operations like insertion and removal,
+/// which are not provided by [UVec].
+#[inline]
+pub(crate) fn edit_uvec<T: UniversalCopy, R>(
+    v: &mut UVec<T>, f: impl FnOnce(&mut Vec<T>) -> R
+) -> R {
+    let mut vec: Vec<T> = std::mem::take(v).into();
+    let ret = f(&mut vec);
+    *v = vec.into();
+    ret
+}
 
 /// A helper type for simple 1-layer CSR.
 #[derive(Debug, Default, Clone)]
@@ -38,6 +52,22 @@ impl VecCSR {
         }
     }
 
+    /// build CSR from the items of each set.
+    pub fn from_sets<I: IntoIterator<Item = usize>>(
+        sets: impl IntoIterator<Item = I>
+    ) -> VecCSR {
+        let mut start = vec![0];
+        let mut items = Vec::new();
+        for set in sets {
+            items.extend(set);
+            start.push(items.len());
+        }
+        VecCSR {
+            start: start.into(),
+            items: items.into()
+        }
+    }
+
     /// convenient method to get an iterator of set items.
     #[inline]
     pub fn iter_set(&self, set_id: usize)
@@ -55,4 +85,76 @@ impl VecCSR {
         let r = self.start[set_id + 1];
         r - l
     }
+
+    /// get the number of sets
+    #[inline]
+    pub fn num_sets(&self) -> usize {
+        self.start.len().saturating_sub(1)
+    }
+
+    /// insert items to a set, starting at a position relative
+    /// to the beginning of the set.
+    pub fn insert(&mut self, set_id: usize, pos: usize, new_items: &[usize]) {
+        let at = self.start[set_id] + pos;
+        assert!(at <= self.start[set_id + 1]);
+        edit_uvec(&mut self.items, |items| {
+            items.splice(at..at, new_items.iter().copied());
+        });
+        for s in self.start[set_id + 1..].iter_mut() {
+            *s += new_items.len();
+        }
+    }
+
+    /// remove an item from a set, preserving the order of the
+    /// remaining ones.
+    ///
+    /// returns false if the item is not found.
+    pub fn remove(&mut self, set_id: usize, item: usize) -> bool {
+        let l = self.start[set_id];
+        let r = self.start[set_id + 1];
+        let at = match self.items[l..r].iter().position(|&x| x == item) {
+            Some(p) => l + p,
+            None => return false
+        };
+        edit_uvec(&mut self.items, |items| {
+            items.remove(at);
+        });
+        for s in self.start[set_id + 1..].iter_mut() {
+            *s -= 1;
+        }
+        true
+    }
+
+    /// remove all items of a set, and return them.
+    pub fn take_set(&mut self, set_id: usize) -> Vec<usize> {
+        let l = self.start[set_id];
+        let r = self.start[set_id + 1];
+        let taken = edit_uvec(&mut self.items, |items| {
+            items.drain(l..r).collect::<Vec<_>>()
+        });
+        for s in self.start[set_id + 1..].iter_mut() {
+            *s -= taken.len();
+        }
+        taken
+    }
+
+    /// append a new empty set.
+    pub fn push_set(&mut self) {
+        edit_uvec(&mut self.start, |start| {
+            let last = start.last().copied().unwrap_or(0);
+            if start.is_empty() {
+                start.push(0);
+            }
+            start.push(last);
+        });
+    }
+
+    /// remove an empty set. the indices of the following sets
+    /// are decreased by one.
+    pub fn remove_set(&mut self, set_id: usize) {
+        assert_eq!(self.len(set_id), 0, "only empty sets can be removed");
+        edit_uvec(&mut self.start, |start| {
+            start.remove(set_id);
+        });
+    }
 }
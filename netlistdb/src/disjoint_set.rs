@@ -1,8 +1,11 @@
 //! Union-find set implementation for net discovery.
 
+/// the names of constants 0, 1, x, z in error messages.
+const CONST_NAMES: [char; 4] = ['0', '1', 'x', 'z'];
+
 /// A simple implementation of a union-find set (disjoint set).
-/// We extend it with the ability to track two special sets:
-/// Net-0 and Net-1.
+/// We extend it with the ability to track four special sets:
+/// Net-0, Net-1, Net-X and Net-Z.
 /// 
 /// It is expected to run in `O(log n)` time, because we do not
 /// use *union by size/rank* trick to optimize it -- this is
@@ -12,8 +15,8 @@
 /// indices are automatically added.
 pub struct DisjointSet {
     fa: Vec<usize>,
-    value_zero: Option<usize>,
-    value_one: Option<usize>
+    /// the set of constants 0, 1, x, z.
+    values: [Option<usize>; 4]
 }
 
 impl DisjointSet {
@@ -22,8 +25,7 @@ impl DisjointSet {
     pub fn with_capacity(c: usize) -> DisjointSet {
         DisjointSet{
             fa: Vec::with_capacity(c),
-            value_zero: None,
-            value_one: None
+            values: [None; 4]
         }
     }
 
@@ -52,32 +54,26 @@ impl DisjointSet {
         self.fa[a] = b;
     }
 
-    /// Set value to be zero or one
-    pub fn set_value(&mut self, a: usize, v: bool) {
-        macro_rules! cas {
-            ($($v:expr => $field:ident),+) => {
-                match v {
-                    $($v => match self.$field {
-                        None => self.$field = Some(a),
-                        Some(b) => self.merge(a, b)
-                    }),+
-                }
-            }
-        }
-        cas! {
-            false => value_zero,
-            true => value_one
+    /// Set value to be a constant.
+    ///
+    /// The value is encoded as 0, 1, x, z => 0, 1, 2, 3.
+    pub fn set_value(&mut self, a: usize, v: u8) {
+        match self.values[v as usize] {
+            None => self.values[v as usize] = Some(a),
+            Some(b) => self.merge(a, b)
         }
     }
 
     /// Finalize and output the number of sets, the set indices
-    /// of all nodes, and the zero/one set indices.
+    /// of all nodes, and the 0/1/x/z set indices.
+    ///
+    /// A set with z and another constant takes that constant,
+    /// and it is an error to connect two of 0, 1 and x.
     ///
     /// Currently, this consumes the whole disjoint set object to
     /// warn user that it is an expensive operation.
     pub fn finalize(mut self, num_nodes: usize) -> Option<(
-        usize, Vec<usize>,
-        Option<usize>, Option<usize>
+        usize, Vec<usize>, [Option<usize>; 4]
     )> {
         self.fa.truncate(num_nodes);
         self.fa.extend(self.fa.len()..num_nodes);
@@ -96,14 +92,23 @@ impl DisjointSet {
             }
         }
 
-        let id_zero = self.value_zero.map(|i| set_indices[i]);
-        let id_one = self.value_one.map(|i| set_indices[i]);
-        if matches!((id_zero, id_one), (Some(a), Some(b)) if a == b) {
-            clilog::error!(NL_SV_LIT, "Constant zero and one connected");
-            return None
+        let mut ids = self.values.map(|v| v.map(|i| set_indices[i]));
+        for i in 0..3 {
+            for j in 0..i {
+                if matches!((ids[j], ids[i]), (Some(a), Some(b)) if a == b) {
+                    clilog::error!(NL_SV_LIT, "Constant {} and {} connected",
+                                   CONST_NAMES[j], CONST_NAMES[i]);
+                    return None
+                }
+            }
         }
-        
-        Some((num_sets, set_indices, id_zero, id_one))
+        // z is not driven, so it gives way to the other
+        // constant on the same set.
+        if ids[3].is_some() && ids[..3].contains(&ids[3]) {
+            ids[3] = None;
+        }
+
+        Some((num_sets, set_indices, ids))
     }
 }
 
@@ -0,0 +1,467 @@
+//! ECO (engineering change order) commands.
+//!
+//! These commands modify the netlist in place, and keep all the
+//! maps consistent, including the CSRs, the names, and the
+//! guarantee that the net root comes first in
+//! [NetlistDB::net2pin].
+//!
+//! When cells, pins or nets are removed, the indices after them
+//! are shifted to keep everything compact. Each command takes
+//! time linear to the netlist size.
+
+use super::*;
+use csr::edit_uvec;
+use builder::WithPrimitives;
+
+/// The [NetlistDB::pin2net] entry of a pin not connected to
+/// any net.
+///
+/// Pins are always connected after construction. They can only
+/// become disconnected through ECO commands like
+/// [NetlistDB::disconnect_pin] and [NetlistDB::add_cell].
+pub const NET_DISCONNECTED: usize = usize::MAX;
+
+/// Build a map from old indices to new ones after removing
+/// some of them. Removed indices are mapped to `usize::MAX`.
+fn compaction_map(n: usize, removed: &HashSet<usize>) -> Vec<usize> {
+    let mut cnt = 0;
+    (0..n).map(|i| match removed.contains(&i) {
+        true => usize::MAX,
+        false => { cnt += 1; cnt - 1 }
+    }).collect()
+}
+
+/// Remove the elements at given indices from a vector.
+fn retain_by_index<T>(v: &mut Vec<T>, removed: &HashSet<usize>) {
+    let mut i = 0;
+    v.retain(|_| {
+        i += 1;
+        !removed.contains(&(i - 1))
+    });
+}
+
+impl NetlistDB {
+    #[must_use]
+    fn check_cell_id(&self, cellid: usize) -> Option<()> {
+        if cellid >= self.num_cells {
+            clilog::error!(NL_ECO_IDX, "cell {} out of range", cellid);
+            return None
+        }
+        Some(())
+    }
+
+    #[must_use]
+    fn check_pin_id(&self, pinid: usize) -> Option<()> {
+        if pinid >= self.num_pins {
+            clilog::error!(NL_ECO_IDX, "pin {} out of range", pinid);
+            return None
+        }
+        Some(())
+    }
+
+    #[must_use]
+    fn check_net_id(&self, netid: usize) -> Option<()> {
+        if netid >= self.num_nets {
+            clilog::error!(NL_ECO_IDX, "net {} out of range", netid);
+            return None
+        }
+        Some(())
+    }
+
+    /// Get the driver pin (net root) of a net, if any.
+    ///
+    /// The driver, when exists, is always the first pin in
+    /// [NetlistDB::net2pin]. It is either the output pin, or an
+    /// inout pin if the net has no output pin.
+    #[inline]
+    pub fn net_driver(&self, netid: usize) -> Option<usize> {
+        let pinid = self.net2pin.iter_set(netid).next()?;
+        match self.pindirect[pinid].is_driver() {
+            true => Some(pinid),
+            false => None
+        }
+    }
+
+    /// Add a new named net without any pin.
+    ///
+    /// The net is put in the module instance `hier`, which must
+    /// exist in [NetlistDB::hiername2id].
+    ///
+    /// Returns the new net index, or None if the module instance
+    /// does not exist or the name already exists.
+    #[must_use]
+    pub fn add_net(
+        &mut self, hier: HierName, name: CompactString, idx: Option<isize>
+    ) -> Option<usize> {
+        if !self.hiername2id.contains_key(&hier) {
+            clilog::error!(NL_ECO_NAME, "module instance {:?} does not exist",
+                           hier);
+            return None
+        }
+        let k = (hier, name, idx);
+        if self.logicpinname2id.contains_key(&k) {
+            clilog::error!(NL_ECO_NAME, "net name {} already exists",
+                           k.dbg_fmt_pin());
+            return None
+        }
+        let logic_id = self.num_logic_pins;
+        self.num_logic_pins += 1;
+        self.logicpinname2id.insert(k.clone(), logic_id);
+        self.logicpintypes.push(LogicPinType::Net);
+        self.logicpinnames.push(k.clone());
+        self.logicpin2net.push(self.num_nets);
+
+        let netid = self.num_nets;
+        self.num_nets += 1;
+        self.netname2id.insert(k.clone(), netid);
+        self.netnames.push(k);
+        self.netattrs.push(Vec::new());
+        self.net2pin.push_set();
+        Some(netid)
+    }
+
+    /// Add a new leaf cell with a list of pins (pin name and
+    /// bus index).
+    ///
+    /// The pin directions are queried from the library, like
+    /// in netlist construction. The new pins are not connected
+    /// to any net. Use [NetlistDB::connect_pin] to connect them.
+    ///
+    /// The cell is put in the module instance named by the prefix
+    /// of its name, e.g., `u_core` for `u_core/eco_buf`, which
+    /// must exist in [NetlistDB::hiername2id].
+    ///
+    /// Returns the new cell index, or None if the cell name or
+    /// any pin name already exists.
+    #[must_use]
+    pub fn add_cell(
+        &mut self, name: HierName, macro_name: CompactString,
+        pins: impl IntoIterator<Item = (CompactString, Option<isize>)>,
+        lib: &impl LeafPinProvider
+    ) -> Option<usize> {
+        if name.is_empty() || self.cellname2id.contains_key(&name) {
+            clilog::error!(NL_ECO_NAME, "cell name {:?} is empty or already exists",
+                           name);
+            return None
+        }
+        let hierid = match name.prev.as_deref() {
+            None => 0,
+            Some(prev) => match self.hiername2id.get(prev) {
+                Some(&hierid) => hierid,
+                None => {
+                    clilog::error!(NL_ECO_NAME, "module instance {:?} does not exist",
+                                   prev);
+                    return None
+                }
+            }
+        };
+        let pins = pins.into_iter()
+            .map(|(pin_name, idx)| (name.clone(), pin_name, idx))
+            .collect::<Vec<_>>();
+        let mut pins_set = HashSet::new();
+        for k in &pins {
+            if !pins_set.insert(k) || self.logicpinname2id.contains_key(k) {
+                clilog::error!(NL_ECO_NAME, "pin name {} already exists",
+                               k.dbg_fmt_pin());
+                return None
+            }
+        }
+
+        let cellid = self.num_cells;
+        self.num_cells += 1;
+        self.cellname2id.insert(name.clone(), cellid);
+        self.celltypes.push(macro_name.clone());
+        self.cellnames.push(name);
+        self.cellparams.push(Vec::new());
+        self.cellattrs.push(Vec::new());
+        self.cell2pin.push_set();
+        self.cell2hier.push(hierid);
+        self.hier2cells.insert(hierid, self.hier2cells.len(hierid), &[cellid]);
+
+        let lib = WithPrimitives(lib);
+        let directions = pins.iter()
+            .map(|(_, pin_name, idx)| lib.direction_of(&macro_name, pin_name, *idx))
+            .collect::<Vec<_>>();
+        let pinids = (self.num_pins..self.num_pins + pins.len())
+            .collect::<Vec<_>>();
+        for k in pins {
+            let logic_id = self.num_logic_pins;
+            self.num_logic_pins += 1;
+            self.logicpinname2id.insert(k.clone(), logic_id);
+            self.logicpintypes.push(LogicPinType::LeafCellPin);
+            self.logicpinnames.push(k.clone());
+            self.logicpin2net.push(NET_DISCONNECTED);
+            self.pinid2logicpinid.push(logic_id);
+            self.pinname2id.insert(k.clone(), self.num_pins);
+            self.pinnames.push(k);
+            self.num_pins += 1;
+        }
+        edit_uvec(&mut self.pin2cell, |v| {
+            v.extend(pinids.iter().map(|_| cellid));
+        });
+        edit_uvec(&mut self.pin2net, |v| {
+            v.extend(pinids.iter().map(|_| NET_DISCONNECTED));
+        });
+        edit_uvec(&mut self.cell2noutputs, |v| {
+            v.push(directions.iter().filter(|d| d.is_driver()).count());
+        });
+        edit_uvec(&mut self.pindirect, |v| v.extend(directions));
+        self.cell2pin.insert(cellid, 0, &pinids);
+        Some(cellid)
+    }
+
+    /// Disconnect a pin from its net.
+    ///
+    /// After this, its [NetlistDB::pin2net] entry becomes
+    /// [NET_DISCONNECTED]. It does nothing if the pin is already
+    /// disconnected.
+    /// If the pin was the net root, the first remaining inout
+    /// pin (if any) becomes the new root.
+    #[must_use]
+    pub fn disconnect_pin(&mut self, pinid: usize) -> Option<()> {
+        self.check_pin_id(pinid)?;
+        let netid = self.pin2net[pinid];
+        if netid == NET_DISCONNECTED {
+            return Some(())
+        }
+        let was_root = self.net_driver(netid) == Some(pinid);
+        assert!(self.net2pin.remove(netid, pinid));
+        self.pin2net[pinid] = NET_DISCONNECTED;
+        self.logicpin2net[self.pinid2logicpinid[pinid]] = NET_DISCONNECTED;
+        if was_root {
+            let new_root = self.net2pin.iter_set(netid)
+                .find(|&p| self.pindirect[p] == Direction::InOut);
+            if let Some(new_root) = new_root {
+                self.net2pin.remove(netid, new_root);
+                self.net2pin.insert(netid, 0, &[new_root]);
+            }
+        }
+        Some(())
+    }
+
+    /// Connect a pin to a net.
+    ///
+    /// If the pin is connected to another net, it is disconnected
+    /// from there first. An output pin becomes the net root, so
+    /// the net must not have another output pin. An inout pin
+    /// becomes the net root only if the net has no driver.
+    #[must_use]
+    pub fn connect_pin(&mut self, pinid: usize, netid: usize) -> Option<()> {
+        self.check_pin_id(pinid)?;
+        self.check_net_id(netid)?;
+        if self.pin2net[pinid] == netid {
+            return Some(())
+        }
+        let driver = self.net_driver(netid);
+        let is_root = match self.pindirect[pinid] {
+            Direction::O => {
+                if let Some(driver) = driver.filter(|&d| {
+                    self.pindirect[d] == Direction::O
+                }) {
+                    clilog::error!(
+                        NL_ECO_DRIVER,
+                        "cannot connect pin {} to net {}, which is \
+                         already driven by pin {}",
+                        self.pinnames[pinid].dbg_fmt_pin(), netid,
+                        self.pinnames[driver].dbg_fmt_pin());
+                    return None
+                }
+                true
+            }
+            Direction::InOut => driver.is_none(),
+            _ => false
+        };
+        self.disconnect_pin(pinid)?;
+        let pos = match is_root {
+            true => 0,
+            false => self.net2pin.len(netid)
+        };
+        self.net2pin.insert(netid, pos, &[pinid]);
+        self.pin2net[pinid] = netid;
+        self.logicpin2net[self.pinid2logicpinid[pinid]] = netid;
+        Some(())
+    }
+
+    /// Merge net `b` into net `a`.
+    ///
+    /// All pins, names and attributes of `b` are moved to `a`, and
+    /// then `b` is removed. At most one of them can have an output
+    /// pin, and they cannot be tied to different constants.
+    /// The new net root is the output pin if any, or otherwise
+    /// the root of `a` or `b` in order.
+    ///
+    /// Returns the new index of the merged net, which is shifted
+    /// if `a` comes after `b`.
+    #[must_use]
+    pub fn merge_nets(&mut self, a: usize, b: usize) -> Option<usize> {
+        self.check_net_id(a)?;
+        self.check_net_id(b)?;
+        if a == b {
+            return Some(a)
+        }
+        let is_output = |d: &usize| self.pindirect[*d] == Direction::O;
+        let (driver_a, driver_b) = (self.net_driver(a), self.net_driver(b));
+        if driver_a.filter(is_output).is_some() && driver_b.filter(is_output).is_some() {
+            clilog::error!(NL_ECO_DRIVER,
+                           "cannot merge net {} and {} which are both driven",
+                           a, b);
+            return None
+        }
+        let root_from_b = driver_b.is_some() &&
+            (driver_a.is_none() || driver_b.filter(is_output).is_some());
+        let (const_a, const_b) = (self.net_constant(a), self.net_constant(b));
+        if matches!((const_a, const_b), (Some(x), Some(y)) if x != y) {
+            clilog::error!(NL_ECO_CONST,
+                           "cannot merge net {} and {} tied to different constants",
+                           a, b);
+            return None
+        }
+
+        // move the pins, keeping the driver first.
+        let pins_b = self.net2pin.take_set(b);
+        for &pinid in &pins_b {
+            self.pin2net[pinid] = a;
+        }
+        match root_from_b {
+            true => {
+                self.net2pin.insert(a, 0, &pins_b[..1]);
+                self.net2pin.insert(a, self.net2pin.len(a), &pins_b[1..]);
+            }
+            false => {
+                self.net2pin.insert(a, self.net2pin.len(a), &pins_b);
+            }
+        }
+
+        // move the names and constants.
+        for netid in self.netname2id.values_mut()
+            .chain(self.logicpin2net.iter_mut())
+        {
+            if *netid == b {
+                *netid = a;
+            }
+        }
+        if is_better_net_name(&self.netnames[b], &self.netnames[a]) {
+            self.netnames[a] = self.netnames[b].clone();
+        }
+        let attrs_b = std::mem::take(&mut self.netattrs[b]);
+        merge_attrs(&mut self.netattrs[a], &attrs_b);
+        match const_b {
+            Some(0) => self.net_zero = Some(a),
+            Some(1) => self.net_one = Some(a),
+            Some(2) => self.net_x = Some(a),
+            Some(3) => self.net_z = Some(a),
+            _ => {}
+        }
+
+        // remove net b, and shift the indices after it.
+        let shift = |netid: usize| match netid {
+            NET_DISCONNECTED => netid,
+            _ if netid > b => netid - 1,
+            _ => netid
+        };
+        self.net2pin.remove_set(b);
+        self.netnames.remove(b);
+        self.netattrs.remove(b);
+        for netid in self.pin2net.iter_mut() {
+            *netid = shift(*netid);
+        }
+        for netid in self.netname2id.values_mut()
+            .chain(self.logicpin2net.iter_mut())
+        {
+            *netid = shift(*netid);
+        }
+        self.net_zero = self.net_zero.map(shift);
+        self.net_one = self.net_one.map(shift);
+        self.net_x = self.net_x.map(shift);
+        self.net_z = self.net_z.map(shift);
+        self.num_nets -= 1;
+        Some(shift(a))
+    }
+
+    /// Remove a leaf cell together with its pins.
+    ///
+    /// The pins are disconnected from their nets first. The nets
+    /// themselves are kept even if they become empty.
+    /// The indices of cells and pins after the removed ones are
+    /// shifted.
+    #[must_use]
+    pub fn remove_cell(&mut self, cellid: usize) -> Option<()> {
+        self.check_cell_id(cellid)?;
+        if cellid == 0 {
+            clilog::error!(NL_ECO_IDX, "cannot remove the top-level cell");
+            return None
+        }
+        let pins = self.cell2pin.take_set(cellid);
+        for &pinid in &pins {
+            self.disconnect_pin(pinid)?;
+        }
+        self.remove_pins(pins.into_iter().collect());
+
+        // remove the cell, and shift the indices after it.
+        let name = self.cellnames.remove(cellid);
+        self.cellname2id.remove(&name);
+        self.celltypes.remove(cellid);
+        self.cellparams.remove(cellid);
+        self.cellattrs.remove(cellid);
+        self.cell2pin.remove_set(cellid);
+        self.hier2cells.remove(self.cell2hier.remove(cellid), cellid);
+        edit_uvec(&mut self.cell2noutputs, |v| {
+            v.remove(cellid);
+        });
+        let shift = |i: usize| if i > cellid { i - 1 } else { i };
+        for c in self.pin2cell.iter_mut() {
+            *c = shift(*c);
+        }
+        for c in self.cellname2id.values_mut()
+            .chain(self.hier2cells.items.iter_mut())
+        {
+            *c = shift(*c);
+        }
+        self.num_cells -= 1;
+        Some(())
+    }
+
+    /// Remove a set of disconnected pins that no longer reside
+    /// in [NetlistDB::cell2pin], and compact the pin and logic
+    /// pin indices.
+    fn remove_pins(&mut self, pins: HashSet<usize>) {
+        let logic_pins = pins.iter()
+            .map(|&pinid| self.pinid2logicpinid[pinid])
+            .collect::<HashSet<_>>();
+        let pin_map = compaction_map(self.num_pins, &pins);
+        let logic_map = compaction_map(self.num_logic_pins, &logic_pins);
+
+        // pins
+        for &pinid in &pins {
+            self.pinname2id.remove(&self.pinnames[pinid]);
+        }
+        edit_uvec(&mut self.pin2cell, |v| retain_by_index(v, &pins));
+        edit_uvec(&mut self.pin2net, |v| retain_by_index(v, &pins));
+        edit_uvec(&mut self.pindirect, |v| retain_by_index(v, &pins));
+        retain_by_index(&mut self.pinnames, &pins);
+        retain_by_index(&mut self.pinid2logicpinid, &pins);
+        for pinid in self.pinname2id.values_mut()
+            .chain(self.portname2pinid.values_mut())
+            .chain(self.cell2pin.items.iter_mut())
+            .chain(self.net2pin.items.iter_mut())
+        {
+            *pinid = pin_map[*pinid];
+        }
+        self.num_pins -= pins.len();
+
+        // logic pins
+        for &logic_id in &logic_pins {
+            self.logicpinname2id.remove(&self.logicpinnames[logic_id]);
+        }
+        retain_by_index(&mut self.logicpintypes, &logic_pins);
+        retain_by_index(&mut self.logicpinnames, &logic_pins);
+        retain_by_index(&mut self.logicpin2net, &logic_pins);
+        for logic_id in self.logicpinname2id.values_mut()
+            .chain(self.pinid2logicpinid.iter_mut())
+            .chain(self.hier2ports.items.iter_mut())
+        {
+            *logic_id = logic_map[*logic_id];
+        }
+        self.num_logic_pins -= logic_pins.len();
+    }
+}
@@ -0,0 +1,293 @@
+//! Directed graph views of the netlist, with levelization,
+//! fanin/fanout cones and combinational loop detection.
+
+use super::*;
+
+/// Leaf cell arc provider trait, which decides where the graph
+/// views are broken.
+///
+/// Downstream databases (e.g., Liberty library) should implement
+/// this to tell the sequential cells and the combinational arcs
+/// inside leaf cells.
+pub trait LeafArcProvider {
+    /// This function is called to query whether a leaf macro is
+    /// sequential (e.g., a flip-flop or a latch).
+    ///
+    /// The graph views are broken at the sequential cells: they
+    /// have no arcs from their input pins to output pins.
+    fn is_sequential(&self, macro_name: &CompactString) -> bool;
+
+    /// This function is called to query whether there is a
+    /// combinational arc from an input pin to an output pin of a
+    /// combinational leaf macro.
+    ///
+    /// The default gives arcs between all pairs of pins.
+    #[inline]
+    fn has_arc(
+        &self,
+        _macro_name: &CompactString,
+        _from_pin: &CompactString, _from_idx: Option<isize>,
+        _to_pin: &CompactString, _to_idx: Option<isize>
+    ) -> bool {
+        true
+    }
+}
+
+impl<T> LeafArcProvider for T
+where T: Fn(&CompactString) -> bool {
+    #[inline]
+    fn is_sequential(&self, macro_name: &CompactString) -> bool {
+        self(macro_name)
+    }
+}
+
+impl LeafArcProvider for HashSet<CompactString> {
+    #[inline]
+    fn is_sequential(&self, macro_name: &CompactString) -> bool {
+        self.contains(macro_name)
+    }
+}
+
+/// A directed graph over the cells or pins of a netlist.
+///
+/// Created by [NetlistDB::cell_graph] or [NetlistDB::pin_graph].
+#[derive(Debug, Clone)]
+pub struct NetlistGraph {
+    /// number of nodes.
+    pub num_nodes: usize,
+    /// Node CSR of the fanout nodes.
+    pub fanouts: VecCSR,
+    /// Node CSR of the fanin nodes.
+    pub fanins: VecCSR,
+}
+
+/// The levels of a graph without loops.
+///
+/// Created by [NetlistGraph::levelize].
+#[derive(Debug, Clone)]
+pub struct Levelization {
+    /// number of levels.
+    pub num_levels: usize,
+    /// Node to level.
+    ///
+    /// The nodes without fanins are at level 0, and the others
+    /// are one level above their highest fanin.
+    pub levels: UVec<usize>,
+    /// Level CSR of the nodes, which gives a topological order
+    /// when flattened.
+    pub level2nodes: VecCSR,
+}
+
+/// build the fanout and fanin CSRs of edges.
+fn csr_of_edges(num_nodes: usize, edges: &[(usize, usize)]) -> (VecCSR, VecCSR) {
+    let build = |from: fn(&(usize, usize)) -> usize, to: fn(&(usize, usize)) -> usize| {
+        let inset = edges.iter().map(from).collect::<Vec<_>>();
+        let mut csr = VecCSR::from(num_nodes, edges.len(), &inset);
+        for e in csr.items.iter_mut() {
+            *e = to(&edges[*e]);
+        }
+        csr
+    };
+    (build(|e| e.0, |e| e.1), build(|e| e.1, |e| e.0))
+}
+
+/// the nodes reachable from the roots through a CSR, including
+/// the roots, in the order of indices.
+fn reachable(csr: &VecCSR, num_nodes: usize, roots: impl IntoIterator<Item = usize>) -> Vec<usize> {
+    let mut visited = vec![false; num_nodes];
+    let mut stack = Vec::new();
+    for u in roots {
+        if !visited[u] {
+            visited[u] = true;
+            stack.push(u);
+        }
+    }
+    while let Some(u) = stack.pop() {
+        for v in csr.iter_set(u) {
+            if !visited[v] {
+                visited[v] = true;
+                stack.push(v);
+            }
+        }
+    }
+    (0..num_nodes).filter(|&u| visited[u]).collect()
+}
+
+impl NetlistGraph {
+    /// Build a graph from edges (from, to).
+    pub fn from_edges(num_nodes: usize, edges: &[(usize, usize)]) -> NetlistGraph {
+        let (fanouts, fanins) = csr_of_edges(num_nodes, edges);
+        NetlistGraph { num_nodes, fanouts, fanins }
+    }
+
+    /// Get the transitive fanin cone of some nodes, i.e., the
+    /// nodes reaching any of them, including themselves.
+    ///
+    /// The nodes are in the order of indices.
+    pub fn fanin_cone(&self, roots: impl IntoIterator<Item = usize>) -> Vec<usize> {
+        reachable(&self.fanins, self.num_nodes, roots)
+    }
+
+    /// Get the transitive fanout cone of some nodes, i.e., the
+    /// nodes reachable from any of them, including themselves.
+    ///
+    /// The nodes are in the order of indices.
+    pub fn fanout_cone(&self, roots: impl IntoIterator<Item = usize>) -> Vec<usize> {
+        reachable(&self.fanouts, self.num_nodes, roots)
+    }
+
+    /// Find a loop in the graph.
+    ///
+    /// Returns the nodes in the loop along the edges, the last
+    /// one having an edge to the first one, or None if the graph
+    /// has no loop.
+    pub fn find_loop(&self) -> Option<Vec<usize>> {
+        // 0: unvisited, 1: on the stack, 2: finished.
+        let mut state = vec![0u8; self.num_nodes];
+        // the nodes on the stack, with the positions of their
+        // next fanouts to visit.
+        let mut stack = Vec::<(usize, usize)>::new();
+        for s in 0..self.num_nodes {
+            if state[s] != 0 {
+                continue
+            }
+            state[s] = 1;
+            stack.push((s, self.fanouts.start[s]));
+            while let Some(top) = stack.last_mut() {
+                let (u, pos) = *top;
+                if pos == self.fanouts.start[u + 1] {
+                    state[u] = 2;
+                    stack.pop();
+                    continue
+                }
+                top.1 += 1;
+                let v = self.fanouts.items[pos];
+                match state[v] {
+                    0 => {
+                        state[v] = 1;
+                        stack.push((v, self.fanouts.start[v]));
+                    }
+                    1 => {
+                        let at = stack.iter().position(|&(w, _)| w == v).unwrap();
+                        return Some(stack[at..].iter().map(|&(w, _)| w).collect())
+                    }
+                    _ => {}
+                }
+            }
+        }
+        None
+    }
+
+    /// Levelize the graph in topological order.
+    ///
+    /// If the graph has loops, returns one of them as in
+    /// [NetlistGraph::find_loop].
+    pub fn levelize(&self) -> Result<Levelization, Vec<usize>> {
+        let n = self.num_nodes;
+        let mut indegree = (0..n).map(|u| self.fanins.len(u)).collect::<Vec<_>>();
+        let mut levels = vec![0; n];
+        let mut order = (0..n).filter(|&u| indegree[u] == 0).collect::<Vec<_>>();
+        let mut head = 0;
+        while head < order.len() {
+            let u = order[head];
+            head += 1;
+            for v in self.fanouts.iter_set(u) {
+                levels[v] = levels[v].max(levels[u] + 1);
+                indegree[v] -= 1;
+                if indegree[v] == 0 {
+                    order.push(v);
+                }
+            }
+        }
+        if order.len() < n {
+            return Err(self.find_loop().expect("a graph not levelized has loops"))
+        }
+        let num_levels = levels.iter().max().map_or(0, |l| l + 1);
+        Ok(Levelization {
+            num_levels,
+            level2nodes: VecCSR::from(num_levels, n, &levels),
+            levels: levels.into(),
+        })
+    }
+}
+
+impl NetlistDB {
+    /// Build the cell-level graph, with an edge from each cell
+    /// driving a net to each cell with other pins on that net.
+    ///
+    /// The edges into the sequential cells and the top-level cell
+    /// 0 are removed, so that they only start the paths. To get
+    /// the fanin cone of a sequential cell, use the pin-level
+    /// graph on its input pins.
+    pub fn cell_graph(&self, lib: &impl LeafArcProvider) -> NetlistGraph {
+        let is_sink = (0..self.num_cells)
+            .map(|cellid| cellid != 0 && !lib.is_sequential(&self.celltypes[cellid]))
+            .collect::<Vec<_>>();
+        let mut edges = Vec::new();
+        for netid in 0..self.num_nets {
+            let Some(driver) = self.net_driver(netid) else { continue };
+            let u = self.pin2cell[driver];
+            for pinid in self.net2pin.iter_set(netid).skip(1) {
+                let v = self.pin2cell[pinid];
+                if is_sink[v] {
+                    edges.push((u, v));
+                }
+            }
+        }
+        edges.sort_unstable();
+        edges.dedup();
+        NetlistGraph::from_edges(self.num_cells, &edges)
+    }
+
+    /// Build the pin-level graph, with edges from each net driver
+    /// to the other pins on the net, and the arcs inside the
+    /// combinational leaf cells given by the library.
+    ///
+    /// The arcs go from the input pins to the output (or inout)
+    /// pins. The sequential cells have no arcs, so that their
+    /// input pins end the paths and their output pins start them.
+    pub fn pin_graph(&self, lib: &impl LeafArcProvider) -> NetlistGraph {
+        let mut edges = Vec::new();
+        for netid in 0..self.num_nets {
+            let Some(driver) = self.net_driver(netid) else { continue };
+            edges.extend(self.net2pin.iter_set(netid).skip(1).map(|p| (driver, p)));
+        }
+        for cellid in 1..self.num_cells {
+            let macro_name = &self.celltypes[cellid];
+            if lib.is_sequential(macro_name) {
+                continue
+            }
+            for from in self.cell2pin.iter_set(cellid) {
+                if self.pindirect[from] != Direction::I {
+                    continue
+                }
+                let (_, from_pin, from_idx) = &self.pinnames[from];
+                for to in self.cell2pin.iter_set(cellid) {
+                    let (_, to_pin, to_idx) = &self.pinnames[to];
+                    if self.pindirect[to].is_driver() &&
+                        lib.has_arc(macro_name, from_pin, *from_idx, to_pin, *to_idx)
+                    {
+                        edges.push((from, to));
+                    }
+                }
+            }
+        }
+        NetlistGraph::from_edges(self.num_pins, &edges)
+    }
+
+    /// Find a combinational loop in the pin-level graph.
+    ///
+    /// Returns the pins in the loop along the signal flow, or None
+    /// if there is no loop. The loop found is also reported as an
+    /// error.
+    pub fn find_comb_loop(&self, lib: &impl LeafArcProvider) -> Option<Vec<usize>> {
+        let pins = self.pin_graph(lib).find_loop()?;
+        clilog::error!(
+            NL_COMB_LOOP,
+            "combinational loop found through {} pins: {}",
+            pins.len(),
+            pins.iter().map(|&p| self.pinnames[p].dbg_fmt_pin())
+                .collect::<Vec<_>>().join(" -> "));
+        Some(pins)
+    }
+}
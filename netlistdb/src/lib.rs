@@ -5,10 +5,10 @@ use std::sync::Arc;
 use std::collections::HashSet;
 use compact_str::CompactString;
 use ulib::{UVec, Device, UniversalCopy, Zeroable};
+use sverilogparse::SVerilogAttrs;
 
-/// types of directions: input or output.
+/// types of directions: input, output or inout.
 /// 
-/// note: inout is not supported yet.
 /// **should be identical to `csrc/lib.h`**.
 #[derive(Zeroable, Debug, PartialEq, Eq, Clone, UniversalCopy)]
 #[repr(u8)]
@@ -18,7 +18,18 @@ pub enum Direction {
     /// output
     O = 1,
     /// unknown (unassigned)
-    Unknown = 2
+    Unknown = 2,
+    /// inout (bidirectional)
+    InOut = 3
+}
+
+impl Direction {
+    /// Whether a pin of this direction can drive a net,
+    /// i.e., output or inout.
+    #[inline]
+    pub fn is_driver(self) -> bool {
+        matches!(self, Direction::O | Direction::InOut)
+    }
 }
 
 mod csr;
@@ -31,21 +42,29 @@ pub use hier_name::{
     GeneralMacroPinName, RefMacroPinName
 };
 
+/// types of logic pins. See [NetlistDB::logicpintypes].
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
-enum LogicPinType {
+pub enum LogicPinType {
+    /// a port of the top module, which is both a pin and a net.
     TopPort,
+    /// a net wire, including the ports of submodules.
     Net,
+    /// a pin of a leaf cell.
     LeafCellPin,
+    /// other names, like the named ports `.port(expr)` of
+    /// submodules that are not wires.
     Others
 }
 
 impl LogicPinType {
+    /// Whether it is a pin, i.e., a top port or a leaf cell pin.
     #[inline]
     pub fn is_pin(self) -> bool {
         use LogicPinType::*;
         if let TopPort | LeafCellPin = self { true } else { false }
     }
 
+    /// Whether it is a net, i.e., a top port or a net wire.
     #[inline]
     pub fn is_net(self) -> bool {
         use LogicPinType::*;
@@ -56,8 +75,8 @@ impl LogicPinType {
 /// The netlist storage.
 /// 
 /// The public members are all READ-ONLY outside. Please modify
-/// them through the ECO commands that will be available
-/// in the future.
+/// them through the ECO commands, like [NetlistDB::add_cell],
+/// [NetlistDB::connect_pin] and [NetlistDB::merge_nets].
 #[readonly::make]
 #[non_exhaustive]
 #[derive(Debug, Clone)]
@@ -84,8 +103,8 @@ pub struct NetlistDB {
     ///
     /// The top-level macro is always the 0th cell, which has a
     /// special name of empty string.
-    /// Also, the hierarchical non-leaf cells do NOT reside in here,
-    /// yet -- they are to-be-added in the future.
+    /// The hierarchical non-leaf cells (i.e., submodule instances)
+    /// do NOT reside in here. They are in [NetlistDB::hiername2id].
     /// This map only contains leaf cells.
     pub cellname2id: HashMap<HierName, usize>,
     /// Logical pin name tuple (cell hier name, macro pin type, vec idx) to logical pin index.
@@ -95,13 +114,18 @@ pub struct NetlistDB {
     /// 1. net wires  (yes, nets are also ``logic pins''.)
     /// 2. I/O ports of top module and submodules
     /// 3. pins of leaf cells.
-    logicpinname2id: HashMap<(HierName, CompactString, Option<isize>), usize>,
+    ///
+    /// Unlike [NetlistDB::netname2id], this contains the ports of
+    /// submodules like `u_core/i[0]`, and the wires merged by
+    /// assignments. They can be resolved to nets through
+    /// [NetlistDB::logicpin2net].
+    pub logicpinname2id: HashMap<(HierName, CompactString, Option<isize>), usize>,
     /// Pin name tuple (cell hier name, macro pin type, vec idx) to index.
     /// 
     /// Pin names are always unique without ambiguity.
     /// For top-level named port connections, only the port names are
     /// created as valid pin names. The I/O definition can be referred
-    /// in logicpinname2id (private member).
+    /// in [NetlistDB::logicpinname2id].
     pub pinname2id: HashMap<(HierName, CompactString, Option<isize>), usize>,
     /// Net name tuple (net hier name, vec idx) to index.
     ///
@@ -120,23 +144,89 @@ pub struct NetlistDB {
     pub celltypes: Vec<CompactString>,
     /// Cell index to name (hierarchical).
     ///
-    /// This information actually contains the tree structure that
-    /// might be useful later when we implement verilog writer.
+    /// This information actually contains the tree structure,
+    /// which is used by the verilog writer.
     pub cellnames: Vec<HierName>,
+    /// Cell index to the instance parameter overrides, as tuples
+    /// of (parameter_name, value_expression).
+    /// See [SVerilogCell::params](sverilogparse::SVerilogCell::params).
+    ///
+    /// Parameters of hierarchical module instances are not kept,
+    /// as the modules are flattened.
+    pub cellparams: Vec<Vec<(Option<CompactString>, CompactString)>>,
+    /// Cell index to the attributes like `(* keep *)` before the
+    /// instantiation.
+    /// See [SVerilogCell::attrs](sverilogparse::SVerilogCell::attrs).
+    pub cellattrs: Vec<SVerilogAttrs>,
+    /// Cell index to the index of the module instance it is
+    /// directly inside. See [NetlistDB::hiernames].
+    ///
+    /// The top-level cell 0 is in the top-level module 0.
+    pub cell2hier: Vec<usize>,
+
+    /// Number of hierarchical module instances.
+    ///
+    /// This is always at least 1, as the 0th instance is always
+    /// the top-level module.
+    pub num_hiers: usize,
+    /// Module instance name to index.
+    ///
+    /// The top-level module is always the 0th instance, which has
+    /// a special name of empty string.
+    pub hiername2id: HashMap<HierName, usize>,
+    /// Module instance index to name (hierarchical).
+    pub hiernames: Vec<HierName>,
+    /// Module instance index to module name.
+    pub hiertypes: Vec<CompactString>,
+    /// Module instance index to the parent instance index.
+    ///
+    /// The top-level module is the parent of itself.
+    pub hier2parent: Vec<usize>,
+    /// Module instance CSR of the child instances, in the
+    /// netlist order.
+    pub hier2children: VecCSR,
+    /// Module instance CSR of the leaf cells directly inside,
+    /// in the netlist order.
+    ///
+    /// The top-level cell 0 is not in any instance.
+    pub hier2cells: VecCSR,
+    /// Module instance CSR of the logic pins of the module ports,
+    /// in the order of the ports and then the bits.
+    ///
+    /// For the top-level module, these are the top ports.
+    pub hier2ports: VecCSR,
+
     /// Logic pin classes.
-    logicpintypes: Vec<LogicPinType>,
+    pub logicpintypes: Vec<LogicPinType>,
     /// Logic pin index to name.
-    logicpinnames: Vec<(HierName, CompactString, Option<isize>)>,
+    pub logicpinnames: Vec<(HierName, CompactString, Option<isize>)>,
+    /// Logic pin index to the net it belongs to.
+    ///
+    /// Logic pins of leaf cells disconnected by ECO commands
+    /// have a special value of [NET_DISCONNECTED].
+    pub logicpin2net: Vec<usize>,
     /// Pin index to corresponding logic pin index.
-    pinid2logicpinid: Vec<usize>,
+    pub pinid2logicpinid: Vec<usize>,
     /// Net index to net hier and index.
+    ///
+    /// Nets without any name (e.g., those created only from
+    /// constant connections) get an empty name here.
     pub netnames: Vec<(HierName, CompactString, Option<isize>)>,
+    /// Net index to the attributes before the wire definitions.
+    ///
+    /// A net connected across hierarchy boundaries collects the
+    /// attributes of all its wires. The same attribute is kept
+    /// only once.
+    pub netattrs: Vec<SVerilogAttrs>,
     /// Pin index to cell hier, macro pin name, and pin index.
     pub pinnames: Vec<(HierName, CompactString, Option<isize>)>,
 
     /// Pin to parent cell.
     pub pin2cell: UVec<usize>,
     /// Pin to parent net.
+    ///
+    /// Pins disconnected by ECO commands have a special value
+    /// of [NET_DISCONNECTED].
     pub pin2net: UVec<usize>,
     /// Cell CSR.
     pub cell2pin: VecCSR,
@@ -145,20 +235,131 @@ pub struct NetlistDB {
     /// **Caveat**: After assigning directions, it is guaranteed that
     /// the net root would be the first in net CSR.
     /// Before such assignment, the order is not determined.
+    ///
+    /// The net root is the output pin of a net. For nets driven
+    /// only by inout pins, the first inout pin is chosen as the
+    /// root. See [NetlistDB::net_driver].
     pub net2pin: VecCSR,
 
     /// Pin direction.
     pub pindirect: UVec<Direction>,
 
+    /// Number of output or inout pins of each cell.
     pub cell2noutputs: UVec<usize>,
 
     /// Constant zero net index.
     pub net_zero: Option<usize>,
     /// Constant one net index.
     pub net_one: Option<usize>,
+    /// Constant x (unknown) net index.
+    pub net_x: Option<usize>,
+    /// Constant z (high impedance) net index.
+    pub net_z: Option<usize>,
 }
 
 impl NetlistDB {
+    /// Get the constant value of a net, if it is one of
+    /// [NetlistDB::net_zero], [NetlistDB::net_one],
+    /// [NetlistDB::net_x] and [NetlistDB::net_z].
+    ///
+    /// The value is encoded as 0, 1, x, z => 0, 1, 2, 3.
+    #[inline]
+    pub fn net_constant(&self, netid: usize) -> Option<u8> {
+        [self.net_zero, self.net_one, self.net_x, self.net_z].iter()
+            .position(|&c| c == Some(netid))
+            .map(|v| v as u8)
+    }
+
+    /// Get the value expression of a named parameter of a cell.
+    #[inline]
+    pub fn cell_param(&self, cellid: usize, name: &str) -> Option<&CompactString> {
+        self.cellparams[cellid].iter()
+            .find(|(n, _)| n.as_deref() == Some(name))
+            .map(|(_, v)| v)
+    }
+
+    /// Get an attribute of a cell.
+    ///
+    /// Returns `Some(None)` for an attribute without value,
+    /// like `(* keep *)`.
+    #[inline]
+    pub fn cell_attr(&self, cellid: usize, name: &str) -> Option<Option<&CompactString>> {
+        find_attr(&self.cellattrs[cellid], name)
+    }
+
+    /// Get an attribute of a net.
+    ///
+    /// Returns `Some(None)` for an attribute without value,
+    /// like `(* keep *)`.
+    #[inline]
+    pub fn net_attr(&self, netid: usize, name: &str) -> Option<Option<&CompactString>> {
+        find_attr(&self.netattrs[netid], name)
+    }
+
+    /// Get the logic pin index of a name tuple (hier name, pin
+    /// or wire name, vec idx), like the port `a[0]` of a submodule
+    /// instance `u_core/u_alu`.
+    #[inline]
+    pub fn logic_pin(&self, name: &(HierName, CompactString, Option<isize>)) -> Option<usize> {
+        self.logicpinname2id.get(name).copied()
+    }
+
+    /// Get the net of a logic pin name tuple, like the port `a[0]`
+    /// of a submodule instance `u_core/u_alu`. See
+    /// [NetlistDB::logic_pin].
+    ///
+    /// Returns None if the name does not exist or the logic pin is
+    /// disconnected.
+    #[inline]
+    pub fn logic_pin_net(&self, name: &(HierName, CompactString, Option<isize>)) -> Option<usize> {
+        self.logic_pin(name)
+            .map(|id| self.logicpin2net[id])
+            .filter(|&netid| netid != NET_DISCONNECTED)
+    }
+
+    /// Get all the names of a net, including the wires, the top
+    /// ports and the submodule ports merged into it, but not the
+    /// leaf cell pins.
+    ///
+    /// This takes time linear to the number of logic pins.
+    pub fn net_aliases(&self, netid: usize) -> Vec<&(HierName, CompactString, Option<isize>)> {
+        self.logicpin2net.iter().enumerate()
+            .filter(|&(id, &n)| n == netid && self.logicpintypes[id] != LogicPinType::LeafCellPin)
+            .map(|(id, _)| &self.logicpinnames[id])
+            .collect()
+    }
+
+    /// Get all the leaf cells under a module instance, including
+    /// those in its descendant instances, in the netlist order.
+    pub fn cells_under(&self, hierid: usize) -> Vec<usize> {
+        let mut cells = Vec::new();
+        let mut stack = vec![hierid];
+        while let Some(h) = stack.pop() {
+            cells.extend(self.hier2cells.iter_set(h));
+            stack.extend(self.hier2children.iter_set(h));
+        }
+        cells.sort_unstable();
+        cells
+    }
+
+    /// Check if a cell is under a module instance, directly or
+    /// in its descendant instances.
+    pub fn is_cell_under(&self, cellid: usize, hierid: usize) -> bool {
+        if cellid == 0 {
+            return false
+        }
+        let mut h = self.cell2hier[cellid];
+        loop {
+            if h == hierid {
+                return true
+            }
+            if h == 0 {
+                return false
+            }
+            h = self.hier2parent[h];
+        }
+    }
+
     /// This changes the type (i.e. macro name) of a leaf cell.
     pub fn change_cell_type(&mut self, cellid: usize, new_cell_type: CompactString) {
         self.celltypes[cellid] = new_cell_type;
@@ -174,5 +375,13 @@ use disjoint_set::*;
 mod builder;
 pub use builder::{LeafPinProvider, NoDirection};
 
+mod writer;
+
+mod eco;
+pub use eco::NET_DISCONNECTED;
+
+mod graph;
+pub use graph::{LeafArcProvider, NetlistGraph, Levelization};
+
 #[doc(hidden)]
 pub use builder::DirectionProvider;
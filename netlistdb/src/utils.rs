@@ -5,22 +5,28 @@ use sverilogparse::*;
 use std::collections::HashSet;
 use either::Either;
 
+/// The parsed modules by name, with their preprocessed maps.
+///
+/// The names refer to the parsed AST, which can be either owned
+/// or borrowed from the source.
+pub(crate) type Modules<'i, S> = HashMap<&'i str, (&'i SVerilogModule<S>, ModuleMap<'i>)>;
+
 /// Find the top module.
 /// Currently, if the module is not explicitly specified, we
 /// guess it by scanning for unreferenced ones.
 #[must_use]
 #[inline]
-pub(crate) fn find_top_module<'i>(
-    modules: &'i HashMap<CompactString, (SVerilogModule, ModuleMap)>,
+pub(crate) fn find_top_module<'i, S: AsRef<str>>(
+    modules: &'i Modules<'i, S>,
     top: Option<&'_ str>
-) -> Option<(&'i CompactString, &'i SVerilogModule, &'i ModuleMap)> {
+) -> Option<(&'i str, &'i SVerilogModule<S>, &'i ModuleMap<'i>)> {
     if modules.len() == 0 {
         clilog::error!(NL_SV_PARSE, "Empty verilog netlist.");
         return None;
     }
     if let Some(top) = top {
         if let Some((k, (v1, v2))) = modules.get_key_value(top) {
-            Some((k, v1, v2))
+            Some((k, *v1, v2))
         }
         else {
             clilog::error!(
@@ -30,22 +36,22 @@ pub(crate) fn find_top_module<'i>(
         }
     }
     else if modules.len() != 1 {
-        let mut referenced = HashSet::<&CompactString>::new();
+        let mut referenced = HashSet::<&str>::new();
         for (_, (m, _)) in modules {
             for cell in &m.cells {
-                if modules.contains_key(&cell.macro_name) {
-                    referenced.insert(&cell.macro_name);
+                if modules.contains_key(cell.macro_name.as_ref()) {
+                    referenced.insert(cell.macro_name.as_ref());
                 }
             }
         }
         let unrefs: Vec<_> = modules.iter()
-            .filter(|(s, _)| !referenced.contains(s)).collect();
+            .filter(|(s, _)| !referenced.contains(*s)).collect();
         if unrefs.len() == 1 {
             let (s, (m, mm)) = unrefs[0];
             clilog::info!(
                 NL_SV_GUESSTOP,
                 "The top module is guessed to be {}.", s);
-            Some((s, m, mm))
+            Some((s, *m, mm))
         }
         else if unrefs.len() == 0 {
             clilog::error!(
@@ -63,7 +69,7 @@ pub(crate) fn find_top_module<'i>(
     }
     else {
         let (s, (m, mm)) = modules.iter().next().unwrap();
-        Some((s, m, mm))
+        Some((s, *m, mm))
     }
 }
 
@@ -77,22 +83,84 @@ pub fn enum_in_width(
     }
 }
 
+/// Check whether a net name is better than the current one.
+///
+/// Names in shallower hierarchies are preferred, and then the
+/// lexicographically smaller ones. An empty current name is
+/// always replaced.
+#[inline]
+pub(crate) fn is_better_net_name(
+    netname: &(HierName, CompactString, Option<isize>),
+    current_name: &(HierName, CompactString, Option<isize>)
+) -> bool {
+    let current_hier_depth = current_name.0.iter().count();
+    let netname_hier_depth = netname.0.iter().count();
+    current_name.1.is_empty()
+        || netname_hier_depth < current_hier_depth
+        || (netname_hier_depth == current_hier_depth
+            && netname.1 < current_name.1)
+}
+
+/// Find an attribute by name. The last one wins if repeated.
+#[inline]
+pub(crate) fn find_attr<'i>(
+    attrs: &'i SVerilogAttrs, name: &str
+) -> Option<Option<&'i CompactString>> {
+    attrs.iter().rev()
+        .find(|(n, _)| n.as_str() == name)
+        .map(|(_, v)| v.as_ref())
+}
+
+/// Copy attributes from the parsed AST.
+#[inline]
+pub(crate) fn own_attrs<S: AsRef<str>>(attrs: &SVerilogAttrs<S>) -> SVerilogAttrs {
+    attrs.iter()
+        .map(|(n, v)| (n.as_ref().into(), v.as_ref().map(|v| v.as_ref().into())))
+        .collect()
+}
+
+/// Merge attributes into another, skipping the duplicates.
+#[inline]
+pub(crate) fn merge_attrs<'i>(
+    into: &mut SVerilogAttrs,
+    attrs: impl IntoIterator<Item = &'i (CompactString, Option<CompactString>)>
+) {
+    for a in attrs {
+        if !into.contains(a) {
+            into.push(a.clone());
+        }
+    }
+}
+
 /// Useful preprocessed map for each SVerilog module.
 #[readonly::make]
-pub struct ModuleMap {
+pub struct ModuleMap<'i> {
     /// For each vector def, we store its range here.
     ///
     /// Scalar defs do not present in this map. This can be used
     /// to check whether a def is a scalar or a vector.
-    pub def_widths: HashMap<CompactString, SVerilogRange>,
+    pub def_widths: HashMap<&'i str, SVerilogRange>,
+    /// For each multi-dimensional def, we store all its
+    /// dimensions here, outer first.
+    ///
+    /// The bits of such a def are flattened into the range
+    /// `[n-1:0]` in [ModuleMap::def_widths], in the order of
+    /// declaration. E.g., for `wire [3:0] mem [0:15];`,
+    /// `mem[0][3]` is the flattened bit 63.
+    pub def_dims: HashMap<&'i str, Vec<SVerilogRange>>,
     /// For each def, we store its type (input/output/wire/...) here.
-    pub def_types: HashMap<CompactString, WireDefType>,
+    pub def_types: HashMap<&'i str, WireDefType>,
     /// For each port, we store its range (if any) here.
     ///
     /// This is tricky for named port connections, as we have to
     /// determine whether it is a vector or a scalar based on the
     /// context.
-    pub port_widths: HashMap<CompactString, SVerilogRange>,
+    pub port_widths: HashMap<&'i str, SVerilogRange>,
+    /// For each unnamed gate primitive instance, we store its
+    /// generated name like `$and$0` here, by the cell index.
+    ///
+    /// The names are unique among the cells in the module.
+    pub gate_names: HashMap<usize, CompactString>,
 }
 
 /// An enum representing a bit of a Wirexpr.
@@ -100,67 +168,174 @@ pub struct ModuleMap {
 /// This is expected to be created from [`ModuleMap::eval_expr_iter`].
 pub enum ExprBit<'i> {
     Const(u8 /* 0, 1, x, z => 0, 1, 2, 3 */),
-    Var(&'i CompactString, Option<isize>)
+    Var(&'i str, Option<isize>)
+}
+
+/// The width of a def, flattened if it is multi-dimensional.
+///
+/// A def with a single dimension (packed or unpacked) keeps
+/// its range.
+pub(crate) fn def_flat_width<S>(def: &SVerilogWireDef<S>) -> Option<SVerilogRange> {
+    let mut dims = def.dims();
+    let first = dims.next()?;
+    match dims.next() {
+        None => Some(first),
+        Some(_) => Some(SVerilogRange(
+            def.dims().map(|r| r.len()).product::<usize>() as isize - 1, 0))
+    }
+}
+
+/// The number of bits in the inner dimensions.
+#[inline]
+fn dims_len(dims: &[SVerilogRange]) -> usize {
+    dims.iter().map(|r| r.len()).product()
+}
+
+/// The position of an index in a range, counted from the
+/// right end.
+#[inline]
+fn range_pos(SVerilogRange(l, r): SVerilogRange, i: isize) -> isize {
+    match l >= r {
+        true => i - r,
+        false => r - i
+    }
+}
+
+/// Resolve an indexed part-select on a def to a range, in the
+/// direction of the selected dimension.
+///
+/// The selects not fitting in the dimensions are resolved as
+/// on a descending range, and reported elsewhere.
+#[inline]
+fn resolve_part_select(
+    def_widths: &HashMap<&str, SVerilogRange>,
+    def_dims: &HashMap<&str, Vec<SVerilogRange>>,
+    s: &str, indices: &[isize], ps: SVerilogPartSelect
+) -> SVerilogRange {
+    let decl = match def_dims.get(s) {
+        Some(dims) => dims.get(indices.len()).copied(),
+        None if indices.is_empty() => def_widths.get(s).copied(),
+        None => None
+    };
+    ps.resolve(decl.unwrap_or(SVerilogRange(0, 0)))
+}
+
+/// Check whether a select fits in the dimensions.
+#[inline]
+fn select_fits(
+    dims: &[SVerilogRange], indices: &[isize], range: Option<SVerilogRange>
+) -> bool {
+    indices.len() + range.is_some() as usize <= dims.len()
+}
+
+/// Enumerate the flattened bits of a select on a
+/// multi-dimensional def, MSB first.
+///
+/// The select should fit in the dimensions.
+fn flat_select(
+    dims: &[SVerilogRange], indices: &[isize], range: Option<SVerilogRange>
+) -> impl Iterator<Item = isize> {
+    let m = indices.len();
+    let base = indices.iter().enumerate()
+        .map(|(k, &i)| range_pos(dims[k], i) * dims_len(&dims[k + 1..]) as isize)
+        .sum::<isize>();
+    let blocks = match range {
+        None => Either::Left(Some((base, dims_len(&dims[m..]))).into_iter()),
+        Some(range) => {
+            let (dim, size) = (dims[m], dims_len(&dims[m + 1..]));
+            Either::Right(range.map(move |i| {
+                (base + range_pos(dim, i) * size as isize, size)
+            }))
+        }
+    };
+    blocks.flat_map(|(start, size)| (start..start + size as isize).rev())
 }
 
 /// Evaluate the length of an expr, based on the preprocessed widths.
 ///
 /// This is intended to be fast, as no need to enumerate the slice indices.
-fn eval_expr_len(
-    def_widths: &HashMap<CompactString, SVerilogRange>,
-    expr: &Wirexpr
+fn eval_expr_len<S: AsRef<str>>(
+    def_widths: &HashMap<&str, SVerilogRange>,
+    def_dims: &HashMap<&str, Vec<SVerilogRange>>,
+    expr: &Wirexpr<S>
 ) -> usize {
-    #[inline]
-    fn len_basic(
-        def_widths: &HashMap<CompactString, SVerilogRange>,
-        exprbasic: &WirexprBasic
-    ) -> usize {
+    let len_basic = |exprbasic: &WirexprBasic<S>| -> usize {
         use WirexprBasic::*;
         match exprbasic {
             Full(s) => {
-                match def_widths.get(s.as_str()) {
+                match def_widths.get(s.as_ref()) {
                     Some(range) => range.len(),
                     None => 1
                 }
             },
-            SingleBit(_, _) => 1,
-            Slice(_, range) => range.len(),
+            SingleBit(s, _) => match def_dims.get(s.as_ref()) {
+                Some(dims) => dims_len(&dims[1..]),
+                None => 1
+            },
+            Slice(s, range) => match def_dims.get(s.as_ref()) {
+                Some(dims) => range.len() * dims_len(&dims[1..]),
+                None => range.len()
+            },
+            Select(s, indices, range) => match def_dims.get(s.as_ref()) {
+                Some(dims) if select_fits(dims, indices, *range) => {
+                    let m = indices.len();
+                    match range {
+                        None => dims_len(&dims[m..]),
+                        Some(range) => range.len() * dims_len(&dims[m + 1..])
+                    }
+                }
+                _ => 0
+            },
+            PartSelect(s, indices, ps) => match def_dims.get(s.as_ref()) {
+                Some(dims) if indices.len() < dims.len() =>
+                    ps.width * dims_len(&dims[indices.len() + 1..]),
+                None if indices.is_empty() => ps.width,
+                _ => 0
+            },
             Literal(size, _, _) => *size
         }
-    }
+    };
     use Wirexpr::*;
     match expr {
-        Basic(basic) => len_basic(def_widths, basic),
-        Concat(v) => v.iter().map(|b| len_basic(def_widths, b)).sum()
+        Basic(basic) => len_basic(basic),
+        Concat(v) => v.iter().map(len_basic).sum()
     }
 }
 
-impl ModuleMap {
-    pub fn from(m: &SVerilogModule) -> ModuleMap {
+impl<'i> ModuleMap<'i> {
+    pub fn from<S: AsRef<str>>(m: &'i SVerilogModule<S>) -> ModuleMap<'i> {
         // compute def widths:
         // just create a map of all defs that have nontrivial widths
-        let def_widths: HashMap<CompactString, SVerilogRange> = m.defs.iter()
-            .filter_map(|SVerilogWireDef{name, width, ..}| {
-                let w = width.as_ref()?.clone();
-                Some((name.clone(), w))
+        let def_widths: HashMap<&str, SVerilogRange> = m.defs.iter()
+            .filter_map(|def| {
+                let w = def_flat_width(def)?;
+                Some((def.name.as_ref(), w))
             })
             .collect();
+        let def_dims: HashMap<&str, Vec<SVerilogRange>> = m.defs.iter()
+            .filter(|def| def.dims().nth(1).is_some())
+            .map(|def| (def.name.as_ref(), def.dims().collect()))
+            .collect();
 
         // compute def types:
         // the tricky thing is that an input/output port may later
-        // be defined as wire again (which is then ignored).
-        let mut def_types = HashMap::with_capacity(m.defs.len());
+        // be defined as wire (or other net types) again, which is
+        // then ignored.
+        let mut def_types = HashMap::<&str, WireDefType>::with_capacity(m.defs.len());
         for SVerilogWireDef{name, typ, ..} in &m.defs {
-            use WireDefType::*;
-            match def_types.get_mut(name) {
+            match def_types.get_mut(name.as_ref()) {
                 Some(v) => {
-                    match (&v, typ) {
-                        (Wire, Input | Output | InOut) => { *v = *typ; }
-                        (_, Wire) => {}
-                        _ => { assert_eq!(v, typ, "conflicting def"); }
+                    match (v.is_port(), typ.is_port()) {
+                        (false, true) => { *v = *typ; }
+                        (true, false) => {}
+                        // a supply net type is kept over other net types.
+                        (false, false) => if typ.supply_value().is_some() {
+                            *v = *typ;
+                        }
+                        (true, true) => { assert_eq!(v, typ, "conflicting def"); }
                     }
                 }
-                None => { def_types.insert(name.clone(), *typ); }
+                None => { def_types.insert(name.as_ref(), *typ); }
             }
         }
 
@@ -170,8 +345,8 @@ impl ModuleMap {
         let port_widths = m.ports.iter().filter_map(|def| {
             use SVerilogPortDef::*;
             match def {
-                Basic(name) => match def_widths.get(name.as_str()) {
-                    Some(w) => Some((name.clone(), *w)),
+                Basic(name) => match def_widths.get(name.as_ref()) {
+                    Some(w) => Some((name.as_ref(), *w)),
                     None => None
                 },
                 Conn(name, expr) => {
@@ -180,13 +355,13 @@ impl ModuleMap {
                     // it is a scalar or a vector.
                     //
                     // Here, we mimic the behavior of CadXX InnoXX.
-                    let width = eval_expr_len(&def_widths, expr);
+                    let width = eval_expr_len(&def_widths, &def_dims, expr);
 
                     // 1. if width \> 1, it is certainly a vector.
                     //    the vector is always indexed from 0.
                     //    note that it is reversed, [len - 1, 0] is right.
                     if width > 1 {
-                        return Some((name.clone(),
+                        return Some((name.as_ref(),
                                      SVerilogRange(width as isize - 1, 0)))
                     }
 
@@ -194,12 +369,15 @@ impl ModuleMap {
                     //    iff it contains a vector. otherwise it is
                     //    a scalar. it is not related to whether
                     //    curly braces are used.
-                    let expr_basic_has_vector = |eb: &WirexprBasic| -> bool {
+                    let expr_basic_has_vector = |eb: &WirexprBasic<S>| -> bool {
                         use WirexprBasic::*;
                         match eb {
-                            Full(name) => def_widths.contains_key(name),
-                            SingleBit(_, _) => false,
+                            Full(name) => def_widths.contains_key(name.as_ref()),
+                            SingleBit(name, _) => def_dims.contains_key(name.as_ref()),
                             Slice(_, _) => true,
+                            Select(name, indices, range) => range.is_some() ||
+                                def_dims.get(name.as_ref()).is_some_and(|d| indices.len() < d.len()),
+                            PartSelect(_, _, _) => true,
                             Literal(_, _, _) => false
                         }
                     };
@@ -209,35 +387,72 @@ impl ModuleMap {
                         Concat(v) => v.iter().any(expr_basic_has_vector)
                     };
                     match has_vector {
-                        true => Some((name.clone(), SVerilogRange(0, 0))),
+                        true => Some((name.as_ref(), SVerilogRange(0, 0))),
                         false => None
                     }
                 }
             }
         }).collect();
-        
-        ModuleMap { def_widths, def_types, port_widths }
+
+        // name the unnamed gate primitive instances.
+        let cell_names = m.cells.iter().map(|c| c.cell_name.as_ref())
+            .collect::<HashSet<_>>();
+        let mut num_gates = 0;
+        let gate_names = m.cells.iter().enumerate()
+            .filter(|(_, c)| c.cell_name.as_ref().is_empty())
+            .map(|(cellidx, c)| loop {
+                let name = format!("${}${}", c.macro_name.as_ref(), num_gates);
+                num_gates += 1;
+                if !cell_names.contains(name.as_str()) {
+                    break (cellidx, name.into())
+                }
+            })
+            .collect();
+
+        ModuleMap { def_widths, def_dims, def_types, port_widths, gate_names }
     }
 
-    pub fn eval_expr<'a>(
-        &'a self, expr: &'a Wirexpr
+    pub fn eval_expr<'a, S: AsRef<str>>(
+        &'a self, expr: &'a Wirexpr<S>
     ) -> impl Iterator<Item = ExprBit<'a>> + 'a {
         use Either::*;
         #[inline]
-        fn eval_basic<'a>(
-            mm: &'a ModuleMap, exprbasic: &'a WirexprBasic,
+        fn eval_basic<'a, S: AsRef<str>>(
+            mm: &'a ModuleMap, exprbasic: &'a WirexprBasic<S>,
         ) -> impl Iterator<Item = ExprBit<'a>> + 'a {
             use WirexprBasic::*;
             use ExprBit::*;
-            let index_map = |s: &'a CompactString| move |i| Var(s, Some(i));
+            let index_map = |s: &'a str| move |i| Var(s, Some(i));
+            let select = |s: &'a str, indices, range| match mm.def_dims.get(s) {
+                Some(dims) if select_fits(dims, indices, range) => Right(Right(
+                    flat_select(dims, indices, range).map(index_map(s)))),
+                // invalid selects are reported in [ModuleMap::check_selects].
+                _ => Left(Left(None.into_iter()))
+            };
             match exprbasic {
-                Full(s) => match mm.def_widths.get(s.as_str()) {
-                    Some(range) => Right(Left(range.map(index_map(s)))),
-                    None => Left(Some(Var(s, None)).into_iter())
+                Full(s) => match mm.def_widths.get(s.as_ref()) {
+                    Some(range) => Left(Right(range.map(index_map(s.as_ref())))),
+                    None => Left(Left(Some(Var(s.as_ref(), None)).into_iter()))
                 },
-                SingleBit(s, i) => Left(Some(Var(s, Some(*i))).into_iter()),
-                Slice(s, range) => Right(Left(range.map(index_map(s)))),
-                Literal(size, value, is_xz) => Right(Right({
+                SingleBit(s, i) => match mm.def_dims.contains_key(s.as_ref()) {
+                    true => select(s.as_ref(), std::slice::from_ref(i), None),
+                    false => Left(Left(Some(Var(s.as_ref(), Some(*i))).into_iter()))
+                },
+                Slice(s, range) => match mm.def_dims.contains_key(s.as_ref()) {
+                    true => select(s.as_ref(), &[], Some(*range)),
+                    false => Left(Right(range.map(index_map(s.as_ref()))))
+                },
+                Select(s, indices, range) => select(s.as_ref(), indices, *range),
+                PartSelect(s, indices, ps) => {
+                    let s = s.as_ref();
+                    let range = resolve_part_select(
+                        &mm.def_widths, &mm.def_dims, s, indices, *ps);
+                    match mm.def_dims.contains_key(s) || !indices.is_empty() {
+                        true => select(s, indices, Some(range)),
+                        false => Left(Right(range.map(index_map(s))))
+                    }
+                }
+                Literal(size, value, is_xz) => Right(Left({
                     let (value, is_xz) = (*value, *is_xz);
                     (0..*size).rev()
                         .map(move |i| Const((((is_xz >> i & 1) << 1) |
@@ -256,8 +471,65 @@ impl ModuleMap {
     ///
     /// This is intended to be fast, as no need to enumerate the
     /// slice indices.
-    pub fn eval_expr_len(&self, expr: &Wirexpr) -> usize {
-        eval_expr_len(&self.def_widths, expr)
+    pub fn eval_expr_len<S: AsRef<str>>(&self, expr: &Wirexpr<S>) -> usize {
+        eval_expr_len(&self.def_widths, &self.def_dims, expr)
+    }
+
+    /// Check that the multi-index selects in an expr refer to
+    /// multi-dimensional defs, and do not have more indices
+    /// than the dimensions.
+    #[must_use]
+    pub(crate) fn check_selects<S: AsRef<str>>(&self, expr: &Wirexpr<S>) -> Option<()> {
+        let check = |eb: &WirexprBasic<S>| {
+            let (s, indices, range) = match eb {
+                WirexprBasic::Select(s, indices, range) => (s, indices, *range),
+                WirexprBasic::PartSelect(s, indices, _) if !indices.is_empty() =>
+                    (s, indices, Some(SVerilogRange(0, 0))),
+                _ => return Some(())
+            };
+            match self.def_dims.get(s.as_ref()) {
+                Some(dims) if select_fits(dims, indices, range) => Some(()),
+                _ => {
+                    clilog::error!(
+                        NL_SV_SELECT,
+                        "select {} does not match the dimensions of {}",
+                        eb, s.as_ref());
+                    None
+                }
+            }
+        };
+        use Wirexpr::*;
+        match expr {
+            Basic(eb) => check(eb),
+            Concat(v) => v.iter().try_for_each(check)
+        }
+    }
+}
+
+/// Estimate the synthetic cells lowered from an assign
+/// expression, as (width, num_cells, num_logic_pins).
+///
+/// The extra cells of a conditional operator lowered into
+/// gates are not counted.
+fn estimate_assign_size<S: AsRef<str>>(
+    mm: &ModuleMap, expr: &SVerilogExpr<S>
+) -> (usize, usize, usize) {
+    match expr {
+        SVerilogExpr::Wire(w) => (mm.eval_expr_len(w), 0, 0),
+        SVerilogExpr::Op(op, operands) => {
+            let (mut width, mut num_cells, mut num_logic_pins) = (0, 0, 0);
+            for (k, operand) in operands.iter().enumerate() {
+                let (w, c, lp) = estimate_assign_size(mm, operand);
+                // the condition does not count in the width.
+                if *op != SVerilogOp::Cond || k != 0 {
+                    width = width.max(w);
+                }
+                num_cells += c;
+                num_logic_pins += lp;
+            }
+            (width, num_cells + width,
+             num_logic_pins + width * (operands.len() + 1))
+        }
     }
 }
 
@@ -271,11 +543,11 @@ impl ModuleMap {
 /// To call this function, one need to provide a [HashSet]
 /// and a [HashMap] mutable reference.
 #[must_use]
-pub(crate) fn estimate_size<'i>(
-    modules: &'i HashMap<CompactString, (SVerilogModule, ModuleMap)>,
-    parent_modules: &mut HashSet<&'i CompactString>,
-    (cur_name, cur_m, cur_mm): (&'i CompactString, &'i SVerilogModule, &'i ModuleMap),
-    cache: &mut HashMap<&'i CompactString, (usize, usize)>
+pub(crate) fn estimate_size<'i, S: AsRef<str>>(
+    modules: &'i Modules<'i, S>,
+    parent_modules: &mut HashSet<&'i str>,
+    (cur_name, cur_m, cur_mm): (&'i str, &'i SVerilogModule<S>, &'i ModuleMap<'i>),
+    cache: &mut HashMap<&'i str, (usize, usize)>
 ) -> Option<(usize, usize)> {
     // check if the result is already cached.
     if let Some((x, y)) = cache.get(cur_name) {
@@ -300,9 +572,26 @@ pub(crate) fn estimate_size<'i>(
     // num_logic_pins is initialized with the entry ports.
     let mut num_cells = 0;
     let mut num_logic_pins = cur_m.defs.iter()
-        .map(|def| def.width.map(|r| r.len()).unwrap_or(1))
+        .map(|def| def_flat_width(def).map(|r| r.len()).unwrap_or(1))
         .sum::<usize>();
 
+    // check the multi-index selects.
+    for expr in cur_m.ports.iter()
+        .filter_map(|port| match port {
+            SVerilogPortDef::Basic(_) => None,
+            SVerilogPortDef::Conn(_, e) => Some(e)
+        })
+        .chain(cur_m.assigns.iter().flat_map(|a| {
+            std::iter::once(&a.lhs).chain(a.rhs.wirexprs())
+        }))
+        .chain(cur_m.cells.iter().flat_map(|c| {
+            c.ioports.iter().map(|(_, e)| e)
+                .chain(c.ordered_ioports.iter().flatten())
+        }))
+    {
+        cur_mm.check_selects(expr)?;
+    }
+
     // then, named port connections
     num_logic_pins += cur_m.ports.iter()
         .map(|port| match port {
@@ -311,21 +600,29 @@ pub(crate) fn estimate_size<'i>(
         })
         .sum::<usize>();
 
+    // the synthetic cells lowered from assign operators.
+    for assign in &cur_m.assigns {
+        let (_, c, lp) = estimate_assign_size(cur_mm, &assign.rhs);
+        num_cells += c;
+        num_logic_pins += lp;
+    }
+
     // dive into cells.
     // 1. for submodules, recurse and collect result.
     // 2. for leaf cells, evaluate the pin width.
     for cell in &cur_m.cells {
-        if let Some((m, mm)) = modules.get(&cell.macro_name) {
+        if let Some((m, mm)) = modules.get(cell.macro_name.as_ref()) {
             let (c, lp) = estimate_size(
                 modules, &mut parent_modules,
-                (&cell.macro_name, m, mm), cache)?;
+                (cell.macro_name.as_ref(), m, mm), cache)?;
             num_cells += c;
             num_logic_pins += lp;
         }
         else {
             num_cells += 1;
-            num_logic_pins += cell.ioports.iter()
-                .map(|(_, expr)| cur_mm.eval_expr_len(expr))
+            num_logic_pins += cell.ioports.iter().map(|(_, expr)| expr)
+                .chain(cell.ordered_ioports.iter().flatten())
+                .map(|expr| cur_mm.eval_expr_len(expr))
                 .sum::<usize>();
         }
     }
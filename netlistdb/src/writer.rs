@@ -0,0 +1,732 @@
+//! Structural verilog writer.
+
+use super::*;
+use sverilogparse::*;
+
+/// Build a bit reference to a (possibly vector) wire.
+#[inline]
+fn bit_ref(name: &CompactString, idx: Option<isize>) -> WirexprBasic {
+    match idx {
+        None => WirexprBasic::Full(name.clone()),
+        Some(i) => WirexprBasic::SingleBit(name.clone(), i)
+    }
+}
+
+/// Bits grouped by wire or pin name.
+type NameGroups<T> = Vec<(CompactString, Vec<(Option<isize>, T)>)>;
+
+/// Group `(name, idx, item)` tuples by name, keeping the order
+/// of first appearance for both names and items.
+fn group_by_name<T>(
+    iter: impl Iterator<Item = (CompactString, Option<isize>, T)>
+) -> NameGroups<T> {
+    let mut groups: NameGroups<T> = Vec::new();
+    let mut name2group = HashMap::new();
+    for (name, idx, item) in iter {
+        let gid = *name2group.entry(name.clone()).or_insert_with(|| {
+            groups.push((name, Vec::new()));
+            groups.len() - 1
+        });
+        groups[gid].1.push((idx, item));
+    }
+    groups
+}
+
+/// Compute the declared width of a group of wire bits.
+///
+/// Scalars give `None`. Vectors are declared in descending order
+/// covering all the bits that appear.
+fn group_width<T>(bits: &[(Option<isize>, T)]) -> Option<SVerilogRange> {
+    let idxs = bits.iter().filter_map(|(idx, _)| *idx);
+    let (l, r) = idxs.fold(None, |acc, i| match acc {
+        None => Some((i, i)),
+        Some((l, r)) => Some((l.max(i), r.min(i)))
+    })?;
+    Some(SVerilogRange(l, r))
+}
+
+/// Build the wire expression connected to a group of pins.
+///
+/// A single-bit group is connected directly, and a wider group
+/// is concatenated in the original order of pin creation.
+fn pins_expr(bit_refs: impl Iterator<Item = WirexprBasic>) -> Wirexpr {
+    let mut v: Vec<_> = bit_refs.collect();
+    match v.len() {
+        1 => Wirexpr::Basic(v.swap_remove(0)),
+        _ => Wirexpr::Concat(v)
+    }
+}
+
+/// An item inside a reconstructed module.
+#[derive(Debug, Clone, Copy)]
+enum HierItem {
+    /// A leaf cell, by cell id.
+    Leaf(usize),
+    /// A submodule instance, by node id.
+    Sub(usize)
+}
+
+/// A module instance in the reconstructed tree.
+/// Node ids are the instance ids in [NetlistDB::hiernames].
+struct HierNode {
+    /// The parent node id. The top node points to itself.
+    parent: usize,
+    /// The depth in tree, 0 for the top node.
+    depth: usize,
+    /// Leaf cells and submodule instances, in netlist order.
+    items: Vec<HierItem>,
+}
+
+/// A reconstructed module definition, ready to be instantiated
+/// by its parent.
+struct HierModule {
+    /// The module name, after deduplication.
+    name: CompactString,
+    /// Port bits grouped by port name, with net ids.
+    ports: NameGroups<usize>,
+}
+
+/// Attach a node to its parent, after attaching the parent.
+fn attach_hier_node(nodes: &mut [HierNode], attached: &mut [bool], u: usize) {
+    if u == 0 || attached[u] {
+        return
+    }
+    attached[u] = true;
+    let parent = nodes[u].parent;
+    attach_hier_node(nodes, attached, parent);
+    nodes[parent].items.push(HierItem::Sub(u));
+}
+
+/// Check if a group of port bits can be declared as one port.
+///
+/// It must be a scalar, or a vector without holes.
+fn is_dense_group<T>(bits: &[(Option<isize>, T)]) -> bool {
+    match group_width(bits) {
+        None => bits.len() == 1,
+        Some(range) => {
+            let idxs = bits.iter().filter_map(|(idx, _)| *idx)
+                .collect::<HashSet<_>>();
+            idxs.len() == bits.len() && idxs.len() == range.len()
+        }
+    }
+}
+
+impl NetlistDB {
+    /// Get the flattened verilog identifier of a hierarchical name,
+    /// e.g., `a/b/c`.
+    fn flat_ident(hier: &HierName, name: &CompactString) -> CompactString {
+        match hier.is_empty() {
+            true => name.clone(),
+            false => format!("{}/{}", hier, name).into()
+        }
+    }
+
+    /// Decide the flat name and bit index of each net.
+    ///
+    /// Nets connected to top-level ports are named after the
+    /// first such port. Others inherit their best name in
+    /// [NetlistDB::netnames], or get a generated name if
+    /// they have none. Generated names get suffixes like
+    /// `netlistdb_net_3_1` if they are already used.
+    fn flat_net_names(&self) -> Vec<(CompactString, Option<isize>)> {
+        let mut names = vec![None; self.num_nets];
+        for pinid in self.cell2pin.iter_set(0) {
+            let netid = self.pin2net[pinid];
+            if netid != NET_DISCONNECTED && names[netid].is_none() {
+                let (_, name, idx) = &self.pinnames[pinid];
+                names[netid] = Some((name.clone(), *idx));
+            }
+        }
+        for (netid, name) in names.iter_mut().enumerate() {
+            if name.is_some() {
+                continue
+            }
+            if let Some((hier, n, idx)) = self.netnames.get(netid) {
+                if !n.is_empty() {
+                    *name = Some((NetlistDB::flat_ident(hier, n), *idx));
+                }
+            }
+        }
+        let mut used_names = names.iter().flatten()
+            .map(|(name, _)| name.clone())
+            .collect::<HashSet<_>>();
+        names.into_iter().enumerate().map(|(netid, name)| {
+            if let Some(name) = name {
+                return name
+            }
+            let base_name = format!("netlistdb_net_{}", netid);
+            let mut name = CompactString::from(base_name.as_str());
+            let mut suffix = 0;
+            while !used_names.insert(name.clone()) {
+                suffix += 1;
+                name = format!("{}_{}", base_name, suffix).into();
+            }
+            (name, None)
+        }).collect()
+    }
+
+    /// Collect the attributes of the nets in a wire definition.
+    fn nets_attrs(&self, nets: impl Iterator<Item = usize>) -> SVerilogAttrs {
+        let mut attrs = Vec::new();
+        for netid in nets.filter(|&netid| netid != NET_DISCONNECTED) {
+            merge_attrs(&mut attrs, &self.netattrs[netid]);
+        }
+        attrs
+    }
+
+    /// Get the top-level port pins grouped by port name.
+    fn top_port_groups(&self) -> NameGroups<usize> {
+        group_by_name(
+            self.cell2pin.iter_set(0).map(|pinid| {
+                let (_, name, idx) = &self.pinnames[pinid];
+                (name.clone(), *idx, pinid)
+            }))
+    }
+
+    /// Push the definitions of top-level ports.
+    ///
+    /// Ports that share a net with a previous port are connected
+    /// to it by assigns.
+    fn push_top_port_defs<'i>(
+        &self, ports: &NameGroups<usize>,
+        net_name: impl Fn(usize) -> &'i (CompactString, Option<isize>),
+        defs: &mut Vec<SVerilogWireDef>,
+        assigns: &mut Vec<SVerilogAssign>
+    ) {
+        for (name, bits) in ports {
+            use Direction::*;
+            let typ = match self.pindirect[bits[0].1] {
+                // a top-level input port drives the net.
+                O => WireDefType::Input,
+                I => WireDefType::Output,
+                InOut | Unknown => WireDefType::InOut
+            };
+            defs.push(SVerilogWireDef {
+                name: name.clone(), width: group_width(bits), typ,
+                signed: false, packed_dims: Vec::new(), unpacked_dims: Vec::new(),
+                attrs: self.nets_attrs(
+                    bits.iter().map(|&(_, pinid)| self.pin2net[pinid]))
+            });
+            for &(idx, pinid) in bits {
+                if self.pin2net[pinid] == NET_DISCONNECTED {
+                    continue
+                }
+                let (net_name, net_idx) = net_name(self.pin2net[pinid]);
+                if net_name == name && *net_idx == idx {
+                    continue
+                }
+                let port = Wirexpr::Basic(bit_ref(name, idx));
+                let net = Wirexpr::Basic(bit_ref(net_name, *net_idx));
+                assigns.push(match typ {
+                    WireDefType::Output => SVerilogAssign {
+                        lhs: port, rhs: net.into(), attrs: Vec::new()
+                    },
+                    _ => SVerilogAssign { lhs: net, rhs: port.into(), attrs: Vec::new() }
+                });
+            }
+        }
+    }
+
+    /// Build the tie-off assign of a constant net, if it is
+    /// one of the constant nets (see [NetlistDB::net_constant]).
+    fn tie_off(
+        &self, netid: usize, (name, idx): &(CompactString, Option<isize>)
+    ) -> Option<SVerilogAssign> {
+        let c = self.net_constant(netid)?;
+        Some(SVerilogAssign {
+            lhs: Wirexpr::Basic(bit_ref(name, *idx)),
+            rhs: Wirexpr::Basic(WirexprBasic::Literal(
+                1, (c & 1) as u128, (c >> 1) as u128)).into(),
+            attrs: Vec::new()
+        })
+    }
+
+    /// Build the positional connections of a gate primitive
+    /// cell, if all its pins are the implicit pins.
+    fn primitive_ioports<'i>(
+        &self, cellid: usize,
+        net_name: impl Fn(usize) -> &'i (CompactString, Option<isize>)
+    ) -> Option<Vec<Option<Wirexpr>>> {
+        let primitive = GatePrimitive::from_name(&self.celltypes[cellid])?;
+        let names = primitive.pin_names(self.cell2pin.len(cellid))?;
+        let mut ordered = vec![None; names.len()];
+        for pinid in self.cell2pin.iter_set(cellid) {
+            let (_, name, idx) = &self.pinnames[pinid];
+            let pos = names.iter().position(|n| n == name)
+                .filter(|_| idx.is_none())?;
+            if self.pin2net[pinid] != NET_DISCONNECTED {
+                let (net_name, net_idx) = net_name(self.pin2net[pinid]);
+                ordered[pos] = Some(Wirexpr::Basic(bit_ref(net_name, *net_idx)));
+            }
+        }
+        Some(ordered)
+    }
+
+    /// Build the instantiation of a leaf cell.
+    ///
+    /// Disconnected pins are omitted. Gate primitives are
+    /// connected by position.
+    fn leaf_cell_sv<'i>(
+        &self, cellid: usize, cell_name: CompactString,
+        net_name: impl Fn(usize) -> &'i (CompactString, Option<isize>)
+    ) -> SVerilogCell {
+        if let Some(ordered_ioports) = self.primitive_ioports(cellid, &net_name) {
+            return SVerilogCell {
+                macro_name: self.celltypes[cellid].clone(),
+                cell_name,
+                params: self.cellparams[cellid].clone(),
+                ioports: Vec::new(),
+                ordered_ioports,
+                attrs: self.cellattrs[cellid].clone()
+            }
+        }
+        let pins = group_by_name(
+            self.cell2pin.iter_set(cellid)
+                .filter(|&pinid| self.pin2net[pinid] != NET_DISCONNECTED)
+                .map(|pinid| {
+                    let (_, name, idx) = &self.pinnames[pinid];
+                    (name.clone(), *idx, pinid)
+                }));
+        SVerilogCell {
+            macro_name: self.celltypes[cellid].clone(),
+            cell_name,
+            params: self.cellparams[cellid].clone(),
+            ioports: pins.into_iter().map(|(name, bits)| {
+                (name, pins_expr(bits.iter().map(|&(_, pinid)| {
+                    let (net_name, net_idx) = net_name(self.pin2net[pinid]);
+                    bit_ref(net_name, *net_idx)
+                })))
+            }).collect(),
+            ordered_ioports: Vec::new(),
+            attrs: self.cellattrs[cellid].clone()
+        }
+    }
+
+    /// Convert the netlist to a flattened structural verilog
+    /// module.
+    ///
+    /// The result contains top-level ports, one wire per net,
+    /// leaf cell instances, and constant tie-offs for
+    /// 0, 1, x and z nets (see [NetlistDB::net_constant]).
+    /// Hierarchical cell and net names are flattened into
+    /// escaped identifiers like `\u_core/u_alu/n1 `.
+    pub fn to_sverilog(&self) -> SVerilog {
+        let net_names = self.flat_net_names();
+        let ports = self.top_port_groups();
+
+        let mut defs = Vec::new();
+        let mut assigns = Vec::new();
+        self.push_top_port_defs(
+            &ports, |netid| &net_names[netid], &mut defs, &mut assigns);
+
+        // wires, excluding those already defined as ports.
+        let port_names = ports.iter().map(|(name, _)| name)
+            .collect::<HashSet<_>>();
+        let wires = group_by_name(
+            net_names.iter().enumerate()
+                .filter(|(_, (name, _))| !port_names.contains(name))
+                .map(|(netid, (name, idx))| (name.clone(), *idx, netid)));
+        for (name, bits) in &wires {
+            defs.push(SVerilogWireDef {
+                name: name.clone(), width: group_width(bits),
+                typ: WireDefType::Wire,
+                signed: false, packed_dims: Vec::new(), unpacked_dims: Vec::new(),
+                attrs: self.nets_attrs(bits.iter().map(|&(_, netid)| netid))
+            });
+        }
+
+        // constant tie-offs.
+        assigns.extend(net_names.iter().enumerate()
+                       .filter_map(|(netid, name)| self.tie_off(netid, name)));
+
+        // leaf cells.
+        let cells = (1..self.num_cells).map(|cellid| {
+            self.leaf_cell_sv(
+                cellid, format!("{}", self.cellnames[cellid]).into(),
+                |netid| &net_names[netid])
+        }).collect();
+
+        SVerilog {
+            modules: vec![(self.name.clone(), SVerilogModule {
+                ports: ports.into_iter()
+                    .map(|(name, _)| SVerilogPortDef::Basic(name))
+                    .collect(),
+                params: Vec::new(),
+                defs, assigns, cells,
+                attrs: Vec::new()
+            })]
+        }
+    }
+
+    /// Write the netlist as a flattened structural verilog module.
+    ///
+    /// See [NetlistDB::to_sverilog] for the content of output.
+    pub fn write_sverilog(
+        &self, mut w: impl std::io::Write
+    ) -> std::io::Result<()> {
+        write!(w, "{}", self.to_sverilog())
+    }
+
+    /// Build the instance tree from the module instances in
+    /// [NetlistDB::hiernames].
+    ///
+    /// The items of each node are in the order of the leaf cells.
+    /// Instances without leaf cells come after the others.
+    fn build_hier_nodes(&self) -> Vec<HierNode> {
+        let mut nodes = Vec::<HierNode>::with_capacity(self.num_hiers);
+        for h in 0..self.num_hiers {
+            let parent = self.hier2parent[h];
+            nodes.push(HierNode {
+                parent,
+                depth: if h == 0 { 0 } else { nodes[parent].depth + 1 },
+                items: Vec::new()
+            });
+        }
+        let mut attached = vec![false; self.num_hiers];
+        for cellid in 1..self.num_cells {
+            let h = self.cell2hier[cellid];
+            attach_hier_node(&mut nodes, &mut attached, h);
+            nodes[h].items.push(HierItem::Leaf(cellid));
+        }
+        for h in 1..self.num_hiers {
+            attach_hier_node(&mut nodes, &mut attached, h);
+        }
+        nodes
+    }
+
+    /// Convert the netlist to structural verilog, preserving the
+    /// module hierarchy in [NetlistDB::hiernames].
+    ///
+    /// Each submodule instance is rebuilt as a module whose ports
+    /// are the nets crossing its boundary, named after the net
+    /// names inside that instance.
+    /// Instances with identical content share one module
+    /// definition, named after the module of the first instance
+    /// (see [NetlistDB::hiertypes]). Instances of one module can
+    /// differ in content, e.g., after ECO commands, and the
+    /// definitions after the first one get suffixes like `alu_1`.
+    ///
+    /// Submodules come before their parents, and the top module
+    /// is the last one.
+    pub fn to_sverilog_hier(&self) -> SVerilog {
+        let nodes = self.build_hier_nodes();
+        let node_of_pin = |pinid: usize| self.cell2hier[self.pin2cell[pinid]];
+        let is_const = |netid| self.net_constant(netid).is_some();
+
+        // the instances with ports on each net.
+        let mut net2hiers = vec![Vec::new(); self.num_nets];
+        for h in 1..self.num_hiers {
+            for logicpinid in self.hier2ports.iter_set(h) {
+                let netid = self.logicpin2net[logicpinid];
+                if netid != NET_DISCONNECTED {
+                    net2hiers[netid].push(h);
+                }
+            }
+        }
+
+        // find the nets crossing each instance boundary.
+        // a net crosses all instances between its pins and the
+        // lowest common ancestor of them, and the instances with
+        // ports on it, which keeps the ports of instances without
+        // leaf cells.
+        // constant nets never cross, as they are tied locally.
+        let mut crossing = vec![HashSet::new(); nodes.len()];
+        let mut mark = vec![usize::MAX; nodes.len()];
+        for netid in (0..self.num_nets).filter(|&n| !is_const(n)) {
+            let lca = self.net2pin.iter_set(netid).map(node_of_pin)
+                .chain(net2hiers[netid].iter().map(|&h| nodes[h].parent))
+                .reduce(|mut a, mut b| {
+                    while nodes[a].depth > nodes[b].depth { a = nodes[a].parent; }
+                    while nodes[b].depth > nodes[a].depth { b = nodes[b].parent; }
+                    while a != b {
+                        a = nodes[a].parent;
+                        b = nodes[b].parent;
+                    }
+                    a
+                });
+            let lca = match lca {
+                Some(lca) => lca,
+                None => continue
+            };
+            let starts = self.net2pin.iter_set(netid).map(node_of_pin)
+                .chain(net2hiers[netid].iter().copied());
+            for mut u in starts {
+                while u != lca && mark[u] != netid {
+                    mark[u] = netid;
+                    crossing[u].insert(netid);
+                    u = nodes[u].parent;
+                }
+            }
+        }
+
+        // the driver of a net is its root, if any.
+        // a net driven by inout pins is declared as inout.
+        let driver = |netid: usize| {
+            self.net_driver(netid)
+                .filter(|&pinid| self.pindirect[pinid] == Direction::O)
+        };
+        let is_inside = |mut u: usize, node: usize| {
+            while nodes[u].depth > nodes[node].depth {
+                u = nodes[u].parent;
+            }
+            u == node
+        };
+
+        // the smallest net name inside each hierarchy.
+        let mut aliases = HashMap::<(usize, &HierName), (&CompactString, Option<isize>)>::new();
+        for ((hier, name, idx), &netid) in &self.netname2id {
+            let alias = aliases.entry((netid, hier)).or_insert((name, *idx));
+            if (name, *idx) < *alias {
+                *alias = (name, *idx);
+            }
+        }
+
+        let mut used_names = self.celltypes.iter().cloned()
+            .collect::<HashSet<_>>();
+        let mut modules = Vec::<(CompactString, SVerilogModule)>::new();
+        let mut body2name = HashMap::<String, CompactString>::new();
+        let mut node2module = vec![usize::MAX; nodes.len()];
+        let mut hier_modules = Vec::<HierModule>::new();
+
+        // visit the nodes in post-order, so that children are
+        // built before their parents, and the module definitions
+        // are named after their first instances.
+        fn post_order(nodes: &[HierNode], u: usize, order: &mut Vec<usize>) {
+            for item in &nodes[u].items {
+                if let HierItem::Sub(v) = *item {
+                    post_order(nodes, v, order);
+                }
+            }
+            order.push(u);
+        }
+        let mut order = Vec::with_capacity(nodes.len());
+        post_order(&nodes, 0, &mut order);
+
+        for nodeid in order {
+            let node = &nodes[nodeid];
+            let hier = &self.hiernames[nodeid];
+
+            // collect the nets used in this module, in order.
+            let mut nets = Vec::new();
+            let mut nets_set = HashSet::new();
+            let top_ports = match nodeid {
+                0 => self.top_port_groups(),
+                _ => Vec::new()
+            };
+            let mut push_net = |netid| {
+                if netid != NET_DISCONNECTED && nets_set.insert(netid) {
+                    nets.push(netid);
+                }
+            };
+            for (_, bits) in &top_ports {
+                for &(_, pinid) in bits {
+                    push_net(self.pin2net[pinid]);
+                }
+            }
+            if nodeid != 0 {
+                for logicpinid in self.hier2ports.iter_set(nodeid) {
+                    let netid = self.logicpin2net[logicpinid];
+                    if crossing[nodeid].contains(&netid) {
+                        push_net(netid);
+                    }
+                }
+            }
+            for item in &node.items {
+                match *item {
+                    HierItem::Leaf(cellid) => {
+                        for pinid in self.cell2pin.iter_set(cellid) {
+                            push_net(self.pin2net[pinid]);
+                        }
+                    }
+                    HierItem::Sub(child) => {
+                        let m = &hier_modules[node2module[child]];
+                        for (_, bits) in &m.ports {
+                            for &(_, netid) in bits {
+                                push_net(netid);
+                            }
+                        }
+                    }
+                }
+            }
+
+            // name the nets.
+            let mut net_names = HashMap::with_capacity(nets.len());
+            for (name, bits) in &top_ports {
+                for &(idx, pinid) in bits {
+                    if self.pin2net[pinid] != NET_DISCONNECTED {
+                        net_names.entry(self.pin2net[pinid])
+                            .or_insert((name.clone(), idx));
+                    }
+                }
+            }
+            for &netid in &nets {
+                if let Some((name, idx)) = aliases.get(&(netid, hier)) {
+                    net_names.entry(netid).or_insert(((*name).clone(), *idx));
+                }
+            }
+            let mut used_net_names = net_names.values()
+                .map(|(name, _)| name.clone())
+                .collect::<HashSet<_>>();
+            let mut num_generated = 0;
+            for &netid in &nets {
+                net_names.entry(netid).or_insert_with(|| loop {
+                    let name = CompactString::from(
+                        format!("netlistdb_net_{}", num_generated));
+                    num_generated += 1;
+                    if used_net_names.insert(name.clone()) {
+                        break (name, None)
+                    }
+                });
+            }
+
+            // group the nets into ports and wires.
+            // a name mixing ports and wires, or a port vector with
+            // holes, is split into scalars like `\n[1] `.
+            let groups = group_by_name(nets.iter().map(|&netid| {
+                let (name, idx) = &net_names[&netid];
+                (name.clone(), *idx, netid)
+            }));
+            let mut ports = Vec::new();
+            let mut wires = Vec::new();
+            for (name, bits) in groups {
+                let num_ports = bits.iter()
+                    .filter(|(_, netid)| crossing[nodeid].contains(netid))
+                    .count();
+                if num_ports == 0 {
+                    wires.push((name, bits));
+                }
+                else if num_ports == bits.len() && is_dense_group(&bits) {
+                    ports.push((name, bits));
+                }
+                else {
+                    for (idx, netid) in bits {
+                        let name: CompactString = match idx {
+                            Some(idx) => format!("{}[{}]", name, idx).into(),
+                            None => name.clone()
+                        };
+                        net_names.insert(netid, (name.clone(), None));
+                        match crossing[nodeid].contains(&netid) {
+                            true => ports.push((name, vec![(None, netid)])),
+                            false => wires.push((name, vec![(None, netid)]))
+                        }
+                    }
+                }
+            }
+            for (_, bits) in &mut ports {
+                bits.sort_by(|(a, _), (b, _)| b.cmp(a));
+            }
+            let net_name = |netid| &net_names[&netid];
+
+            // build the module.
+            let mut defs = Vec::new();
+            let mut assigns = Vec::new();
+            if nodeid == 0 {
+                self.push_top_port_defs(
+                    &top_ports, net_name, &mut defs, &mut assigns);
+            }
+            for (name, bits) in &ports {
+                use WireDefType::*;
+                let typ = bits.iter().map(|&(_, netid)| match driver(netid) {
+                    None => InOut,
+                    Some(pinid) => match is_inside(node_of_pin(pinid), nodeid) {
+                        true => Output,
+                        false => Input
+                    }
+                }).reduce(|a, b| if a == b { a } else { InOut }).unwrap();
+                defs.push(SVerilogWireDef {
+                    name: name.clone(), width: group_width(bits), typ,
+                    signed: false, packed_dims: Vec::new(), unpacked_dims: Vec::new(),
+                    attrs: self.nets_attrs(bits.iter().map(|&(_, netid)| netid))
+                });
+            }
+            let port_names = top_ports.iter().map(|(name, _)| name)
+                .collect::<HashSet<_>>();
+            for (name, bits) in &wires {
+                if port_names.contains(name) {
+                    continue
+                }
+                defs.push(SVerilogWireDef {
+                    name: name.clone(), width: group_width(bits),
+                    typ: WireDefType::Wire,
+                    signed: false, packed_dims: Vec::new(), unpacked_dims: Vec::new(),
+                    attrs: self.nets_attrs(bits.iter().map(|&(_, netid)| netid))
+                });
+            }
+            assigns.extend(nets.iter().filter_map(|&netid| {
+                self.tie_off(netid, net_name(netid))
+            }));
+
+            let cells = node.items.iter().map(|item| match *item {
+                HierItem::Leaf(cellid) => self.leaf_cell_sv(
+                    cellid, self.cellnames[cellid].cur.clone(), net_name),
+                HierItem::Sub(child) => {
+                    let m = &hier_modules[node2module[child]];
+                    SVerilogCell {
+                        macro_name: m.name.clone(),
+                        cell_name: self.hiernames[child].cur.clone(),
+                        params: Vec::new(),
+                        ioports: m.ports.iter().map(|(name, bits)| {
+                            (name.clone(), pins_expr(bits.iter().map(|&(_, netid)| {
+                                let (net_name, net_idx) = net_name(netid);
+                                bit_ref(net_name, *net_idx)
+                            })))
+                        }).collect(),
+                        ordered_ioports: Vec::new(),
+                        attrs: Vec::new()
+                    }
+                }
+            }).collect();
+
+            let module = SVerilogModule {
+                ports: match nodeid {
+                    0 => &top_ports,
+                    _ => &ports
+                }.iter().map(|(name, _)| SVerilogPortDef::Basic(name.clone()))
+                    .collect(),
+                params: Vec::new(),
+                defs, assigns, cells,
+                attrs: Vec::new()
+            };
+
+            if nodeid == 0 {
+                modules.push((self.name.clone(), module));
+                continue
+            }
+
+            // deduplicate with existing identical modules.
+            // the port nets still differ among instances.
+            let body = format!("{}", SVerilog {
+                modules: vec![(CompactString::new_inline(""), module.clone())]
+            });
+            let name = match body2name.get(&body) {
+                Some(name) => name.clone(),
+                None => {
+                    let base_name = &self.hiertypes[nodeid];
+                    let mut name = base_name.clone();
+                    let mut suffix = 0;
+                    while !used_names.insert(name.clone()) {
+                        suffix += 1;
+                        name = format!("{}_{}", base_name, suffix).into();
+                    }
+                    body2name.insert(body, name.clone());
+                    modules.push((name.clone(), module));
+                    name
+                }
+            };
+            node2module[nodeid] = hier_modules.len();
+            hier_modules.push(HierModule { name, ports });
+        }
+
+        SVerilog { modules }
+    }
+
+    /// Write the netlist as structural verilog, preserving the
+    /// module hierarchy.
+    ///
+    /// See [NetlistDB::to_sverilog_hier] for the content of output.
+    pub fn write_sverilog_hier(
+        &self, mut w: impl std::io::Write
+    ) -> std::io::Result<()> {
+        write!(w, "{}", self.to_sverilog_hier())
+    }
+}
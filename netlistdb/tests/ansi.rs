@@ -0,0 +1,46 @@
+use netlistdb::*;
+use compact_str::CompactString;
+
+const SOURCE: &str = "
+module top (
+   input wire [1:0] a,
+   input clk,
+   output reg q, y
+);
+   wire n;
+   sub u_sub (.i(a), .o(n));
+   DFF r0 (.D(n), .CK(clk), .Q(q));
+   BUF b0 (.A(a[1]), .Y(y));
+endmodule
+
+module sub (input [1:0] i, output o);
+   AND2 g0 (.A(i[0]), .B(i[1]), .Y(o));
+endmodule
+";
+
+#[test]
+fn ansi_ports() {
+    clilog::init_stdout_simple_trace();
+
+    let directions = |_: &CompactString, pin: &CompactString, _: Option<isize>| {
+        match pin.as_str() {
+            "Y" | "Q" => Direction::O,
+            _ => Direction::I
+        }
+    };
+    let db = NetlistDB::from_sverilog_source(SOURCE, Some("top"), &directions).unwrap();
+    println!("The database: {db:#?}");
+    assert_eq!(db.num_cells, 4);
+
+    let pin = |hier: &[&str], name: &str, idx| db.pinname2id[&(
+        HierName::from_topdown_hier_iter(hier.iter().copied()), name.into(), idx)];
+    use Direction::*;
+    assert_eq!(db.pindirect[pin(&[], "a", Some(1))], O);
+    assert_eq!(db.pindirect[pin(&[], "clk", None)], O);
+    assert_eq!(db.pindirect[pin(&[], "q", None)], I);
+    assert_eq!(db.pindirect[pin(&[], "y", None)], I);
+    assert_eq!(db.pin2net[pin(&[], "a", Some(1))],
+               db.pin2net[pin(&["u_sub", "g0"], "B", None)]);
+    assert_eq!(db.pin2net[pin(&["u_sub", "g0"], "Y", None)],
+               db.pin2net[pin(&["r0"], "D", None)]);
+}
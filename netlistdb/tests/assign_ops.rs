@@ -0,0 +1,112 @@
+use netlistdb::*;
+use compact_str::CompactString;
+use sverilogparse::{SVerilogOp, SVerilogRange};
+
+const SOURCE: &str = "
+module top (a, b, s, y, z, w);
+   input [1:0] a, b;
+   input s;
+   output [1:0] y, w;
+   output z;
+   assign y = ~(a & b);
+   assign z = a[0] ^ b[0] | s;
+   assign w = s ? a : b;
+endmodule
+";
+
+#[test]
+fn gate_primitives() {
+    clilog::init_stdout_simple_trace();
+
+    // no library is needed for the default gate primitives.
+    let db = NetlistDB::from_sverilog_source(SOURCE, None, &NoDirection).unwrap();
+    println!("The database: {db:#?}");
+    // y: 2 and + 2 not. z: xor + or.
+    // w: not + 2 * (and + and + or).
+    assert_eq!(db.num_cells, 1 + 4 + 2 + 7);
+    assert!(db.pindirect.iter().all(|d| *d != Direction::Unknown));
+
+    let pin = |cell: &str, name: &str| db.pinname2id[&(
+        HierName::single(cell.into()), name.into(), None)];
+    let port = |name: &str, i: Option<isize>| db.pinname2id[&(
+        HierName::empty(), name.into(), i)];
+    let celltype = |cell: &str| db.celltypes[db.cellname2id[&HierName::single(cell.into())]].as_str();
+    // the operands are lowered first.
+    assert_eq!(celltype("$assign$0$0"), "and");
+    assert_eq!(celltype("$assign$0$2"), "not");
+    assert_eq!(db.pin2net[pin("$assign$0$0", "A1")], db.pin2net[port("a", Some(1))]);
+    assert_eq!(db.pin2net[pin("$assign$0$2", "A")], db.pin2net[pin("$assign$0$0", "Y")]);
+    assert_eq!(db.net_driver(db.pin2net[port("y", Some(1))]), Some(pin("$assign$0$2", "Y")));
+    assert_eq!(db.net_driver(db.pin2net[port("z", None)]), Some(pin("$assign$1$1", "Y")));
+    assert_eq!(celltype("$assign$2$3"), "or");
+    assert_eq!(db.net_driver(db.pin2net[port("w", Some(1))]), Some(pin("$assign$2$3", "Y")));
+
+    // the synthetic cells survive a round trip.
+    let db2 = NetlistDB::from_sverilog_source(
+        &format!("{}", db.to_sverilog()), None, &NoDirection
+    ).unwrap();
+    assert_eq!(db2.num_cells, db.num_cells);
+    assert_eq!(db2.num_nets, db.num_nets);
+}
+
+/// A library with a MUX2 cell for `?:`.
+struct Lib;
+
+impl LeafPinProvider for Lib {
+    fn direction_of(
+        &self, _: &CompactString, pin_name: &CompactString, _: Option<isize>
+    ) -> Direction {
+        match pin_name.as_str() {
+            "Y" | "Z" => Direction::O,
+            _ => Direction::I
+        }
+    }
+
+    fn width_of(&self, _: &CompactString, _: &CompactString) -> Option<SVerilogRange> {
+        None
+    }
+
+    fn assign_op_macro(&self, op: SVerilogOp) -> Option<(CompactString, Vec<CompactString>)> {
+        match op {
+            SVerilogOp::Cond => Some((
+                "MUX2".into(), vec!["Z".into(), "S".into(), "I1".into(), "I0".into()])),
+            SVerilogOp::And => Some((
+                "AND2".into(), vec!["Z".into(), "A".into(), "B".into()])),
+            _ => None
+        }
+    }
+}
+
+#[test]
+fn custom_mapping() {
+    clilog::init_stdout_simple_trace();
+
+    let db = NetlistDB::from_sverilog_source("
+module top (a, b, s, w, x);
+   input [1:0] a, b;
+   input s;
+   output [1:0] w;
+   output x;
+   assign w = s ? a : b;
+   assign x = a[1] & s;
+endmodule
+", None, &Lib).unwrap();
+    println!("The database: {db:#?}");
+    assert_eq!(&db.celltypes[1..], &["MUX2", "MUX2", "AND2"]);
+    let pin = |cell: &str, name: &str| db.pinname2id[&(
+        HierName::single(cell.into()), name.into(), None)];
+    let port = |name: &str, i: Option<isize>| db.pinname2id[&(
+        HierName::empty(), name.into(), i)];
+    assert_eq!(db.pin2net[pin("$assign$0$0", "I0")], db.pin2net[port("b", Some(1))]);
+    assert_eq!(db.pin2net[pin("$assign$0$1", "S")], db.pin2net[port("s", None)]);
+    assert_eq!(db.net_driver(db.pin2net[port("w", Some(0))]), Some(pin("$assign$0$1", "Z")));
+
+    // no macro for `|`.
+    assert!(NetlistDB::from_sverilog_source(
+        "module top (a, b, y); input a, b; output y; assign y = a | b; endmodule",
+        None, &Lib).is_none());
+    // different operand widths.
+    assert!(NetlistDB::from_sverilog_source(
+        "module top (a, b, y); input [1:0] a; input b; output y; assign y = a & b; endmodule",
+        None, &Lib).is_none());
+}
@@ -0,0 +1,60 @@
+use netlistdb::*;
+
+const SOURCE: &str = "
+module top (a, y);
+   input a;
+   (* keep *) output y;
+   (* src = \"top.v:5\" *) wire n1;
+   wire n2;
+   sub u_sub (.a(a), .y(n1));
+   (* dont_touch = \"true\" *) BUF b0 (.A(n1), .Y(y));
+   BUF b1 (.A(a), .Y(n2));
+endmodule
+
+module sub (a, y);
+   input a;
+   output y;
+   (* keep, src = \"sub.v:3\" *) wire y;
+   (* keep *) INV i0 (.A(a), .Y(y));
+endmodule
+";
+
+#[test]
+fn cell_net_attrs() {
+    clilog::init_stdout_simple_trace();
+
+    let mut db = NetlistDB::from_sverilog_source(SOURCE, Some("top"), &NoDirection).unwrap();
+    println!("The database: {db:#?}");
+    let i0 = db.cellname2id[&HierName::from_topdown_hier_iter(["u_sub", "i0"])];
+    let b0 = db.cellname2id[&HierName::single("b0".into())];
+    let b1 = db.cellname2id[&HierName::single("b1".into())];
+    assert_eq!(db.cell_attr(i0, "keep"), Some(None));
+    assert_eq!(db.cell_attr(b0, "dont_touch").flatten().map(|v| v.as_str()),
+               Some("\"true\""));
+    assert_eq!(db.cell_attr(b1, "keep"), None);
+    assert!(db.cellattrs[0].is_empty());
+
+    // the attributes of n1 and u_sub/y are collected into one net.
+    let n1 = db.netname2id[&(HierName::empty(), "n1".into(), None)];
+    let n2 = db.netname2id[&(HierName::empty(), "n2".into(), None)];
+    let y = db.netname2id[&(HierName::empty(), "y".into(), None)];
+    assert_eq!(db.net_attr(n1, "keep"), Some(None));
+    assert_eq!(db.netattrs[n1].len(), 3);
+    assert_eq!(db.net_attr(y, "keep"), Some(None));
+    assert!(db.netattrs[n2].is_empty());
+
+    // the attributes are written back.
+    let db2 = NetlistDB::from_sverilog_source(
+        &format!("{}", db.to_sverilog()), None, &NoDirection
+    ).unwrap();
+    let b0_2 = db2.cellname2id[&HierName::single("b0".into())];
+    assert_eq!(db2.cellattrs[b0_2], db.cellattrs[b0]);
+    let n1_2 = db2.netname2id[&(HierName::empty(), "n1".into(), None)];
+    assert_eq!(db2.netattrs[n1_2], db.netattrs[n1]);
+
+    // merging nets merges the attributes.
+    let n = db.merge_nets(n2, n1).unwrap();
+    assert_eq!(db.net_attr(n, "src").flatten().map(|v| v.as_str()),
+               Some("\"sub.v:3\""));
+    assert_eq!(db.netattrs.len(), db.num_nets);
+}
@@ -0,0 +1,75 @@
+//! Fixtures shared by the netlistdb integration tests.
+#![allow(dead_code)]
+
+use netlistdb::*;
+use compact_str::CompactString;
+
+/// Pin directions of the test cells: `o`, `q`, `Y` and `Q` are
+/// outputs, and all other pins are inputs.
+pub fn directions(_: &CompactString, pin: &CompactString, _: Option<isize>) -> Direction {
+    match pin.as_str() {
+        "o" | "q" | "Y" | "Q" => Direction::O,
+        _ => Direction::I
+    }
+}
+
+pub fn hier(names: &[&str]) -> HierName {
+    HierName::from_topdown_hier_iter(names.iter().copied())
+}
+
+/// Check that all maps in the database agree with each other.
+pub fn check_consistency(db: &NetlistDB) {
+    assert_eq!(db.pin2cell.len(), db.num_pins);
+    assert_eq!(db.pin2net.len(), db.num_pins);
+    assert_eq!(db.pindirect.len(), db.num_pins);
+    assert_eq!(db.pinnames.len(), db.num_pins);
+    assert_eq!(db.celltypes.len(), db.num_cells);
+    assert_eq!(db.cellnames.len(), db.num_cells);
+    assert_eq!(db.cellparams.len(), db.num_cells);
+    assert_eq!(db.cellattrs.len(), db.num_cells);
+    assert_eq!(db.cell2noutputs.len(), db.num_cells);
+    assert_eq!(db.netnames.len(), db.num_nets);
+    assert_eq!(db.netattrs.len(), db.num_nets);
+    assert_eq!(db.cell2pin.items.len(), db.num_pins);
+
+    for cellid in 0..db.num_cells {
+        for pinid in db.cell2pin.iter_set(cellid) {
+            assert_eq!(db.pin2cell[pinid], cellid);
+        }
+        assert_eq!(db.cell2noutputs[cellid], db.cell2pin.iter_set(cellid)
+                   .filter(|&p| db.pindirect[p].is_driver()).count());
+        if cellid != 0 {
+            assert_eq!(db.cellname2id[&db.cellnames[cellid]], cellid);
+            assert!(db.hier2cells.iter_set(db.cell2hier[cellid]).any(|c| c == cellid));
+        }
+    }
+    assert_eq!(db.cell2hier.len(), db.num_cells);
+    assert_eq!(db.hier2cells.items.len(), db.num_cells - 1);
+    let mut num_connected = 0;
+    for netid in 0..db.num_nets {
+        for (i, pinid) in db.net2pin.iter_set(netid).enumerate() {
+            assert_eq!(db.pin2net[pinid], netid);
+            // the net root comes first.
+            if db.pindirect[pinid] == Direction::O {
+                assert_eq!(i, 0);
+            }
+            if db.pindirect[pinid] == Direction::InOut {
+                assert!(db.net_driver(netid).is_some());
+            }
+            num_connected += 1;
+        }
+        if !db.netnames[netid].1.is_empty() {
+            assert_eq!(db.netname2id[&db.netnames[netid]], netid);
+        }
+    }
+    assert_eq!(num_connected, db.pin2net.iter()
+               .filter(|&&n| n != NET_DISCONNECTED).count());
+    for pinid in 0..db.num_pins {
+        assert_eq!(db.pinname2id[&db.pinnames[pinid]], pinid);
+        assert_eq!(db.logicpin2net[db.pinid2logicpinid[pinid]], db.pin2net[pinid]);
+    }
+    assert_eq!(db.logicpin2net.len(), db.num_logic_pins);
+    for (name, &netid) in &db.netname2id {
+        assert_eq!(db.logic_pin_net(name), Some(netid));
+    }
+}
@@ -0,0 +1,132 @@
+use netlistdb::*;
+
+mod common;
+use common::*;
+
+const SOURCE: &str = "
+module consts (o);
+   output [3:0] o;
+   wire   a, b, c, d;
+   assign {a, b} = 2'b01;
+   assign c = 1'bx;
+   assign d = 1'bz;
+   AND4 u0 ( .a(a), .b(b), .c(c), .d(d), .o(o[0]) );
+   AND4 u1 ( .a(1'b0), .b(1'b1), .c(1'bx), .d(1'bz), .o(o[1]) );
+   assign o[3:2] = 2'bxz;
+endmodule
+";
+
+#[test]
+fn xz_constants() {
+    clilog::init_stdout_simple_trace();
+
+    let db = NetlistDB::from_sverilog_source(SOURCE, None, &directions).unwrap();
+    println!("The database: {db:#?}");
+    let net_of = |cell: &str, pin: &str| db.pin2net[db.pinname2id[&(
+        HierName::single(cell.into()), pin.into(), None)]];
+    for cell in ["u0", "u1"] {
+        assert_eq!(Some(net_of(cell, "a")), db.net_zero);
+        assert_eq!(Some(net_of(cell, "b")), db.net_one);
+        assert_eq!(Some(net_of(cell, "c")), db.net_x);
+        assert_eq!(Some(net_of(cell, "d")), db.net_z);
+        assert_eq!(db.net_constant(net_of(cell, "c")), Some(2));
+        assert_eq!(db.net_constant(net_of(cell, "o")), None);
+    }
+    let o = |i| db.pin2net[db.pinname2id[&(HierName::empty(), "o".into(), Some(i))]];
+    assert_eq!(Some(o(3)), db.net_x);
+    assert_eq!(Some(o(2)), db.net_z);
+
+    // the constants survive a round trip.
+    let db2 = NetlistDB::from_sverilog_source(
+        &format!("{}", db.to_sverilog()), None, &directions
+    ).unwrap();
+    assert!(db2.net_x.is_some());
+    assert!(db2.net_z.is_some());
+    assert_eq!(db2.net2pin.len(db2.net_x.unwrap()), db.net2pin.len(db.net_x.unwrap()));
+
+    // different constants cannot be connected.
+    assert!(NetlistDB::from_sverilog_source(
+        "module bad (o); output o; assign o = 1'bx; assign o = 1'b0; endmodule",
+        None, &directions
+    ).is_none());
+}
+
+#[test]
+fn z_gives_way() {
+    clilog::init_stdout_simple_trace();
+
+    let build = |value: &str| NetlistDB::from_sverilog_source(&format!(
+        "module top (o); output o; wire n; assign n = 1'bz; assign n = {value}; \
+         BUF b0 ( .a(n), .o(o) ); endmodule"
+    ), None, &directions);
+    for (value, c) in [("1'b0", 0), ("1'b1", 1), ("1'bx", 2), ("1'bz", 3)] {
+        let db = build(value).unwrap();
+        let n = db.pin2net[db.pinname2id[&(
+            HierName::single("b0".into()), "a".into(), None)]];
+        assert_eq!(db.net_constant(n), Some(c));
+        if c != 3 {
+            assert_eq!(db.net_z, None);
+        }
+    }
+
+    // driven constants still conflict.
+    for (a, b) in [("1'b0", "1'b1"), ("1'b0", "1'bx"), ("1'b1", "1'bx")] {
+        assert!(NetlistDB::from_sverilog_source(&format!(
+            "module bad (o); output o; assign o = 1'bz; assign o = {a}; \
+             assign o = {b}; endmodule"
+        ), None, &directions).is_none());
+    }
+}
+
+const SOURCE_SUPPLY: &str = "
+module top (o);
+   output o;
+   supply0 VSS;
+   supply1 VDD;
+   tri t;
+   wire wa;
+   sub u_sub (.o(t));
+   AND4 u0 ( .a(VSS), .b(VDD), .c(t), .d(wa), .o(o) );
+   AND4 u1 ( .a(t), .b(VDD), .c(VSS), .d(VSS), .o(wa) );
+endmodule
+
+module sub (o);
+   output o;
+   supply1 vdd;
+   BUF b0 ( .a(vdd), .o(o) );
+endmodule
+";
+
+#[test]
+fn supply_nets() {
+    clilog::init_stdout_simple_trace();
+
+    let db = NetlistDB::from_sverilog_source(SOURCE_SUPPLY, Some("top"), &directions).unwrap();
+    println!("The database: {db:#?}");
+    let net_of = |cell: &[&str], pin: &str| db.pin2net[db.pinname2id[&(
+        HierName::from_topdown_hier_iter(cell.iter().copied()), pin.into(), None)]];
+    assert_eq!(Some(net_of(&["u0"], "a")), db.net_zero);
+    assert_eq!(Some(net_of(&["u0"], "b")), db.net_one);
+    assert_eq!(Some(net_of(&["u1"], "c")), db.net_zero);
+    assert_eq!(Some(net_of(&["u_sub", "b0"], "a")), db.net_one);
+    assert_eq!(db.net_constant(net_of(&["u0"], "c")), None);
+    assert_eq!(db.net_constant(net_of(&["u0"], "d")), None);
+    assert_eq!(db.net2pin.len(db.net_zero.unwrap()), 3);
+    assert_eq!(db.net2pin.len(db.net_one.unwrap()), 3);
+}
+
+#[test]
+fn wired_logic_unsupported() {
+    clilog::init_stdout_simple_trace();
+
+    for typ in ["wand", "wor"] {
+        let source = format!("
+module top (o);
+   output o;
+   {typ} w;
+   BUF b0 ( .a(w), .o(o) );
+endmodule
+");
+        assert!(NetlistDB::from_sverilog_source(&source, Some("top"), &directions).is_none());
+    }
+}
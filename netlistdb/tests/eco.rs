@@ -0,0 +1,73 @@
+use netlistdb::*;
+
+mod common;
+use common::*;
+
+#[test]
+fn buffer_insertion() {
+    clilog::init_stdout_simple_trace();
+
+    let mut db = NetlistDB::from_sverilog_file(
+        concat!(env!("CARGO_MANIFEST_DIR"), "/tests/notsimple.v"),
+        None, &directions
+    ).unwrap();
+    check_consistency(&db);
+    let (num_cells, num_pins, num_nets) = (db.num_cells, db.num_pins, db.num_nets);
+
+    // insert a buffer between f1:q and one of its sinks, dins1/u2:a.
+    let sink = db.pinname2id[&(
+        HierName::from_topdown_hier_iter(["dins1", "u2"]), "a".into(), None
+    )];
+    let net_q = db.pin2net[sink];
+    let buf = db.add_cell(
+        HierName::single("eco_buf".into()), "BUF_X1".into(),
+        [("a".into(), None), ("o".into(), None)], &directions
+    ).unwrap();
+    assert!(db.add_cell(
+        HierName::single("eco_buf".into()), "BUF_X1".into(),
+        [], &directions).is_none());
+    let net_buf = db.add_net(
+        HierName::empty(), "eco_net".into(), None).unwrap();
+    // nets can only be put in existing module instances.
+    assert!(db.add_net(
+        HierName::single("no_such_inst".into()), "eco_net".into(), None).is_none());
+    let buf_a = db.pinname2id[&(HierName::single("eco_buf".into()), "a".into(), None)];
+    let buf_o = buf_a + 1;
+    db.connect_pin(buf_a, net_q).unwrap();
+    db.connect_pin(buf_o, net_buf).unwrap();
+    db.connect_pin(sink, net_buf).unwrap();
+    check_consistency(&db);
+    assert_eq!((db.num_cells, db.num_pins, db.num_nets),
+               (num_cells + 1, num_pins + 2, num_nets + 1));
+    assert_eq!(db.net_driver(net_buf), Some(buf_o));
+    assert_eq!(db.cell2noutputs[buf], 1);
+    // a second driver is rejected.
+    let f1_q = db.pinname2id[&(HierName::single("f1".into()), "q".into(), None)];
+    assert!(db.connect_pin(f1_q, net_buf).is_none());
+
+    // the modified netlist can be written and read back.
+    let db2 = NetlistDB::from_sverilog_source(
+        &format!("{}", db.to_sverilog()), None, &directions
+    ).unwrap();
+    assert_eq!(db2.num_cells, db.num_cells);
+    assert_eq!(db2.num_pins, db.num_pins);
+
+    // undo the buffer insertion.
+    db.remove_cell(buf).unwrap();
+    check_consistency(&db);
+    let net_q = db.merge_nets(net_q, net_buf).unwrap();
+    check_consistency(&db);
+    assert_eq!((db.num_cells, db.num_pins, db.num_nets),
+               (num_cells, num_pins, num_nets));
+    assert_eq!(db.pin2net[sink], net_q);
+    assert_eq!(db.net_driver(net_q), Some(f1_q));
+    assert_eq!(db.netname2id[&(HierName::empty(), "eco_net".into(), None)], net_q);
+
+    // removing a cell before others shifts the indices.
+    let u1 = db.cellname2id[&HierName::single("u1".into())];
+    db.disconnect_pin(db.pinname2id[&(HierName::single("u1".into()), "o".into(), None)]).unwrap();
+    db.remove_cell(u1).unwrap();
+    check_consistency(&db);
+    assert_eq!(db.num_cells, num_cells - 1);
+    assert_eq!(db.cellname2id[&HierName::single("f1".into())], u1);
+}
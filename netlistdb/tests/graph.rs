@@ -0,0 +1,109 @@
+use netlistdb::*;
+use compact_str::CompactString;
+use std::collections::HashSet;
+
+mod common;
+use common::*;
+
+const PIPELINE: &str = "
+module top (clk, a, b, y);
+   input clk, a, b;
+   output y;
+   wire n1, n2, q;
+   INV i0 (.A(a), .Y(n1));
+   AND2 g0 (.A(n1), .B(b), .Y(n2));
+   DFF r0 (.CK(clk), .D(n2), .Q(q));
+   INV i1 (.A(q), .Y(y));
+endmodule
+";
+
+const RING: &str = "
+module ring (a, y);
+   input a;
+   output y;
+   wire n1, n2;
+   AND2 g0 (.A(a), .B(y), .Y(n1));
+   INV i0 (.A(n1), .Y(n2));
+   INV i1 (.A(n2), .Y(y));
+endmodule
+";
+
+fn is_sequential(macro_name: &CompactString) -> bool {
+    *macro_name == "DFF"
+}
+
+#[test]
+fn graph_pipeline() {
+    clilog::init_stdout_simple_trace();
+
+    let db = NetlistDB::from_sverilog_source(PIPELINE, None, &directions).unwrap();
+    let cell = |name: &str| db.cellname2id[&HierName::single(name.into())];
+    let pin = |cell: &str, pin: &str| db.pinname2id[&(
+        HierName::single(cell.into()), pin.into(), None)];
+    let port = |name: &str| db.pinname2id[&(
+        HierName::empty(), name.into(), None)];
+    let (i0, g0, r0, i1) = (cell("i0"), cell("g0"), cell("r0"), cell("i1"));
+
+    let cells = db.cell_graph(&is_sequential);
+    assert_eq!(cells.num_nodes, db.num_cells);
+    assert_eq!(cells.fanouts.iter_set(0).collect::<Vec<_>>(), [i0, g0]);
+    assert_eq!(cells.fanins.iter_set(g0).collect::<Vec<_>>(), [0, i0]);
+    assert_eq!(cells.fanins.len(r0), 0);
+    let lv = cells.levelize().unwrap();
+    assert_eq!(lv.num_levels, 3);
+    assert_eq!(lv.levels[0], 0);
+    assert_eq!(lv.levels[r0], 0);
+    assert_eq!(lv.levels[i0], 1);
+    assert_eq!(lv.levels[i1], 1);
+    assert_eq!(lv.levels[g0], 2);
+    assert_eq!(lv.level2nodes.iter_set(2).collect::<Vec<_>>(), [g0]);
+    let mut fanin_g0 = vec![0, i0, g0];
+    fanin_g0.sort();
+    assert_eq!(cells.fanin_cone([g0]), fanin_g0);
+    let mut fanout_r0 = vec![r0, i1];
+    fanout_r0.sort();
+    assert_eq!(cells.fanout_cone([r0]), fanout_r0);
+
+    let pins = db.pin_graph(&is_sequential);
+    assert_eq!(pins.num_nodes, db.num_pins);
+    assert_eq!(pins.fanouts.iter_set(pin("i0", "A")).collect::<Vec<_>>(),
+               [pin("i0", "Y")]);
+    assert_eq!(pins.fanouts.len(pin("r0", "D")), 0);
+    let mut fanin_y = vec![port("y"), pin("i1", "Y"), pin("i1", "A"), pin("r0", "Q")];
+    fanin_y.sort();
+    assert_eq!(pins.fanin_cone([port("y")]), fanin_y);
+    let fanin_d = pins.fanin_cone([pin("r0", "D")]);
+    assert!(fanin_d.contains(&port("a")));
+    assert!(fanin_d.contains(&pin("i0", "A")));
+    assert!(!fanin_d.contains(&port("clk")));
+    let lv = pins.levelize().unwrap();
+    assert_eq!(lv.levels.len(), db.num_pins);
+    assert_eq!(lv.levels[port("a")], 0);
+    assert_eq!(lv.levels[pin("r0", "D")], 5);
+    assert!(db.find_comb_loop(&is_sequential).is_none());
+}
+
+#[test]
+fn graph_comb_loop() {
+    clilog::init_stdout_simple_trace();
+
+    let db = NetlistDB::from_sverilog_source(RING, None, &directions).unwrap();
+    let pins = db.pin_graph(&is_sequential);
+    let cycle = pins.levelize().unwrap_err();
+    assert_eq!(cycle.len(), 6);
+    for (i, &p) in cycle.iter().enumerate() {
+        let next = cycle[(i + 1) % cycle.len()];
+        assert!(pins.fanouts.iter_set(p).any(|q| q == next));
+    }
+    let names = cycle.iter()
+        .map(|&p| db.pinnames[p].1.as_str())
+        .collect::<HashSet<_>>();
+    assert_eq!(names, HashSet::from(["A", "B", "Y"]));
+    assert_eq!(db.find_comb_loop(&is_sequential), Some(cycle));
+    assert!(db.cell_graph(&is_sequential).find_loop().is_some());
+
+    // breaking the loop at the inverters.
+    let lib = HashSet::from([CompactString::from("INV")]);
+    assert!(db.pin_graph(&lib).levelize().is_ok());
+    assert!(db.find_comb_loop(&lib).is_none());
+}
@@ -0,0 +1,94 @@
+use netlistdb::*;
+
+mod common;
+use common::*;
+
+const SOURCE: &str = "
+module top (a, b, y);
+   input a, b;
+   output [1:0] y;
+   wire n;
+   core u_core (.i({a, b}), .o(n));
+   BUF b0 (.A(n), .Y(y[0]));
+   alu u_alu (.x(n), .z(y[1]));
+endmodule
+
+module core (i, o);
+   input [1:0] i;
+   output o;
+   wire t;
+   alu u_alu (.x(i[0]), .z(t));
+   AND2 g0 (.A(t), .B(i[1]), .Y(o));
+endmodule
+
+module alu (x, z);
+   input x;
+   output z;
+   wire t;
+   INV i0 (.A(x), .Y(t));
+   INV i1 (.A(t), .Y(z));
+endmodule
+";
+
+#[test]
+fn hierarchy() {
+    clilog::init_stdout_simple_trace();
+
+    let db = NetlistDB::from_sverilog_source(SOURCE, Some("top"), &directions).unwrap();
+    assert_eq!(db.num_hiers, 4);
+    assert_eq!(db.hiername2id[&HierName::empty()], 0);
+    assert_eq!(db.hiertypes[0], "top");
+    let core = db.hiername2id[&hier(&["u_core"])];
+    let core_alu = db.hiername2id[&hier(&["u_core", "u_alu"])];
+    let top_alu = db.hiername2id[&hier(&["u_alu"])];
+    assert_eq!(db.hiertypes[core], "core");
+    assert_eq!(db.hiertypes[core_alu], "alu");
+    assert_eq!(db.hiertypes[top_alu], "alu");
+    assert_eq!(db.hier2parent[core_alu], core);
+    assert_eq!(db.hier2parent[core], 0);
+    assert_eq!(db.hier2parent[0], 0);
+    assert_eq!(db.hier2children.iter_set(0).collect::<Vec<_>>(), [core, top_alu]);
+    assert_eq!(db.hier2children.iter_set(core).collect::<Vec<_>>(), [core_alu]);
+
+    let cell = |names: &[&str]| db.cellname2id[&hier(names)];
+    let i0 = cell(&["u_core", "u_alu", "i0"]);
+    let i1 = cell(&["u_core", "u_alu", "i1"]);
+    let g0 = cell(&["u_core", "g0"]);
+    assert_eq!(db.cell2hier[i0], core_alu);
+    assert_eq!(db.cell2hier[cell(&["b0"])], 0);
+    assert_eq!(db.hier2cells.iter_set(core_alu).collect::<Vec<_>>(), [i0, i1]);
+    assert_eq!(db.hier2cells.iter_set(core).collect::<Vec<_>>(), [g0]);
+    assert_eq!(db.cells_under(core), [i0, i1, g0]);
+    assert_eq!(db.cells_under(0), (1..db.num_cells).collect::<Vec<_>>());
+    assert!(db.is_cell_under(i0, core));
+    assert!(!db.is_cell_under(cell(&["u_alu", "i0"]), core));
+    assert!(!db.is_cell_under(0, 0));
+
+    // the logic pins of the ports: i[1], i[0] and o of u_core.
+    assert_eq!(db.hier2ports.len(core), 3);
+    assert_eq!(db.hier2ports.len(0), 4);
+    assert_eq!(db.hier2ports.len(top_alu), 2);
+}
+
+#[test]
+fn hierarchy_eco() {
+    clilog::init_stdout_simple_trace();
+
+    let mut db = NetlistDB::from_sverilog_source(SOURCE, Some("top"), &directions).unwrap();
+    let core = db.hiername2id[&hier(&["u_core"])];
+    let g0 = db.cellname2id[&hier(&["u_core", "g0"])];
+    let buf = db.add_cell(
+        hier(&["u_core", "eco_buf"]), "BUF".into(),
+        [("A".into(), None), ("Y".into(), None)], &directions
+    ).unwrap();
+    assert_eq!(db.cell2hier[buf], core);
+    assert_eq!(db.hier2cells.iter_set(core).collect::<Vec<_>>(), [g0, buf]);
+    assert!(db.add_cell(
+        hier(&["u_none", "eco_buf"]), "BUF".into(), [], &directions).is_none());
+
+    db.remove_cell(g0).unwrap();
+    assert_eq!(db.hier2cells.iter_set(core).collect::<Vec<_>>(), [buf - 1]);
+    assert_eq!(db.cells_under(core).len(), 3);
+    assert_eq!(db.cell2hier.len(), db.num_cells);
+    check_consistency(&db);
+}
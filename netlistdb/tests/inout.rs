@@ -0,0 +1,46 @@
+use netlistdb::*;
+use compact_str::CompactString;
+
+#[test]
+fn inout() {
+    clilog::init_stdout_simple_trace();
+
+    let directions = |_: &CompactString, pin: &CompactString, pinwidth: Option<isize>| {
+        assert_eq!(pinwidth, None);
+        use Direction::*;
+        match pin.as_str() {
+            "PAD" | "IO" => InOut,
+            "C" | "o" => O,
+            _ => I
+        }
+    };
+
+    let mut db = NetlistDB::from_sverilog_file(
+        concat!(env!("CARGO_MANIFEST_DIR"), "/tests/inout.v"),
+        None, &directions
+    ).unwrap();
+
+    println!("The database: {db:#?}");
+    assert_eq!(db.num_cells, 5);
+    assert_eq!(db.num_pins, 17);
+    assert_eq!(db.num_nets, 6);
+    use Direction::*;
+    assert_eq!(&db.pindirect[..5], &[InOut, InOut, I, O, O]);
+    assert_eq!(db.cell2noutputs, vec![4, 2, 1, 1, 1].into());
+
+    // the top-level inout port is the root of the pad net.
+    let pad = db.pinname2id[&(HierName::empty(), "pad".into(), None)];
+    let p0_pad = db.pinname2id[&(HierName::single("p0".into()), "PAD".into(), None)];
+    let net_pad = db.pin2net[pad];
+    assert_eq!(db.net_driver(net_pad), Some(pad));
+    assert_eq!(db.net2pin.iter_set(net_pad).collect::<Vec<_>>(), vec![pad, p0_pad]);
+
+    // the bus has several inout drivers.
+    let net_bus = db.netname2id[&(HierName::empty(), "bus".into(), None)];
+    assert_eq!(db.net2pin.len(net_bus), 3);
+    assert_eq!(db.net_driver(net_bus), db.net2pin.iter_set(net_bus).next());
+
+    // disconnecting the root moves it to the next inout pin.
+    db.disconnect_pin(pad).unwrap();
+    assert_eq!(db.net_driver(net_pad), Some(p0_pad));
+}
@@ -0,0 +1,14 @@
+// an IO ring with bidirectional pads.
+module padring (pad, bus, core_in, core_out, oe);
+   inout pad;
+   inout bus;
+   output core_in;
+   input core_out, oe;
+
+   wire   oe_n;
+
+   PAD p0 ( .PAD(pad), .I(core_out), .OE(oe), .C(core_in) );
+   INV u0 ( .a(oe), .o(oe_n) );
+   BIDIR b1 ( .IO(bus), .A(core_out), .EN(oe) );
+   BIDIR b2 ( .IO(bus), .A(core_out), .EN(oe_n) );
+endmodule
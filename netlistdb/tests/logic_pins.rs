@@ -0,0 +1,64 @@
+use netlistdb::*;
+use compact_str::CompactString;
+
+mod common;
+use common::*;
+
+const SOURCE: &str = "
+module top (a, .b_port({b}), y);
+   input a, b;
+   output y;
+   wire n, m;
+   assign m = n;
+   sub u_sub (.i(a), .o(n));
+   AND2 g0 (.A(m), .B(b), .Y(y));
+endmodule
+
+module sub (i, o);
+   input i;
+   output o;
+   INV i0 (.A(i), .Y(o));
+endmodule
+";
+
+#[test]
+fn logic_pins() {
+    clilog::init_stdout_simple_trace();
+
+    let db = NetlistDB::from_sverilog_source(SOURCE, Some("top"), &directions).unwrap();
+    let name = |hier: &[&str], name: &str| (
+        HierName::from_topdown_hier_iter(hier.iter().copied()),
+        CompactString::from(name), None);
+
+    // the submodule ports resolve to the nets.
+    let net_n = db.netname2id[&name(&[], "n")];
+    assert_eq!(db.logic_pin_net(&name(&["u_sub"], "o")), Some(net_n));
+    assert_eq!(db.logic_pin_net(&name(&["u_sub", "i0"], "Y")), Some(net_n));
+    assert_eq!(db.logic_pin_net(&name(&["u_sub"], "x")), None);
+    let net_a = db.logic_pin_net(&name(&["u_sub"], "i")).unwrap();
+    assert_eq!(db.pin2net[db.portname2pinid[&("a".into(), None)]], net_a);
+
+    use LogicPinType::*;
+    let typ = |hier: &[&str], n: &str| db.logicpintypes[db.logic_pin(&name(hier, n)).unwrap()];
+    assert_eq!(typ(&[], "a"), TopPort);
+    assert_eq!(typ(&[], "b_port"), TopPort);
+    assert_eq!(typ(&[], "b"), Net);
+    assert_eq!(typ(&["u_sub"], "o"), Net);
+    assert_eq!(typ(&["g0"], "Y"), LeafCellPin);
+    let pin = db.pinname2id[&name(&["g0"], "Y")];
+    assert_eq!(db.logicpinnames[db.pinid2logicpinid[pin]], name(&["g0"], "Y"));
+
+    // the aliases of a net include the assigned wires and the
+    // submodule ports, but not the leaf cell pins.
+    let mut aliases = db.net_aliases(net_n).into_iter()
+        .map(|(hier, n, _)| format!("{hier}:{n}"))
+        .collect::<Vec<_>>();
+    aliases.sort();
+    assert_eq!(aliases, [":m", ":n", "u_sub:o"]);
+
+    // the ports of the submodule instance.
+    let u_sub = db.hiername2id[&HierName::single("u_sub".into())];
+    assert_eq!(db.hier2ports.iter_set(u_sub)
+               .map(|id| db.logicpin2net[id]).collect::<Vec<_>>(),
+               [net_a, net_n]);
+}
@@ -0,0 +1,97 @@
+use netlistdb::*;
+
+mod common;
+use common::*;
+
+const SOURCE: &str = "
+module top (d, q, r, s, w, t);
+   input signed [3:0] d;
+   output [1:0] q;
+   output r, s;
+   wire [3:0] mem [0:3];
+   wire [1:0][1:0] p;
+   output [7:0] w;
+   output [1:0] t;
+   assign mem[2] = d;
+   assign q = mem[2][3:2];
+   assign p[0] = mem[2][1:0];
+   assign w = mem[1:2];
+   assign t = mem[2][1 +: 2];
+   BUF u0 ( .A(p[0][1]), .Y(r) );
+   BUF u1 ( .A(mem[2][0]), .Y(s) );
+endmodule
+";
+
+#[test]
+fn multi_dim() {
+    clilog::init_stdout_simple_trace();
+
+    let db = NetlistDB::from_sverilog_source(SOURCE, None, &directions).unwrap();
+    println!("The database: {db:#?}");
+    let net_of = |name: &str, i: Option<isize>| db.pin2net[db.pinname2id[&(
+        HierName::empty(), name.into(), i)]];
+    let d = |i| net_of("d", Some(i));
+    assert_eq!(net_of("q", Some(1)), d(3));
+    assert_eq!(net_of("q", Some(0)), d(2));
+    let cell_pin = |cell: &str| db.pin2net[db.pinname2id[&(
+        HierName::single(cell.into()), "A".into(), None)]];
+    assert_eq!(cell_pin("u0"), d(1));
+    assert_eq!(cell_pin("u1"), d(0));
+    // w[3:0] is mem[2].
+    for i in 0..4 {
+        assert_eq!(net_of("w", Some(i)), d(i));
+    }
+    assert_ne!(net_of("w", Some(4)), d(0));
+    assert_eq!(net_of("t", Some(1)), d(2));
+    assert_eq!(net_of("t", Some(0)), d(1));
+}
+
+const SOURCE_PART_SELECT: &str = "
+module top (a, b, y, z, r);
+   input [0:7] a;
+   input [7:0] b;
+   output [3:0] y;
+   output [1:0] z;
+   output [3:0] r;
+   assign y = a[2 +: 4];
+   assign z = b[5 -: 2];
+   assign r = a[3:0];
+endmodule
+";
+
+#[test]
+fn indexed_part_select() {
+    clilog::init_stdout_simple_trace();
+
+    let db = NetlistDB::from_sverilog_source(SOURCE_PART_SELECT, None, &directions).unwrap();
+    let net_of = |name: &str, i: isize| db.pin2net[db.pinname2id[&(
+        HierName::empty(), name.into(), Some(i))]];
+    // a[2 +: 4] is a[2:5] on an ascending vector.
+    for i in 0..4 {
+        assert_eq!(net_of("y", 3 - i), net_of("a", 2 + i));
+    }
+    // b[5 -: 2] is b[5:4].
+    assert_eq!(net_of("z", 1), net_of("b", 5));
+    assert_eq!(net_of("z", 0), net_of("b", 4));
+    // an explicit slice keeps its own direction, even if it is
+    // reversed from the declaration.
+    for i in 0..4 {
+        assert_eq!(net_of("r", i), net_of("a", i));
+    }
+}
+
+#[test]
+fn bad_select() {
+    clilog::init_stdout_simple_trace();
+
+    // too many indices.
+    assert!(NetlistDB::from_sverilog_source(
+        "module bad (o); output o; wire [1:0] m [0:3]; assign o = m[0][1][0]; endmodule",
+        None, &directions
+    ).is_none());
+    // a multi-index select on a vector.
+    assert!(NetlistDB::from_sverilog_source(
+        "module bad (o); output o; wire [1:0] v; assign o = v[1][0]; endmodule",
+        None, &directions
+    ).is_none());
+}
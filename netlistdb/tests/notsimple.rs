@@ -55,3 +55,15 @@ fn not_simple() {
     assert_eq!(db.net_zero, Some(7));
     assert_eq!(db.net_one, None);
 }
+
+#[test]
+fn not_simple_files() {
+    clilog::init_stdout_simple_trace();
+
+    let path = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/notsimple.v");
+    let db = NetlistDB::from_sverilog_file(path, None, &NoDirection).unwrap();
+    let db2 = NetlistDB::from_sverilog_files(&[path], None, &NoDirection).unwrap();
+    assert_eq!(db2.cellnames, db.cellnames);
+    assert_eq!(db2.pinnames, db.pinnames);
+    assert_eq!(db2.num_nets, db.num_nets);
+}
@@ -0,0 +1,40 @@
+use netlistdb::*;
+
+const SOURCE: &str = "
+module top (a, y);
+   input [1:0] a;
+   output y;
+   wire   n1;
+   sub #(.W(2)) u_sub (.a(a), .y(n1));
+   BUF #(3) b0 (.A(n1), .Y(y));
+endmodule
+
+module sub #(parameter W = 1) (a, y);
+   input [1:0] a;
+   output y;
+   LUT2 #(.INIT(4'h8), .NAME(\"and\")) lut0 (.I0(a[0]), .I1(a[1]), .O(y));
+endmodule
+";
+
+#[test]
+fn instance_params() {
+    clilog::init_stdout_simple_trace();
+
+    let db = NetlistDB::from_sverilog_source(SOURCE, Some("top"), &NoDirection).unwrap();
+    println!("The database: {db:#?}");
+    let lut0 = db.cellname2id[&HierName::from_topdown_hier_iter(["u_sub", "lut0"])];
+    let b0 = db.cellname2id[&HierName::single("b0".into())];
+    assert_eq!(db.cell_param(lut0, "INIT").map(|v| v.as_str()), Some("4'h8"));
+    assert_eq!(db.cell_param(lut0, "NAME").map(|v| v.as_str()), Some("\"and\""));
+    assert_eq!(db.cell_param(lut0, "W"), None);
+    assert_eq!(db.cellparams[b0], [(None, "3".into())]);
+    assert!(db.cellparams[0].is_empty());
+
+    // the parameters are written back.
+    let db2 = NetlistDB::from_sverilog_source(
+        &format!("{}", db.to_sverilog()), None, &NoDirection
+    ).unwrap();
+    let lut0 = db2.cellname2id[&HierName::single("u_sub/lut0".into())];
+    assert_eq!(db2.cellparams[lut0], db.cellparams[
+        db.cellname2id[&HierName::from_topdown_hier_iter(["u_sub", "lut0"])]]);
+}
@@ -0,0 +1,72 @@
+use netlistdb::*;
+use compact_str::CompactString;
+use sverilogparse::SVerilogRange;
+
+/// a library that knows the pin order of its cells.
+struct Lib;
+
+impl LeafPinProvider for Lib {
+    fn direction_of(
+        &self, _: &CompactString, pin: &CompactString, _: Option<isize>
+    ) -> Direction {
+        match pin.as_str() {
+            "Y" => Direction::O,
+            _ => Direction::I
+        }
+    }
+
+    fn width_of(&self, _: &CompactString, _: &CompactString) -> Option<SVerilogRange> {
+        None
+    }
+
+    fn ordered_pins_of(&self, macro_name: &CompactString) -> Option<Vec<CompactString>> {
+        match macro_name.as_str() {
+            "NAND2" => Some(vec!["Y".into(), "A".into(), "B".into()]),
+            _ => None
+        }
+    }
+}
+
+const SOURCE: &str = "
+module top (a, b, y);
+   input a, b;
+   output y;
+   wire n;
+   sub u_sub (n, {a, b});
+   NAND2 g0 (y, n, );
+endmodule
+
+module sub (o, i);
+   input [1:0] i;
+   output o;
+   NAND2 g0 (.A(i[0]), .B(i[1]), .Y(o));
+endmodule
+";
+
+#[test]
+fn positional() {
+    clilog::init_stdout_simple_trace();
+
+    let db = NetlistDB::from_sverilog_source(SOURCE, Some("top"), &Lib).unwrap();
+    println!("The database: {db:#?}");
+    assert_eq!(db.num_cells, 3);
+    let pin = |hier: &[&str], name: &str, idx| db.pinname2id[&(
+        HierName::from_topdown_hier_iter(hier.iter().copied()), name.into(), idx)];
+    assert_eq!(db.pin2net[pin(&[], "a", None)],
+               db.pin2net[pin(&["u_sub", "g0"], "B", None)]);
+    assert_eq!(db.pin2net[pin(&["g0"], "A", None)],
+               db.pin2net[pin(&["u_sub", "g0"], "Y", None)]);
+    assert_eq!(db.pin2net[pin(&["g0"], "Y", None)],
+               db.pin2net[pin(&[], "y", None)]);
+    // the unconnected position creates no pin.
+    assert!(!db.pinname2id.contains_key(&(
+        HierName::single("g0".into()), "B".into(), None)));
+
+    // too many connections, or unknown pin order.
+    assert!(NetlistDB::from_sverilog_source(
+        "module top (a); input a; NAND2 g0 (a, a, a, a); endmodule",
+        None, &Lib).is_none());
+    assert!(NetlistDB::from_sverilog_source(
+        "module top (a); input a; BUF g0 (a, a); endmodule",
+        None, &Lib).is_none());
+}
@@ -0,0 +1,90 @@
+use netlistdb::*;
+
+#[test]
+fn c17() {
+    clilog::init_stdout_simple_trace();
+
+    // no library is needed for gate primitives.
+    let db = NetlistDB::from_sverilog_file(
+        concat!(env!("CARGO_MANIFEST_DIR"), "/tests/c17.v"),
+        None, &NoDirection
+    ).unwrap();
+    println!("The database: {db:#?}");
+    assert_eq!(db.num_cells, 7);
+    assert_eq!(db.num_pins, 7 + 6 * 3);
+    assert_eq!(db.num_nets, 11);
+    assert!(db.pindirect.iter().all(|d| *d != Direction::Unknown));
+    assert_eq!(&db.cell2noutputs[1..], &[1; 6]);
+
+    let pin = |cell: &str, name: &str| db.pinname2id[&(
+        HierName::single(cell.into()), name.into(), None)];
+    let n11 = db.netname2id[&(HierName::empty(), "N11".into(), None)];
+    assert_eq!(db.net_driver(n11), Some(pin("NAND2_2", "Y")));
+    assert_eq!(db.pin2net[pin("NAND2_4", "A1")], n11);
+    assert_eq!(db.pindirect[pin("NAND2_4", "A2")], Direction::I);
+
+    // primitives are written back with positional connections.
+    let sv = format!("{}", db.to_sverilog());
+    assert!(sv.contains("nand NAND2_1(N10, N1, N3);"), "{sv}");
+}
+
+#[test]
+fn buf_not_bufif() {
+    clilog::init_stdout_simple_trace();
+
+    let db = NetlistDB::from_sverilog_source("
+module top (a, en, y1, y2, y3, z);
+   input a, en;
+   output y1, y2, y3, z;
+   wire n;
+   not g0 (n, a);
+   buf g1 (y1, y2, y3, n);
+   bufif1 g2 (z, a, en);
+endmodule
+", None, &NoDirection).unwrap();
+    println!("The database: {db:#?}");
+    let pin = |cell: &str, name: &str| db.pinname2id[&(
+        HierName::single(cell.into()), name.into(), None)];
+    use Direction::*;
+    assert_eq!(db.pindirect[pin("g0", "Y")], O);
+    assert_eq!(db.pindirect[pin("g1", "Y3")], O);
+    assert_eq!(db.pindirect[pin("g1", "A")], I);
+    assert_eq!(db.pindirect[pin("g2", "EN")], I);
+    assert_eq!(db.pin2net[pin("g0", "Y")], db.pin2net[pin("g1", "A")]);
+    assert_eq!(db.cell2noutputs[db.cellname2id[&HierName::single("g1".into())]], 3);
+
+    // a bad number of terminals.
+    assert!(NetlistDB::from_sverilog_source(
+        "module top (a); input a; bufif0 g0 (a, a); endmodule",
+        None, &NoDirection).is_none());
+}
+
+#[test]
+fn unnamed_and_listed() {
+    clilog::init_stdout_simple_trace();
+
+    let db = NetlistDB::from_sverilog_source("
+module top (a, b, c, y, z);
+   input a, b, c;
+   output y, z;
+   wire n1, n2, n3;
+   and (n1, a, b);
+   nand g1 (n2, b, c), g2 (y, n1, n2), (z, a, c);
+   or \\$nand$1 (n3, a, c);
+endmodule
+", None, &NoDirection).unwrap();
+    // the generated names skip the existing `$nand$1`.
+    let names = db.cellnames[1..].iter().map(|n| n.cur.as_str()).collect::<Vec<_>>();
+    assert_eq!(names, ["$and$0", "g1", "g2", "$nand$2", "$nand$1"]);
+    let pin = |cell: &str, name: &str| db.pinname2id[&(
+        HierName::single(cell.into()), name.into(), None)];
+    assert_eq!(db.pin2net[pin("$and$0", "Y")], db.pin2net[pin("g2", "A1")]);
+    assert_eq!(db.pin2net[pin("g1", "Y")], db.pin2net[pin("g2", "A2")]);
+    assert_eq!(db.celltypes[db.cellname2id[&HierName::single("$nand$2".into())]], "nand");
+
+    // the generated names survive a round trip.
+    let db2 = NetlistDB::from_sverilog_source(
+        &format!("{}", db.to_sverilog()), None, &NoDirection
+    ).unwrap();
+    assert_eq!(db2.cellnames, db.cellnames);
+}
@@ -44,3 +44,20 @@ fn simple() {
     assert_eq!(db.net_zero, None);
     assert_eq!(db.net_one, None);
 }
+
+#[test]
+fn simple_borrowed() {
+    clilog::init_stdout_simple_trace();
+
+    let verilog = fs::read_to_string(
+        concat!(env!("CARGO_MANIFEST_DIR"), "/tests/simple.v")
+    ).expect("unable to read simple.v");
+    let borrowed = sverilogparse::SVerilog::parse_borrowed(&verilog)
+        .expect("parse error");
+    let db = NetlistDB::from_sverilog(borrowed, None, &NoDirection).unwrap();
+    let db2 = NetlistDB::from_sverilog_source(&verilog, None, &NoDirection).unwrap();
+    assert_eq!(db.num_cells, 4);
+    assert_eq!(db.cellnames, db2.cellnames);
+    assert_eq!(db.netnames, db2.netnames);
+    assert_eq!(db.pin2net, db2.pin2net);
+}
@@ -0,0 +1,175 @@
+use netlistdb::*;
+
+mod common;
+use common::*;
+
+/// Check that two pin-to-net maps induce the same partition.
+fn same_partition(a: &[usize], b: &[usize]) -> bool {
+    a.len() == b.len() && (0..a.len()).all(|i| (0..a.len()).all(|j| {
+        (a[i] == a[j]) == (b[i] == b[j])
+    }))
+}
+
+#[test]
+fn write_flat() {
+    clilog::init_stdout_simple_trace();
+
+    let db = NetlistDB::from_sverilog_file(
+        concat!(env!("CARGO_MANIFEST_DIR"), "/tests/notsimple.v"),
+        None, &directions
+    ).unwrap();
+
+    let mut out = Vec::new();
+    db.write_sverilog(&mut out).unwrap();
+    let out = String::from_utf8(out).unwrap();
+    println!("Flattened: \n{out}");
+    assert!(out.contains("INV_X1 \\dins1/u2 (.a(n[3]), .o(\\dins1/n4 ));"));
+    assert!(out.contains("assign nzero = 1'b0;"));
+
+    let db2 = NetlistDB::from_sverilog_source(
+        &out, None, &directions
+    ).unwrap();
+    assert_eq!(db2.name, db.name);
+    assert_eq!(db2.num_cells, db.num_cells);
+    assert_eq!(db2.num_pins, db.num_pins);
+    assert_eq!(db2.celltypes, db.celltypes);
+    assert_eq!(db2.cellnames.iter().map(|n| format!("{n}")).collect::<Vec<_>>(),
+               db.cellnames.iter().map(|n| format!("{n}")).collect::<Vec<_>>());
+    assert_eq!(db2.pindirect, db.pindirect);
+    assert!(same_partition(&db2.pin2net, &db.pin2net));
+    assert!(db2.net_zero.is_some());
+    assert_eq!(db2.net_one, None);
+}
+
+#[test]
+fn write_hier() {
+    clilog::init_stdout_simple_trace();
+
+    let db = NetlistDB::from_sverilog_file(
+        concat!(env!("CARGO_MANIFEST_DIR"), "/tests/notsimple.v"),
+        None, &directions
+    ).unwrap();
+
+    let sv = db.to_sverilog_hier();
+    println!("Hierarchical: \n{sv}");
+    // dins1 and dins2 share one module definition.
+    assert_eq!(sv.modules.len(), 2);
+    assert_eq!(sv.modules[0].0, "simple2_submodule_doubleinv");
+    assert_eq!(format!("{}", sv.modules[0].1.cells[0]),
+               "INV_X1 u2(.a(n3_x[3]), .o(n4));");
+    assert_eq!(sv.modules[1].0, db.name);
+
+    let mut out = Vec::new();
+    db.write_sverilog_hier(&mut out).unwrap();
+    let db2 = NetlistDB::from_sverilog_source(
+        std::str::from_utf8(&out).unwrap(), None, &directions
+    ).unwrap();
+    assert_eq!(db2.name, db.name);
+    assert_eq!(db2.num_cells, db.num_cells);
+    assert_eq!(db2.num_pins, db.num_pins);
+    assert_eq!(db2.celltypes, db.celltypes);
+    assert_eq!(db2.cellnames, db.cellnames);
+    assert_eq!(db2.hiernames, db.hiernames);
+    assert_eq!(db2.hiertypes, db.hiertypes);
+    assert_eq!(db2.pindirect, db.pindirect);
+    assert!(same_partition(&db2.pin2net, &db.pin2net));
+}
+
+const LEAFLESS: &str = "
+module feed (a, o);
+   input a;
+   output o;
+   assign o = a;
+endmodule
+
+module empty ();
+endmodule
+
+module wrap (a, o);
+   input a;
+   output o;
+   feed f0 (.a(a), .o(o));
+endmodule
+
+module top (a, o);
+   input a;
+   output o;
+   wire n;
+   wrap w0 (.a(a), .o(n));
+   empty e0 ();
+   INV_X1 u0 (.a(n), .o(o));
+endmodule
+";
+
+#[test]
+fn write_hier_leafless() {
+    clilog::init_stdout_simple_trace();
+
+    let db = NetlistDB::from_sverilog_source(LEAFLESS, None, &directions).unwrap();
+    let sv = db.to_sverilog_hier();
+    println!("Hierarchical: \n{sv}");
+    let names = sv.modules.iter().map(|(name, _)| name.as_str()).collect::<Vec<_>>();
+    assert_eq!(names, ["feed", "wrap", "empty", "top"]);
+    assert_eq!(format!("{}", sv.modules[1].1.cells[0]), "feed f0(.a(a));");
+    assert_eq!(sv.modules[3].1.cells.len(), 3);
+
+    let db2 = NetlistDB::from_sverilog_source(
+        &format!("{sv}"), None, &directions
+    ).unwrap();
+    assert_eq!(db2.hiernames, db.hiernames);
+    assert_eq!(db2.hiertypes, db.hiertypes);
+    assert_eq!(db2.cellnames, db.cellnames);
+    assert!(same_partition(&db2.pin2net, &db.pin2net));
+}
+
+#[test]
+fn write_flat_generated_names() {
+    clilog::init_stdout_simple_trace();
+
+    // the constant net has no name, and its generated name is
+    // taken by a wire for one of the k's.
+    let mut collided = false;
+    for k in 0..6 {
+        let db = NetlistDB::from_sverilog_source(&format!("
+module top (o, p);
+   output o, p;
+   wire netlistdb_net_{k};
+   BUF b0 (.a(1'b0), .o(o));
+   BUF b1 (.a(netlistdb_net_{k}), .o(p));
+   BUF b2 (.a(1'b0), .o(netlistdb_net_{k}));
+endmodule
+"), None, &directions).unwrap();
+        collided |= db.netnames.get(k).is_some_and(|(_, name, _)| name.is_empty());
+        let out = format!("{}", db.to_sverilog());
+        let db2 = NetlistDB::from_sverilog_source(&out, None, &directions).unwrap();
+        assert!(same_partition(&db2.pin2net, &db.pin2net), "{out}");
+    }
+    assert!(collided);
+}
+
+#[test]
+fn write_hier_generated_names() {
+    clilog::init_stdout_simple_trace();
+
+    // the constant net gets a generated name inside s0, which
+    // is taken by a wire there.
+    let db = NetlistDB::from_sverilog_source("
+module sub (a, o);
+   input a;
+   output o;
+   wire netlistdb_net_0;
+   BUF b0 (.a(a), .o(netlistdb_net_0));
+   AND2 b1 (.a(netlistdb_net_0), .b(1'b1), .o(o));
+endmodule
+
+module top (a, o);
+   input a;
+   output o;
+   sub s0 (.a(a), .o(o));
+endmodule
+", None, &directions).unwrap();
+    let out = format!("{}", db.to_sverilog_hier());
+    let db2 = NetlistDB::from_sverilog_source(&out, None, &directions).unwrap();
+    assert!(same_partition(&db2.pin2net, &db.pin2net), "{out}");
+    assert_eq!(db2.net_one.map(|n| db2.net2pin.len(n)), Some(1));
+}
@@ -8,15 +8,19 @@ fn main() {
             "Usage: {} <sverilog_path>", args[0]);
     clilog::info!("Verilog file {}", args[1]);
 
-    let sv = match SVerilog::parse_str(&std::fs::read_to_string(&args[1]).unwrap()) {
-        Ok(sv) => sv,
-        Err(e) => panic!("{}", e)
-    };
+    // the modules are parsed and dropped one at a time.
+    let file = std::io::BufReader::new(std::fs::File::open(&args[1]).unwrap());
+    let (mut num_modules, mut num_cells) = (0, 0);
+    for m in SVerilog::iter_modules(file) {
+        let (_, m) = match m {
+            Ok(m) => m,
+            Err(e) => panic!("{}", e)
+        };
+        num_modules += 1;
+        num_cells += m.cells.len();
+    }
 
-    clilog::info!("# Modules = {}", sv.modules.len());
-    clilog::info!("# Cell lines = {}", sv.modules.iter().map(|m| m.1.cells.len())
-                  .sum::<usize>());
-
-    drop(sv);
+    clilog::info!("# Modules = {}", num_modules);
+    clilog::info!("# Cell lines = {}", num_cells);
     clilog::info!("cleaned up, exiting..")
 }
@@ -0,0 +1,139 @@
+//! Structured parse errors with source locations.
+
+use std::fmt;
+use std::path::PathBuf;
+
+/// A parse error, with the location and a snippet of the
+/// source line.
+///
+/// It is shared by the parsers of other formats, like Liberty
+/// and LEF/DEF, which build it with [LocatedParseError::at].
+///
+/// It prints like rustc diagnostics:
+/// ```text
+/// error: expected `)` in cell
+///   --> top.v:12:18
+///    |
+/// 12 |   AND2 u1 (.A(a) .B(b));
+///    |                  ^
+/// ```
+///
+/// Long lines are clipped around the error position.
+#[derive(Debug, Clone)]
+pub struct LocatedParseError {
+    /// The file path, if parsed from a file, e.g. by
+    /// [SVerilog::parse_file](crate::SVerilog::parse_file).
+    pub path: Option<PathBuf>,
+    /// The byte offset of the error position.
+    pub offset: usize,
+    /// The 1-based line number, or 0 if the error is not
+    /// related to a position (e.g., an I/O error).
+    pub line: usize,
+    /// The 1-based column number in characters.
+    pub column: usize,
+    /// The construct being parsed, e.g. `module`, `wire`,
+    /// `cell` or `assign` in verilog.
+    pub construct: Option<&'static str>,
+    /// The error message, e.g. ``expected `;` ``.
+    pub message: String,
+    /// The (possibly clipped) source line.
+    snippet: String,
+    /// The caret position in the snippet, in characters.
+    caret: usize,
+}
+
+/// the maximum number of bytes shown on each side of the
+/// error position in a snippet.
+const SNIPPET_RADIUS: usize = 60;
+
+/// count the characters in a UTF-8 byte slice, without
+/// validating it.
+pub(crate) fn count_chars(s: &[u8]) -> usize {
+    s.iter().filter(|&&c| c & 0xC0 != 0x80).count()
+}
+
+/// A structural verilog parse error.
+pub type SVerilogParseError = LocatedParseError;
+
+impl LocatedParseError {
+    /// Build an error at a byte offset of the source, computing
+    /// the line, column and snippet.
+    pub fn at(
+        source: &[u8], offset: usize,
+        construct: Option<&'static str>, message: String
+    ) -> LocatedParseError {
+        let offset = offset.min(source.len());
+        let line_start = source[..offset].iter()
+            .rposition(|&c| c == b'\n')
+            .map(|p| p + 1).unwrap_or(0);
+        let line_end = source[offset..].iter()
+            .position(|&c| c == b'\n' || c == b'\r')
+            .map(|p| p + offset).unwrap_or(source.len());
+        let start = line_start.max(offset.saturating_sub(SNIPPET_RADIUS));
+        let end = line_end.min(offset + SNIPPET_RADIUS);
+        let mut snippet = String::new();
+        if start > line_start {
+            snippet.push_str("...");
+        }
+        snippet.push_str(&String::from_utf8_lossy(&source[start..end]).replace('\t', " "));
+        if end < line_end {
+            snippet.push_str("...");
+        }
+        LocatedParseError {
+            path: None,
+            offset,
+            line: source[..line_start].iter().filter(|&&c| c == b'\n').count() + 1,
+            column: count_chars(&source[line_start..offset]) + 1,
+            construct, message, snippet,
+            caret: count_chars(&source[start..offset])
+                + if start > line_start { 3 } else { 0 }
+        }
+    }
+
+    /// Build an error not related to a position, like an I/O
+    /// error.
+    pub fn other(message: String) -> LocatedParseError {
+        LocatedParseError {
+            path: None, offset: 0, line: 0, column: 0,
+            construct: None, message,
+            snippet: String::new(), caret: 0
+        }
+    }
+
+    /// Set the file path of the error.
+    #[inline]
+    pub fn with_path(mut self, path: impl Into<PathBuf>) -> LocatedParseError {
+        self.path = Some(path.into());
+        self
+    }
+}
+
+impl fmt::Display for LocatedParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "error: {}", self.message)?;
+        if let Some(construct) = self.construct {
+            write!(f, " in {}", construct)?;
+        }
+        let path = match &self.path {
+            Some(path) => format!("{}", path.display()),
+            None => "<input>".to_string()
+        };
+        if self.line == 0 {
+            return write!(f, "\n --> {}", path)
+        }
+        let gutter = self.line.to_string().len();
+        writeln!(f)?;
+        writeln!(f, "{:gutter$}--> {}:{}:{}", "", path, self.line, self.column)?;
+        writeln!(f, "{:gutter$} |", "")?;
+        writeln!(f, "{} | {}", self.line, self.snippet)?;
+        write!(f, "{:gutter$} | {:caret$}^", "", "", caret = self.caret)
+    }
+}
+
+impl std::error::Error for LocatedParseError {}
+
+impl From<LocatedParseError> for String {
+    fn from(e: LocatedParseError) -> String {
+        format!("{}", e)
+    }
+}
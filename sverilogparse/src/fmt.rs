@@ -23,35 +23,75 @@ impl fmt::Display for SVIdentFmt<'_> {
     }
 }
 
-impl fmt::Display for SVerilog {
+/// formats the attributes like `(* keep, src = "a.v:1" *) `,
+/// with a trailing space. empty attributes print nothing.
+pub struct SVAttrsFmt<'i, S>(&'i [(S, Option<S>)]);
+
+impl<S: AsRef<str>> fmt::Display for SVAttrsFmt<'_, S> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.0.is_empty() {
+            return Ok(())
+        }
+        write!(f, "(* {} *) ", self.0.iter().map(|(n, v)| match v {
+            Some(v) => format!("{} = {}", SVIdentFmt(n.as_ref()), v.as_ref()),
+            None => format!("{}", SVIdentFmt(n.as_ref()))
+        }).format(", "))
+    }
+}
+
+impl<S: AsRef<str>> fmt::Display for SVerilog<S> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         for (m_name, m) in &self.modules {
-            writeln!(f, "module {}({});",
-                     SVIdentFmt(&m_name), m.ports.iter().format(", "))?;
-            let mut ind = indenter::indented(f)
-                .with_format(indenter::Format::Uniform{ indentation: "  " });
-            for def in &m.defs {
-                writeln!(ind, "{}", def)?;
-            }
-            writeln!(ind)?;
-            for assign in &m.assigns {
-                writeln!(ind, "{}", assign)?;
-            }
-            for cell in &m.cells {
-                writeln!(ind, "{}", cell)?;
-            }
-            writeln!(f, "endmodule")?
+            write!(f, "{}", SVModuleFmt(m_name, m))?;
         }
         Ok(())
     }
 }
 
-impl fmt::Display for SVerilogPortDef {
+/// formats the header of a module like `module m(a, b);`,
+/// without the declarations and the newline.
+pub(crate) struct SVModuleHeaderFmt<'i, S>(pub &'i S, pub &'i SVerilogModule<S>);
+
+impl<S: AsRef<str>> fmt::Display for SVModuleHeaderFmt<'_, S> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let SVModuleHeaderFmt(m_name, m) = self;
+        write!(f, "{}module {}({});",
+               SVAttrsFmt(&m.attrs), SVIdentFmt(m_name.as_ref()), m.ports.iter().format(", "))
+    }
+}
+
+/// formats a module with its name, ending with a newline.
+pub(crate) struct SVModuleFmt<'i, S>(pub &'i S, pub &'i SVerilogModule<S>);
+
+impl<S: AsRef<str>> fmt::Display for SVModuleFmt<'_, S> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let SVModuleFmt(m_name, m) = self;
+        writeln!(f, "{}", SVModuleHeaderFmt(*m_name, *m))?;
+        let mut ind = indenter::indented(f)
+            .with_format(indenter::Format::Uniform{ indentation: "  " });
+        for param in &m.params {
+            writeln!(ind, "{}", param)?;
+        }
+        for def in &m.defs {
+            writeln!(ind, "{}", def)?;
+        }
+        writeln!(ind)?;
+        for assign in &m.assigns {
+            writeln!(ind, "{}", assign)?;
+        }
+        for cell in &m.cells {
+            writeln!(ind, "{}", cell)?;
+        }
+        writeln!(f, "endmodule")
+    }
+}
+
+impl<S: AsRef<str>> fmt::Display for SVerilogPortDef<S> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         use SVerilogPortDef::*;
         match self {
-            Basic(s) => write!(f, "{}", SVIdentFmt(&s)),
-            Conn(s, e) => write!(f, ".{}({})", SVIdentFmt(&s), e)
+            Basic(s) => write!(f, "{}", SVIdentFmt(s.as_ref())),
+            Conn(s, e) => write!(f, ".{}({})", SVIdentFmt(s.as_ref()), e)
         }
     }
 }
@@ -64,28 +104,81 @@ impl fmt::Display for WireDefType {
             Output => "output",
             InOut => "inout",
             Wire => "wire",
+            Tri => "tri",
+            Wand => "wand",
+            Wor => "wor",
+            Supply0 => "supply0",
+            Supply1 => "supply1",
         })
     }
 }
 
-impl fmt::Display for SVerilogWireDef {
+impl<S: AsRef<str>> fmt::Display for SVerilogWireDef<S> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}{}", SVAttrsFmt(&self.attrs), self.typ)?;
+        if self.signed {
+            write!(f, " signed")?;
+        }
+        if !self.packed_dims.is_empty() || self.width.is_some() {
+            write!(f, " ")?;
+        }
+        for SVerilogRange(l, r) in self.packed_dims.iter().chain(self.width.iter()) {
+            write!(f, "[{}:{}]", l, r)?;
+        }
+        write!(f, " {}", SVIdentFmt(self.name.as_ref()))?;
+        if !self.unpacked_dims.is_empty() {
+            write!(f, " ")?;
+        }
+        for SVerilogRange(l, r) in &self.unpacked_dims {
+            write!(f, "[{}:{}]", l, r)?;
+        }
+        write!(f, ";")
+    }
+}
+
+impl<S: AsRef<str>> fmt::Display for SVerilogParamDef<S> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        match self.width {
-            None => write!(f, "{} {};", self.typ, SVIdentFmt(&self.name)),
-            Some(SVerilogRange(l, r)) => write!(
-                f, "{} [{}:{}] {};", self.typ, l, r,
-                SVIdentFmt(&self.name)),
+        write!(f, "{}", match self.local {
+            false => "parameter",
+            true => "localparam"
+        })?;
+        if let Some(typ) = &self.typ {
+            write!(f, " {}", typ.as_ref())?;
         }
+        if let Some(SVerilogRange(l, r)) = self.width {
+            write!(f, " [{}:{}]", l, r)?;
+        }
+        write!(f, " {} = {};", SVIdentFmt(self.name.as_ref()), self.value.as_ref())
     }
 }
 
-impl fmt::Display for WirexprBasic {
+impl<S: AsRef<str>> fmt::Display for WirexprBasic<S> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         use WirexprBasic::*;
         match self {
-            Full(s) => write!(f, "{}", SVIdentFmt(s)),
-            SingleBit(s, i) => write!(f, "{}[{}]", SVIdentFmt(s), i),
-            Slice(s, SVerilogRange(i, j)) => write!(f, "{}[{}:{}]", SVIdentFmt(s), i, j),
+            Full(s) => write!(f, "{}", SVIdentFmt(s.as_ref())),
+            SingleBit(s, i) => write!(f, "{}[{}]", SVIdentFmt(s.as_ref()), i),
+            Slice(s, SVerilogRange(i, j)) => write!(f, "{}[{}:{}]", SVIdentFmt(s.as_ref()), i, j),
+            Select(s, indices, range) => {
+                write!(f, "{}", SVIdentFmt(s.as_ref()))?;
+                for i in indices {
+                    write!(f, "[{}]", i)?;
+                }
+                match range {
+                    Some(SVerilogRange(i, j)) => write!(f, "[{}:{}]", i, j),
+                    None => Ok(())
+                }
+            }
+            PartSelect(s, indices, ps) => {
+                write!(f, "{}", SVIdentFmt(s.as_ref()))?;
+                for i in indices {
+                    write!(f, "[{}]", i)?;
+                }
+                write!(f, "[{} {}: {}]", ps.base, match ps.up {
+                    true => '+',
+                    false => '-'
+                }, ps.width)
+            }
             Literal(w, v, is_xz) => {
                 write!(f, "{}'b{}", w, (0..*w).rev().map(|i| {
                     match ((v >> i & 1), (is_xz >> i & 1)) {
@@ -101,7 +194,7 @@ impl fmt::Display for WirexprBasic {
     }
 }
 
-impl fmt::Display for Wirexpr {
+impl<S: AsRef<str>> fmt::Display for Wirexpr<S> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         use Wirexpr::*;
         match self {
@@ -111,18 +204,71 @@ impl fmt::Display for Wirexpr {
     }
 }
 
-impl fmt::Display for SVerilogAssign {
+/// formats an operand of an assign operator, with parentheses
+/// around the binary and conditional operations.
+struct SVOperandFmt<'i, S>(&'i SVerilogExpr<S>);
+
+impl<S: AsRef<str>> fmt::Display for SVOperandFmt<'_, S> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "assign {} = {};", self.lhs, self.rhs)
+        match self.0 {
+            SVerilogExpr::Op(op, _) if *op != SVerilogOp::Not => write!(f, "({})", self.0),
+            e => write!(f, "{}", e)
+        }
     }
 }
 
-impl fmt::Display for SVerilogCell {
+impl<S: AsRef<str>> fmt::Display for SVerilogExpr<S> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "{} {}({});",
-               SVIdentFmt(&self.macro_name), SVIdentFmt(&self.cell_name),
+        use SVerilogOp::*;
+        match self {
+            SVerilogExpr::Wire(w) => write!(f, "{}", w),
+            SVerilogExpr::Op(op, operands) => match (op, &operands[..]) {
+                (Not, [a]) => write!(f, "~{}", SVOperandFmt(a)),
+                (Cond, [s, a, b]) => write!(f, "{} ? {} : {}",
+                                            SVOperandFmt(s), SVOperandFmt(a),
+                                            SVOperandFmt(b)),
+                (op, operands) => write!(f, "{}", operands.iter()
+                                         .map(SVOperandFmt)
+                                         .format(&format!(" {} ", op.symbol())))
+            }
+        }
+    }
+}
+
+impl<S: AsRef<str>> fmt::Display for SVerilogAssign<S> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}assign {} = {};",
+               SVAttrsFmt(&self.attrs), self.lhs, self.rhs)
+    }
+}
+
+impl<S: AsRef<str>> fmt::Display for SVerilogCell<S> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}{} ", SVAttrsFmt(&self.attrs),
+               SVIdentFmt(self.macro_name.as_ref()))?;
+        if !self.params.is_empty() {
+            write!(f, "#({}) ", self.params.iter().map(|(n, v)| match n {
+                Some(n) => format!(".{}({})", SVIdentFmt(n.as_ref()), v.as_ref()),
+                None => v.as_ref().to_string()
+            }).format(", "))?;
+        }
+        // unnamed gate primitive instances.
+        let cell_name = match self.cell_name.as_ref() {
+            "" => String::new(),
+            name => SVIdentFmt(name).to_string()
+        };
+        if !self.ordered_ioports.is_empty() {
+            return write!(f, "{}({});",
+                          cell_name,
+                          self.ordered_ioports.iter().map(|e| match e {
+                              Some(e) => e.to_string(),
+                              None => String::new()
+                          }).format(", "))
+        }
+        write!(f, "{}({});",
+               cell_name,
                self.ioports.iter().map(
-                   |(n, e)| format!(".{}({})", SVIdentFmt(&n), e)
+                   |(n, e)| format!(".{}({})", SVIdentFmt(n.as_ref()), e)
                ).format(", "))
     }
 }
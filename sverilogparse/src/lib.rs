@@ -14,28 +14,71 @@
 //! endmodule
 //! "#).expect("parse error");
 //! ```
+//!
+//! # Borrowed AST
+//!
+//! The AST types are generic over the type of identifiers and
+//! texts, which is [CompactString] by default. The borrowed
+//! AST `SVerilog<&'a str>` parsed by [SVerilog::parse_borrowed]
+//! refers to the source without copying, which is useful for
+//! read-only analyses. It can be converted to the owned one by
+//! [IntoOwned::into_owned].
+//!
+//! # Compiler directives
+//!
+//! The parser does not accept compiler directives like
+//! `` `define `` or `` `include ``. Sources with them should be
+//! preprocessed by [SVerilogPreprocessor] first, which keeps a
+//! source map so that the parse errors point to the original
+//! files.
+//!
+//! # Lossless printing
+//!
+//! The [Display](std::fmt::Display) impls print the AST in a
+//! canonical format, without the comments. To edit a source
+//! with a minimal textual diff, parse it by
+//! [LosslessSVerilog::parse_str], edit a clone of the AST, and
+//! print it back by [LosslessSVerilog::print].
 
 use compact_str::CompactString;
 
 /// Packages all content in structural verilog, in an unmodified manner.
-#[derive(Debug, Clone)]
-pub struct SVerilog {
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SVerilog<S = CompactString> {
     /// A vector of module names and parsed module object.
-    pub modules: Vec<(CompactString, SVerilogModule)>,
+    pub modules: Vec<(S, SVerilogModule<S>)>,
 }
 
 mod range;
-pub use range::SVerilogRange;
+pub use range::{SVerilogRange, SVerilogPartSelect};
+
+/// Attribute instances like `(* keep, src = "a.v:1" *)`, as
+/// tuples of (attribute_name, value_expression). The values are
+/// None for attributes without values like `keep`.
+pub type SVerilogAttrs<S = CompactString> = Vec<(S, Option<S>)>;
 
 /// A wire/io definition with optional vector width.
-#[derive(Debug, Clone)]
-pub struct SVerilogWireDef {
+///
+/// A multi-dimensional array like `wire [1:0][3:0] mem [0:15];`
+/// has the innermost packed range `[3:0]` as the width, the
+/// outer packed range `[1:0]` in `packed_dims`, and the unpacked
+/// range `[0:15]` in `unpacked_dims`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SVerilogWireDef<S = CompactString> {
     /// Wire name. E.g. `net0`
-    pub name: CompactString,
+    pub name: S,
     /// Wire width if it is a vector.
     pub width: Option<SVerilogRange>,
     /// Wire type.
     pub typ: WireDefType,
+    /// Whether it is declared `signed`.
+    pub signed: bool,
+    /// The packed dimensions outside the width, outer first.
+    pub packed_dims: Vec<SVerilogRange>,
+    /// The unpacked dimensions after the name, outer first.
+    pub unpacked_dims: Vec<SVerilogRange>,
+    /// Attributes before the definition.
+    pub attrs: SVerilogAttrs<S>,
 }
 
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
@@ -43,43 +86,118 @@ pub enum WireDefType {
     Input,
     Output,
     InOut,
-    Wire
+    Wire,
+    /// `tri`, which is the same as `wire`.
+    Tri,
+    /// `wand`, a wired-AND net.
+    Wand,
+    /// `wor`, a wired-OR net.
+    Wor,
+    /// `supply0`, a net tied to constant 0 (e.g., ground).
+    Supply0,
+    /// `supply1`, a net tied to constant 1 (e.g., power).
+    Supply1,
+}
+
+impl<S> SVerilogWireDef<S> {
+    /// All the dimensions, outer first: the unpacked dimensions,
+    /// the packed dimensions and then the width.
+    pub fn dims(&self) -> impl Iterator<Item = SVerilogRange> + '_ {
+        self.unpacked_dims.iter()
+            .chain(self.packed_dims.iter())
+            .chain(self.width.iter())
+            .copied()
+    }
+}
+
+impl WireDefType {
+    /// Whether this is a port direction (input/output/inout)
+    /// rather than a net type.
+    #[inline]
+    pub fn is_port(self) -> bool {
+        use WireDefType::*;
+        matches!(self, Input | Output | InOut)
+    }
+
+    /// The constant value of a supply net, 0 or 1.
+    #[inline]
+    pub fn supply_value(self) -> Option<u8> {
+        match self {
+            WireDefType::Supply0 => Some(0),
+            WireDefType::Supply1 => Some(1),
+            _ => None
+        }
+    }
+}
+
+/// A `parameter` or `localparam` declaration.
+///
+/// The value is kept as the original expression text, as it is
+/// not evaluated.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SVerilogParamDef<S = CompactString> {
+    /// Parameter name. E.g. `WIDTH`.
+    pub name: S,
+    /// Parameter value expression. E.g. `8'hff`.
+    pub value: S,
+    /// Parameter type keyword, if any. E.g. `integer`.
+    pub typ: Option<S>,
+    /// Parameter width, if any.
+    pub width: Option<SVerilogRange>,
+    /// Whether it is a `localparam`.
+    pub local: bool,
 }
 
 /// A parsed structural verilog module.
-#[derive(Debug, Clone)]
-pub struct SVerilogModule {
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SVerilogModule<S = CompactString> {
     /// Module ports.
-    pub ports: Vec<SVerilogPortDef>,
+    pub ports: Vec<SVerilogPortDef<S>>,
+    /// Module parameters, including those in the module header
+    /// like `module m #(parameter W = 8) (...)`.
+    pub params: Vec<SVerilogParamDef<S>>,
     /// Module I/O and net definitions.
-    pub defs: Vec<SVerilogWireDef>,
+    pub defs: Vec<SVerilogWireDef<S>>,
     /// Assignment operations in the module body.
-    pub assigns: Vec<SVerilogAssign>,
+    pub assigns: Vec<SVerilogAssign<S>>,
     /// Cells in the module body.
-    pub cells: Vec<SVerilogCell>,
+    pub cells: Vec<SVerilogCell<S>>,
+    /// Attributes before the module.
+    pub attrs: SVerilogAttrs<S>,
 }
 
 /// A port definition. Can be either a single identifier, or
 /// a named port connection like `.gpio({g1, g2, g3})`.
-#[derive(Debug, Clone)]
-pub enum SVerilogPortDef {
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SVerilogPortDef<S = CompactString> {
     /// E.g. `gpio`.
-    Basic(CompactString),
+    Basic(S),
     /// E.g. `.gpio({g1, g2, g3})`.
-    Conn(CompactString, Wirexpr)
+    Conn(S, Wirexpr<S>)
 }
 
 /// Basic component of a wire expression, which can be
 /// a wire reference, reference to a single wire bit,
 /// slice of a wire vector, or a constant literal.
 #[derive(Debug, Clone, PartialEq, Eq)]
-pub enum WirexprBasic {
+pub enum WirexprBasic<S = CompactString> {
     /// E.g. `somepin`.
-    Full(CompactString),
+    Full(S),
     /// E.g. `somepin[1]`.
-    SingleBit(CompactString, isize),
+    SingleBit(S, isize),
     /// E.g. `somepin[0:7]`.
-    Slice(CompactString, SVerilogRange),
+    Slice(S, SVerilogRange),
+    /// A select with multiple indices on a multi-dimensional wire,
+    /// optionally followed by a range on the next dimension.
+    /// E.g. `mem[3][1]` or `mem[3][7:4]`.
+    ///
+    /// A select with only one index or range is a [WirexprBasic::SingleBit]
+    /// or [WirexprBasic::Slice], even on a multi-dimensional wire.
+    Select(S, Vec<isize>, Option<SVerilogRange>),
+    /// An indexed part-select, optionally after indices on a
+    /// multi-dimensional wire.
+    /// E.g. `somepin[0 +: 4]` or `mem[3][7 -: 4]`.
+    PartSelect(S, Vec<isize>, SVerilogPartSelect),
     /// E.g. `4'b01xz`.
     /// The pairs are (size, value, is\_xz).
     Literal(usize, u128, u128),
@@ -87,59 +205,196 @@ pub enum WirexprBasic {
 
 /// A wire expression containing either a basic component or a
 /// concatenation of multiple basic components.
-#[derive(Debug, Clone)]
-pub enum Wirexpr {
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Wirexpr<S = CompactString> {
     /// A single basic component.
-    Basic(WirexprBasic),
+    Basic(WirexprBasic<S>),
     /// Multiple basic components enclosed in curly braces.
     /// E.g. `{somepin, 1'b0, otherpin[0:7]}`.
-    Concat(Vec<WirexprBasic>),
+    Concat(Vec<WirexprBasic<S>>),
+}
+
+/// A bitwise operator in an assign expression.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+pub enum SVerilogOp {
+    /// `~a`.
+    Not,
+    /// `a & b`.
+    And,
+    /// `a | b`.
+    Or,
+    /// `a ^ b`.
+    Xor,
+    /// `a ~^ b`, or equivalently `a ^~ b`.
+    Xnor,
+    /// The conditional operator `s ? a : b`.
+    Cond,
+}
+
+impl SVerilogOp {
+    /// The number of operands.
+    #[inline]
+    pub fn num_operands(self) -> usize {
+        use SVerilogOp::*;
+        match self {
+            Not => 1,
+            And | Or | Xor | Xnor => 2,
+            Cond => 3
+        }
+    }
+
+    /// The operator symbol, like `&`. It is `?` for the
+    /// conditional operator.
+    #[inline]
+    pub fn symbol(self) -> &'static str {
+        use SVerilogOp::*;
+        match self {
+            Not => "~", And => "&", Or => "|",
+            Xor => "^", Xnor => "~^", Cond => "?"
+        }
+    }
+}
+
+/// The right-hand side expression of an assign, which is either
+/// a wire expression or a bitwise operation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SVerilogExpr<S = CompactString> {
+    /// A wire expression. The assign is then a wire alias.
+    Wire(Wirexpr<S>),
+    /// An operation on the operands in order. E.g., `s ? a : b`
+    /// has the operands `s`, `a` and `b`.
+    Op(SVerilogOp, Vec<SVerilogExpr<S>>),
+}
+
+impl<S> SVerilogExpr<S> {
+    /// The wire expression, if this is not an operation.
+    #[inline]
+    pub fn as_wirexpr(&self) -> Option<&Wirexpr<S>> {
+        match self {
+            SVerilogExpr::Wire(w) => Some(w),
+            SVerilogExpr::Op(_, _) => None
+        }
+    }
+
+    /// All the wire expressions in the leaves, from left to right.
+    pub fn wirexprs(&self) -> Vec<&Wirexpr<S>> {
+        fn collect<'i, S>(e: &'i SVerilogExpr<S>, v: &mut Vec<&'i Wirexpr<S>>) {
+            match e {
+                SVerilogExpr::Wire(w) => v.push(w),
+                SVerilogExpr::Op(_, operands) => for o in operands {
+                    collect(o, v);
+                }
+            }
+        }
+        let mut v = Vec::new();
+        collect(self, &mut v);
+        v
+    }
+}
+
+impl<S> From<Wirexpr<S>> for SVerilogExpr<S> {
+    #[inline]
+    fn from(w: Wirexpr<S>) -> SVerilogExpr<S> {
+        SVerilogExpr::Wire(w)
+    }
 }
 
 /// An assign operation.
-#[derive(Debug, Clone)]
-pub struct SVerilogAssign {
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SVerilogAssign<S = CompactString> {
     /// Left-hand side expr.
-    pub lhs: Wirexpr,
-    /// Right-hand side expr.
-    pub rhs: Wirexpr,
+    pub lhs: Wirexpr<S>,
+    /// Right-hand side expr, which can have bitwise operators
+    /// like `assign y = s ? a : ~b;`.
+    pub rhs: SVerilogExpr<S>,
+    /// Attributes before the assignment.
+    pub attrs: SVerilogAttrs<S>,
 }
 
 /// A parsed cell instantiation in structural verilog.
-#[derive(Debug, Clone)]
-pub struct SVerilogCell {
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SVerilogCell<S = CompactString> {
     /// The name of macro. E.g. `NAND`.
-    pub macro_name: CompactString,
+    pub macro_name: S,
     /// The name of cell. E.g. `nand01`.
-    pub cell_name: CompactString,
+    ///
+    /// It is empty for unnamed gate primitive instances like
+    /// `and (y, a, b);`.
+    pub cell_name: S,
+    /// Parameter overrides like `#(.WIDTH(8))`, as tuples of
+    /// (parameter_name, value_expression). The names are None for
+    /// ordered overrides like `#(8)`.
+    pub params: Vec<(Option<S>, S)>,
     /// contains tuples of (macro_pin_name, wire_name).
-    pub ioports: Vec<(CompactString, Wirexpr)>,
+    pub ioports: Vec<(S, Wirexpr<S>)>,
+    /// Positional connections like `(y, a, b)`, in the order of
+    /// the macro pins. An unconnected position is None.
+    ///
+    /// A cell has either named or positional connections, so
+    /// at most one of this and `ioports` is nonempty.
+    pub ordered_ioports: Vec<Option<Wirexpr<S>>>,
+    /// Attributes before the instantiation.
+    pub attrs: SVerilogAttrs<S>,
 }
 
 mod sverilognom;
 
+mod error;
+pub use error::{LocatedParseError, SVerilogParseError};
+
+mod parallel;
+
+mod stream;
+pub use stream::SVerilogModuleIter;
+
+mod primitive;
+pub use primitive::GatePrimitive;
+
 impl SVerilog {
-    /// Parses a string of structural verilog code, and returns a [Result], indicating successful parse result or an error string.
+    /// Parses a string of structural verilog code, and returns a [Result], indicating successful parse result or an error with location.
     #[inline]
-    pub fn parse_str(s: &str) -> Result<SVerilog, String> {
-        Ok(sverilognom::parse_sverilog(s.as_bytes())?)
+    pub fn parse_str(s: &str) -> Result<SVerilog, SVerilogParseError> {
+        sverilognom::parse_sverilog(s.as_bytes())
     }
     
-    /// Parses a u8 slice of structural verilog code, and returns a [Result], indicating successful parse result or an error string.
+    /// Parses a u8 slice of structural verilog code, and returns a [Result], indicating successful parse result or an error with location.
     #[inline]
-    pub fn parse_u8slice(s: &[u8]) -> Result<SVerilog, String> {
-        Ok(sverilognom::parse_sverilog(s)?)
+    pub fn parse_u8slice(s: &[u8]) -> Result<SVerilog, SVerilogParseError> {
+        sverilognom::parse_sverilog(s)
     }
     
-    /// Parses a structural verilog code at the specific path, and returns a [Result], indicating successful parse result or an error string.
+    /// Parses a structural verilog code at the specific path, and returns a [Result], indicating successful parse result or an error with location.
+    /// The path is included in the error.
     #[inline]
-    pub fn parse_file(path: impl AsRef<std::path::Path>) -> Result<SVerilog, String> {
-        let s = match std::fs::read(&path) {
+    pub fn parse_file(path: impl AsRef<std::path::Path>) -> Result<SVerilog, SVerilogParseError> {
+        let path = path.as_ref();
+        let s = match std::fs::read(path) {
             Ok(s) => s,
-            Err(e) => return Err(format!("{}", e))
+            Err(e) => return Err(SVerilogParseError::other(format!("{}", e))
+                                 .with_path(path))
         };
-        SVerilog::parse_u8slice(&s)
+        SVerilog::parse_u8slice(&s).map_err(|e| e.with_path(path))
     }
 }
 
+impl<'a> SVerilog<&'a str> {
+    /// Parses a string of structural verilog code into a borrowed AST
+    /// that refers to the source.
+    ///
+    /// Use [IntoOwned::into_owned] to convert it to the owned AST.
+    #[inline]
+    pub fn parse_borrowed(s: &'a str) -> Result<SVerilog<&'a str>, SVerilogParseError> {
+        sverilognom::parse_sverilog(s.as_bytes())
+    }
+}
+
+mod owned;
+pub use owned::IntoOwned;
+
+mod preprocess;
+pub use preprocess::{SVerilogPreprocessor, PreprocessedSource};
+
+mod lossless;
+pub use lossless::LosslessSVerilog;
+
 mod fmt;
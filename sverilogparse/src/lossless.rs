@@ -0,0 +1,308 @@
+//! Lossless printing of edited sources.
+
+use super::*;
+use crate::fmt::{SVModuleFmt, SVModuleHeaderFmt};
+use itertools::Itertools;
+use std::fmt::{Display, Write};
+use std::ops::Range;
+use sverilognom::{ItemKind, ModuleSpans};
+
+/// A parsed source that keeps its text, to print an edited AST
+/// back with a minimal textual diff.
+///
+/// The comments, whitespaces and attributes in the source are
+/// kept along with the modules and the module items (i.e., the
+/// declarations, assigns and cells). [LosslessSVerilog::print]
+/// prints the unchanged items verbatim, and only reformats the
+/// changed ones by the [Display](std::fmt::Display) impls.
+/// E.g., renaming an instance changes only that statement:
+/// ```
+/// use sverilogparse::LosslessSVerilog;
+///
+/// let src = "\
+/// // a buffer chain.
+/// module chain(a, y);
+///   input a;   // the input
+///   output y;
+///   wire n;
+///   BUF u1(.A(a), .Y(n));
+///   BUF u2(.A(n), .Y(y));  /* the last one */
+/// endmodule
+/// ";
+/// let lossless = LosslessSVerilog::parse_str(src).expect("parse error");
+/// let mut sv = lossless.sverilog().clone();
+/// sv.modules[0].1.cells[0].cell_name = "u_first".into();
+/// assert_eq!(lossless.print(&sv), src.replace("u1", "u_first"));
+/// ```
+///
+/// The edited modules and items are matched to the source ones
+/// in order: the unchanged ones at both ends are matched first,
+/// and the rest are paired one by one. The new items are
+/// printed next to the matched ones of the same kind, or before
+/// `endmodule` if there is none. The removed items are dropped
+/// along with their lines if they are alone in the lines.
+/// A module header with changed ports or declarations is
+/// reformatted, with the declarations moved out of the port list.
+#[derive(Debug, Clone)]
+pub struct LosslessSVerilog {
+    text: String,
+    sverilog: SVerilog,
+    spans: Vec<ModuleSpans>,
+}
+
+/// a step in matching the edited nodes to the source ones.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Step {
+    /// a source node paired with an edited one.
+    Keep(usize, usize),
+    /// a removed source node.
+    Remove(usize),
+    /// an inserted edited node.
+    Insert(usize),
+}
+
+/// match the edited nodes to the source ones. the unchanged
+/// nodes at both ends are matched first, and the rest are
+/// paired in order.
+fn match_nodes<T: PartialEq>(orig: &[T], edited: &[T]) -> Vec<Step> {
+    let prefix = orig.iter().zip(edited)
+        .take_while(|(a, b)| a == b).count();
+    let suffix = orig[prefix..].iter().rev()
+        .zip(edited[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b).count();
+    let n = orig.len() - prefix - suffix;
+    let m = edited.len() - prefix - suffix;
+    let mut steps = (0..prefix + n.min(m))
+        .map(|k| Step::Keep(k, k))
+        .collect::<Vec<_>>();
+    steps.extend((m..n).map(|k| Step::Remove(prefix + k)));
+    steps.extend((n..m).map(|k| Step::Insert(prefix + k)));
+    steps.extend((0..suffix).map(|k| Step::Keep(
+        orig.len() - suffix + k, edited.len() - suffix + k)));
+    steps
+}
+
+/// the edited nodes to print in place of each source node.
+struct Slots {
+    slots: Vec<Vec<usize>>,
+    /// whether a source node is paired with an equal one.
+    same: Vec<bool>,
+    /// the new nodes if there is no source node.
+    extra: Vec<usize>,
+}
+
+impl Slots {
+    fn new<T: PartialEq>(orig: &[T], edited: &[T]) -> Slots {
+        let mut slots: Vec<Vec<usize>> = vec![Vec::new(); orig.len()];
+        let mut same = vec![false; orig.len()];
+        let mut last: Option<usize> = None;
+        let mut pending = Vec::new();
+        for step in match_nodes(orig, edited) {
+            match step {
+                Step::Insert(e) => match last {
+                    Some(o) => slots[o].push(e),
+                    None => pending.push(e)
+                },
+                Step::Keep(o, _) | Step::Remove(o) => {
+                    slots[o].append(&mut pending);
+                    if let Step::Keep(_, e) = step {
+                        slots[o].push(e);
+                        same[o] = orig[o] == edited[e];
+                    }
+                    last = Some(o);
+                }
+            }
+        }
+        Slots { slots, same, extra: pending }
+    }
+
+    /// whether the source nodes are unchanged.
+    fn unchanged(&self, nodes: Range<usize>) -> bool {
+        nodes.into_iter().all(|o| self.same[o] && self.slots[o].len() == 1)
+    }
+
+    /// print the edited nodes in place of the source nodes, one
+    /// in a line.
+    fn print<T: Display>(&self, edited: &[T], nodes: Range<usize>, indent: &str) -> String {
+        self.slots[nodes].iter().flatten()
+            .map(|&e| &edited[e])
+            .join(&format!("\n{}", indent))
+    }
+}
+
+/// whether a text has only whitespaces and comments.
+fn is_trivia(s: &str) -> bool {
+    let s = s.trim_start();
+    s.is_empty() || s.starts_with("//") ||
+        (s.starts_with("/*") && s[2..].find("*/").is_some_and(|p| is_trivia(&s[p + 4..])))
+}
+
+impl LosslessSVerilog {
+    /// Parses a source, keeping its text.
+    pub fn parse_str(s: &str) -> Result<LosslessSVerilog, SVerilogParseError> {
+        let (sverilog, spans) = sverilognom::parse_sverilog_spanned(s.as_bytes())?;
+        Ok(LosslessSVerilog { text: s.to_string(), sverilog, spans })
+    }
+
+    /// The source text.
+    #[inline]
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// The parsed AST, which can be cloned and edited to print.
+    #[inline]
+    pub fn sverilog(&self) -> &SVerilog {
+        &self.sverilog
+    }
+
+    /// remove a source construct at `span` from the printing,
+    /// and return the position to continue.
+    ///
+    /// if it is alone in its line, the line is removed along
+    /// with the comments after it.
+    fn remove(&self, out: &mut String, span: Range<usize>) -> usize {
+        let rest = &self.text[span.end..];
+        let rest = &rest[..rest.find('\n').unwrap_or(rest.len())];
+        let line_start = out.trim_end_matches([' ', '\t']);
+        let alone = line_start.is_empty() || line_start.ends_with('\n');
+        if !alone {
+            out.truncate(line_start.len());
+            return span.end
+        }
+        if !is_trivia(rest) {
+            return span.end + rest.len() - rest.trim_start().len()
+        }
+        out.truncate(line_start.len());
+        if out.ends_with('\n') {
+            out.pop();
+            if out.ends_with('\r') {
+                out.pop();
+            }
+        }
+        span.end + rest.len()
+    }
+
+    /// Prints an edited AST, keeping the source text except the
+    /// changed parts. Printing the AST from
+    /// [LosslessSVerilog::sverilog] gives the source text.
+    pub fn print(&self, edited: &SVerilog) -> String {
+        let text = &self.text;
+        let orig = &self.sverilog.modules;
+        let mut out = String::with_capacity(text.len());
+        let mut pos = 0;
+        let mut last = None;
+        let mut pending = Vec::new();
+        for step in match_nodes(orig, &edited.modules) {
+            let o = match step {
+                Step::Insert(e) => {
+                    match last {
+                        Some(_) => {
+                            let (name, m) = &edited.modules[e];
+                            write!(out, "\n\n{}", SVModuleFmt(name, m)).unwrap();
+                            // without the newline after `endmodule`.
+                            out.pop();
+                        }
+                        None => pending.push(e)
+                    }
+                    continue
+                }
+                Step::Keep(o, _) | Step::Remove(o) => o
+            };
+            let spans = &self.spans[o];
+            out.push_str(&text[pos..spans.header.start]);
+            for e in pending.drain(..) {
+                let (name, m) = &edited.modules[e];
+                writeln!(out, "{}", SVModuleFmt(name, m)).unwrap();
+            }
+            pos = match step {
+                Step::Keep(_, e) if orig[o] == edited.modules[e] => {
+                    out.push_str(&text[spans.header.start..spans.endmodule.end]);
+                    spans.endmodule.end
+                }
+                Step::Keep(_, e) => {
+                    self.print_module(&mut out, o, &edited.modules[e]);
+                    spans.endmodule.end
+                }
+                _ => self.remove(&mut out, spans.header.start..spans.endmodule.end)
+            };
+            last = Some(o);
+        }
+        for e in pending {
+            if !out.is_empty() && !out.ends_with('\n') {
+                out.push('\n');
+            }
+            let (name, m) = &edited.modules[e];
+            write!(out, "{}", SVModuleFmt(name, m)).unwrap();
+        }
+        out.push_str(&text[pos..]);
+        out
+    }
+
+    /// print an edited module in place of the source module `o`.
+    fn print_module(&self, out: &mut String, o: usize, (name, m): &(CompactString, SVerilogModule)) {
+        let text = &self.text;
+        let (orig_name, orig) = &self.sverilog.modules[o];
+        let spans = &self.spans[o];
+        // the indentation of the first item.
+        let indent = spans.items.first()
+            .map(|(span, _, _)| {
+                let line_start = text[..span.start].rfind('\n').map_or(0, |p| p + 1);
+                &text[line_start..span.start]
+            })
+            .filter(|s| s.chars().all(|c| c == ' ' || c == '\t'))
+            .unwrap_or("  ");
+        let params = Slots::new(&orig.params, &m.params);
+        let defs = Slots::new(&orig.defs, &m.defs);
+        let assigns = Slots::new(&orig.assigns, &m.assigns);
+        let cells = Slots::new(&orig.cells, &m.cells);
+
+        let header_params = 0..spans.header_params;
+        let header_defs = 0..spans.header_defs;
+        if orig_name == name && orig.attrs == m.attrs && orig.ports == m.ports &&
+            params.unchanged(header_params.clone()) && defs.unchanged(header_defs.clone())
+        {
+            out.push_str(&text[spans.header.clone()]);
+        }
+        else {
+            write!(out, "{}", SVModuleHeaderFmt(name, m)).unwrap();
+            for s in [params.print(&m.params, header_params, indent),
+                      defs.print(&m.defs, header_defs, indent)] {
+                if !s.is_empty() {
+                    write!(out, "\n{}{}", indent, s).unwrap();
+                }
+            }
+        }
+
+        let mut pos = spans.header.end;
+        for (span, kind, nodes) in &spans.items {
+            let nodes = nodes.clone();
+            let printed = match kind {
+                ItemKind::Param => (!params.unchanged(nodes.clone()))
+                    .then(|| params.print(&m.params, nodes, indent)),
+                ItemKind::Def => (!defs.unchanged(nodes.clone()))
+                    .then(|| defs.print(&m.defs, nodes, indent)),
+                ItemKind::Assign => (!assigns.unchanged(nodes.clone()))
+                    .then(|| assigns.print(&m.assigns, nodes, indent)),
+                ItemKind::Cell => (!cells.unchanged(nodes.clone()))
+                    .then(|| cells.print(&m.cells, nodes, indent)),
+            };
+            out.push_str(&text[pos..span.start]);
+            pos = span.end;
+            match printed {
+                None => out.push_str(&text[span.clone()]),
+                Some(s) if s.is_empty() => pos = self.remove(out, span.clone()),
+                Some(s) => out.push_str(&s)
+            }
+        }
+        // the new items of the kinds not in the source module.
+        let extra = params.extra.iter().map(|&e| m.params[e].to_string())
+            .chain(defs.extra.iter().map(|&e| m.defs[e].to_string()))
+            .chain(assigns.extra.iter().map(|&e| m.assigns[e].to_string()))
+            .chain(cells.extra.iter().map(|&e| m.cells[e].to_string()));
+        for s in extra {
+            write!(out, "\n{}{}", indent, s).unwrap();
+        }
+        out.push_str(&text[pos..spans.endmodule.end]);
+    }
+}
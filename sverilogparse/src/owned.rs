@@ -0,0 +1,164 @@
+//! Conversion from the borrowed AST to the owned one.
+
+use super::*;
+
+fn own<S: Into<CompactString>>(s: S) -> CompactString {
+    s.into()
+}
+
+fn own_attrs<S: Into<CompactString>>(attrs: SVerilogAttrs<S>) -> SVerilogAttrs {
+    attrs.into_iter().map(|(n, v)| (own(n), v.map(own))).collect()
+}
+
+/// Conversion of the AST nodes with borrowed texts (e.g., from
+/// [SVerilog::parse_borrowed]) into the owned ones.
+pub trait IntoOwned {
+    /// The owned AST node.
+    type Owned;
+
+    /// Convert to the owned AST, copying the borrowed texts.
+    fn into_owned(self) -> Self::Owned;
+}
+
+impl<S: Into<CompactString>> IntoOwned for SVerilog<S> {
+    type Owned = SVerilog;
+
+    fn into_owned(self) -> SVerilog {
+        SVerilog {
+            modules: self.modules.into_iter()
+                .map(|(name, m)| (own(name), m.into_owned()))
+                .collect()
+        }
+    }
+}
+
+impl<'a> From<SVerilog<&'a str>> for SVerilog {
+    #[inline]
+    fn from(sv: SVerilog<&'a str>) -> SVerilog {
+        sv.into_owned()
+    }
+}
+
+impl<S: Into<CompactString>> IntoOwned for SVerilogModule<S> {
+    type Owned = SVerilogModule;
+
+    fn into_owned(self) -> SVerilogModule {
+        SVerilogModule {
+            ports: self.ports.into_iter().map(SVerilogPortDef::into_owned).collect(),
+            params: self.params.into_iter().map(SVerilogParamDef::into_owned).collect(),
+            defs: self.defs.into_iter().map(SVerilogWireDef::into_owned).collect(),
+            assigns: self.assigns.into_iter().map(SVerilogAssign::into_owned).collect(),
+            cells: self.cells.into_iter().map(SVerilogCell::into_owned).collect(),
+            attrs: own_attrs(self.attrs)
+        }
+    }
+}
+
+impl<S: Into<CompactString>> IntoOwned for SVerilogWireDef<S> {
+    type Owned = SVerilogWireDef;
+
+    fn into_owned(self) -> SVerilogWireDef {
+        SVerilogWireDef {
+            name: own(self.name), width: self.width, typ: self.typ,
+            signed: self.signed,
+            packed_dims: self.packed_dims,
+            unpacked_dims: self.unpacked_dims,
+            attrs: own_attrs(self.attrs)
+        }
+    }
+}
+
+impl<S: Into<CompactString>> IntoOwned for SVerilogParamDef<S> {
+    type Owned = SVerilogParamDef;
+
+    fn into_owned(self) -> SVerilogParamDef {
+        SVerilogParamDef {
+            name: own(self.name), value: own(self.value),
+            typ: self.typ.map(own), width: self.width, local: self.local
+        }
+    }
+}
+
+impl<S: Into<CompactString>> IntoOwned for SVerilogPortDef<S> {
+    type Owned = SVerilogPortDef;
+
+    fn into_owned(self) -> SVerilogPortDef {
+        use SVerilogPortDef::*;
+        match self {
+            Basic(name) => Basic(own(name)),
+            Conn(name, expr) => Conn(own(name), expr.into_owned())
+        }
+    }
+}
+
+impl<S: Into<CompactString>> IntoOwned for WirexprBasic<S> {
+    type Owned = WirexprBasic;
+
+    fn into_owned(self) -> WirexprBasic {
+        use WirexprBasic::*;
+        match self {
+            Full(name) => Full(own(name)),
+            SingleBit(name, i) => SingleBit(own(name), i),
+            Slice(name, range) => Slice(own(name), range),
+            Select(name, indices, range) => Select(own(name), indices, range),
+            PartSelect(name, indices, ps) => PartSelect(own(name), indices, ps),
+            Literal(w, v, is_xz) => Literal(w, v, is_xz)
+        }
+    }
+}
+
+impl<S: Into<CompactString>> IntoOwned for Wirexpr<S> {
+    type Owned = Wirexpr;
+
+    fn into_owned(self) -> Wirexpr {
+        use Wirexpr::*;
+        match self {
+            Basic(b) => Basic(b.into_owned()),
+            Concat(v) => Concat(v.into_iter().map(WirexprBasic::into_owned).collect())
+        }
+    }
+}
+
+impl<S: Into<CompactString>> IntoOwned for SVerilogExpr<S> {
+    type Owned = SVerilogExpr;
+
+    fn into_owned(self) -> SVerilogExpr {
+        use SVerilogExpr::*;
+        match self {
+            Wire(w) => Wire(w.into_owned()),
+            Op(op, operands) => Op(op, operands.into_iter().map(SVerilogExpr::into_owned).collect())
+        }
+    }
+}
+
+impl<S: Into<CompactString>> IntoOwned for SVerilogAssign<S> {
+    type Owned = SVerilogAssign;
+
+    fn into_owned(self) -> SVerilogAssign {
+        SVerilogAssign {
+            lhs: self.lhs.into_owned(), rhs: self.rhs.into_owned(),
+            attrs: own_attrs(self.attrs)
+        }
+    }
+}
+
+impl<S: Into<CompactString>> IntoOwned for SVerilogCell<S> {
+    type Owned = SVerilogCell;
+
+    fn into_owned(self) -> SVerilogCell {
+        SVerilogCell {
+            macro_name: own(self.macro_name),
+            cell_name: own(self.cell_name),
+            params: self.params.into_iter()
+                .map(|(n, v)| (n.map(own), own(v)))
+                .collect(),
+            ioports: self.ioports.into_iter()
+                .map(|(n, e)| (own(n), e.into_owned()))
+                .collect(),
+            ordered_ioports: self.ordered_ioports.into_iter()
+                .map(|e| e.map(Wirexpr::into_owned))
+                .collect(),
+            attrs: own_attrs(self.attrs)
+        }
+    }
+}
@@ -0,0 +1,84 @@
+//! Memory-mapped, parallel parsing of multiple files.
+
+use super::*;
+use std::collections::HashMap;
+use std::path::Path;
+use rayon::prelude::*;
+
+/// Split the source into parts that end right after each
+/// `endmodule` keyword. The last part contains the remaining
+/// text, if any.
+fn split_modules(s: &[u8]) -> Vec<(usize, usize)> {
+    let mut parts = Vec::new();
+    let mut start = 0;
+    let mut i = 0;
+    while let Some(end) = sverilognom::scan_module_end(s, &mut i, true) {
+        parts.push((start, end));
+        start = end;
+    }
+    if s[start..].iter().any(|c| !c.is_ascii_whitespace()) {
+        parts.push((start, s.len()));
+    }
+    parts
+}
+
+/// Parse a memory-mapped source in parallel, module by module.
+fn parse_source_parallel(source: &[u8]) -> Result<SVerilog, SVerilogParseError> {
+    let parts = split_modules(source).into_par_iter()
+        .map(|(start, end)| sverilognom::parse_sverilog_part(source, start, end))
+        .collect::<Vec<_>>();
+    let mut modules = Vec::new();
+    for part in parts {
+        modules.extend(part?.modules);
+    }
+    Ok(SVerilog { modules })
+}
+
+/// Memory-map a file and parse it.
+fn parse_file_parallel(path: &Path) -> Result<SVerilog, SVerilogParseError> {
+    let io_error = |e: std::io::Error| {
+        SVerilogParseError::other(format!("{}", e)).with_path(path)
+    };
+    let file = std::fs::File::open(path).map_err(io_error)?;
+    if file.metadata().map_err(io_error)?.len() == 0 {
+        return Ok(SVerilog { modules: Vec::new() })
+    }
+    // SAFETY: the file should not be modified when being parsed.
+    let mmap = unsafe { memmap2::Mmap::map(&file) }.map_err(io_error)?;
+    parse_source_parallel(&mmap).map_err(|e| e.with_path(path))
+}
+
+impl SVerilog {
+    /// Parses multiple structural verilog files in parallel, and
+    /// merges the modules into one [SVerilog], in the order of
+    /// files and then the order in each file.
+    ///
+    /// The files are memory-mapped, and split at `endmodule`
+    /// boundaries so that the modules are parsed in parallel.
+    /// The files should not be modified during parsing.
+    ///
+    /// A module defined more than once, in the same file or in
+    /// different files, is an error.
+    pub fn parse_files<P: AsRef<Path> + Sync>(
+        paths: &[P]
+    ) -> Result<SVerilog, SVerilogParseError> {
+        let parsed = paths.par_iter()
+            .map(|path| parse_file_parallel(path.as_ref()))
+            .collect::<Vec<_>>();
+        let mut modules = Vec::new();
+        let mut module2path = HashMap::<CompactString, &Path>::new();
+        for (sv, path) in parsed.into_iter().zip(paths) {
+            let path = path.as_ref();
+            for (name, m) in sv?.modules {
+                if let Some(prev) = module2path.insert(name.clone(), path) {
+                    return Err(SVerilogParseError::other(format!(
+                        "duplicate module {}, previously defined in {}",
+                        name, prev.display()
+                    )).with_path(path))
+                }
+                modules.push((name, m));
+            }
+        }
+        Ok(SVerilog { modules })
+    }
+}
@@ -0,0 +1,499 @@
+//! Compiler directive preprocessing.
+
+use super::*;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// the maximum depth of nested includes and macro expansions,
+/// to catch recursions.
+const MAX_DEPTH: usize = 64;
+
+/// A preprocessor of compiler directives.
+///
+/// It handles:
+/// * `` `define `` and `` `undef `` of macros without arguments,
+///   and the macro usages like `` `WIDTH ``.
+/// * `` `ifdef ``, `` `ifndef ``, `` `elsif ``, `` `else `` and
+///   `` `endif ``.
+/// * `` `include "file" ``, searched in the directory of the
+///   including file and then in [SVerilogPreprocessor::include_dirs].
+/// * `` `timescale ``, which is captured in
+///   [PreprocessedSource::timescale].
+/// * `` `celldefine ``, `` `endcelldefine ``, `` `resetall ``,
+///   `` `default_nettype `` and other directives that do not
+///   affect a structural netlist, which are removed.
+///
+/// Example:
+/// ```
+/// use sverilogparse::SVerilogPreprocessor;
+///
+/// let mut pp = SVerilogPreprocessor::default();
+/// pp.defines.insert("MSB".into(), "3".into());
+/// let src = pp.preprocess_str("`timescale 1ns/1ps
+/// module m(a); input [`MSB:0] a; endmodule").expect("preprocess error");
+/// assert_eq!(src.timescale, Some(("1ns".into(), "1ps".into())));
+/// let parsed = src.parse().expect("parse error");
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct SVerilogPreprocessor {
+    /// The directories to search for included files, in order.
+    pub include_dirs: Vec<PathBuf>,
+    /// The predefined macros, as (name, text).
+    pub defines: HashMap<CompactString, CompactString>,
+}
+
+/// A segment in the source map.
+#[derive(Debug, Clone, Copy)]
+struct Segment {
+    /// the start offset in the preprocessed text.
+    out: usize,
+    /// the source file index.
+    file: usize,
+    /// the start offset in the source file.
+    src: usize,
+    /// whether it is a macro expansion at `src`, rather than
+    /// a copy of the source.
+    expanded: bool,
+}
+
+/// A preprocessed source, with a source map back to the
+/// original files.
+#[derive(Debug, Clone)]
+pub struct PreprocessedSource {
+    /// The preprocessed text.
+    pub text: String,
+    /// The unit and precision of the last `` `timescale ``,
+    /// e.g. `("1ns", "1ps")`.
+    pub timescale: Option<(CompactString, CompactString)>,
+    /// The macros defined at the end of the source.
+    pub defines: HashMap<CompactString, CompactString>,
+    /// the source files, as (path, text). the path is None for
+    /// a string input.
+    files: Vec<(Option<PathBuf>, Arc<str>)>,
+    /// the source map, sorted by the offsets in the text.
+    segments: Vec<Segment>,
+}
+
+impl PreprocessedSource {
+    /// find the source file index and the offset in it.
+    fn locate(&self, offset: usize) -> (usize, usize) {
+        let seg = match self.segments.partition_point(|s| s.out <= offset) {
+            0 => return (0, 0),
+            i => self.segments[i - 1]
+        };
+        match seg.expanded {
+            true => (seg.file, seg.src),
+            false => (seg.file, seg.src + (offset - seg.out))
+        }
+    }
+
+    /// Find the original location of an offset in the
+    /// preprocessed text, as (file path, offset in the file).
+    /// The path is None for a string input.
+    ///
+    /// The text expanded from a macro is located at the macro
+    /// usage.
+    pub fn source_offset(&self, offset: usize) -> (Option<&Path>, usize) {
+        let (file, src) = self.locate(offset);
+        (self.files[file].0.as_deref(), src)
+    }
+
+    /// Point a parse error of the preprocessed text back to the
+    /// original file.
+    pub fn remap_error(&self, e: SVerilogParseError) -> SVerilogParseError {
+        if e.line == 0 {
+            return e
+        }
+        let (file, src) = self.locate(e.offset);
+        let (path, text) = &self.files[file];
+        let e = SVerilogParseError::at(text.as_bytes(), src, e.construct, e.message);
+        match path {
+            Some(path) => e.with_path(path),
+            None => e
+        }
+    }
+
+    /// Parses the preprocessed text, with the errors pointing
+    /// to the original files.
+    pub fn parse(&self) -> Result<SVerilog, SVerilogParseError> {
+        SVerilog::parse_str(&self.text).map_err(|e| self.remap_error(e))
+    }
+}
+
+/// a branch of conditional directives.
+struct Cond {
+    /// whether the enclosing text is active.
+    parent: bool,
+    /// whether the current branch is active.
+    active: bool,
+    /// whether any branch has been taken.
+    taken: bool,
+    /// whether `else has been seen.
+    in_else: bool,
+    /// the location of the `ifdef, as (file, offset).
+    loc: (usize, usize),
+}
+
+/// the preprocessing state.
+struct State<'p> {
+    pp: &'p SVerilogPreprocessor,
+    out: String,
+    timescale: Option<(CompactString, CompactString)>,
+    defines: HashMap<CompactString, CompactString>,
+    files: Vec<(Option<PathBuf>, Arc<str>)>,
+    segments: Vec<Segment>,
+    conds: Vec<Cond>,
+}
+
+#[inline]
+fn is_ident_char(c: u8) -> bool {
+    c.is_ascii_alphanumeric() || c == b'_' || c == b'$'
+}
+
+/// the end of the line starting from `i`, excluding the newline.
+#[inline]
+fn line_end(b: &[u8], i: usize) -> usize {
+    b[i..].iter().position(|&c| c == b'\n').map(|p| p + i).unwrap_or(b.len())
+}
+
+/// skip a comment, a string or an escaped identifier at `i`,
+/// and return its end, or None if there is none at `i`.
+fn skip_token(b: &[u8], i: usize) -> Option<usize> {
+    match (b[i], b.get(i + 1)) {
+        (b'/', Some(b'/')) => Some(line_end(b, i)),
+        (b'/', Some(b'*')) => Some(
+            b[i + 2..].windows(2).position(|w| w == b"*/")
+                .map(|p| p + i + 4).unwrap_or(b.len())),
+        (b'"', _) => {
+            let mut j = i + 1;
+            while j < b.len() && b[j] != b'"' && b[j] != b'\n' {
+                j += if b[j] == b'\\' { 2 } else { 1 };
+            }
+            Some((j + 1).min(b.len()))
+        }
+        (b'\\', _) => Some(
+            b[i..].iter().position(|c| c.is_ascii_whitespace())
+                .map(|p| p + i).unwrap_or(b.len())),
+        _ => None
+    }
+}
+
+impl State<'_> {
+    #[inline]
+    fn active(&self) -> bool {
+        self.conds.last().map(|c| c.active).unwrap_or(true)
+    }
+
+    fn error(&self, file: usize, offset: usize, message: String) -> SVerilogParseError {
+        let (path, text) = &self.files[file];
+        let e = SVerilogParseError::at(text.as_bytes(), offset, Some("directive"), message);
+        match path {
+            Some(path) => e.with_path(path),
+            None => e
+        }
+    }
+
+    /// copy a part of a source file to the output.
+    fn emit(&mut self, file: usize, text: &str, start: usize, end: usize) {
+        if start >= end || !self.active() {
+            return
+        }
+        self.segments.push(Segment {
+            out: self.out.len(), file, src: start, expanded: false
+        });
+        self.out.push_str(&text[start..end]);
+    }
+
+    /// read a macro or directive name after a backtick at `i`.
+    fn name_at<'t>(&self, file: usize, b: &'t [u8], i: usize) -> Result<&'t str, SVerilogParseError> {
+        let len = b[i + 1..].iter().take_while(|&&c| is_ident_char(c)).count();
+        if len == 0 {
+            return Err(self.error(file, i, "expected a directive or macro name after `` ` ``".into()))
+        }
+        Ok(std::str::from_utf8(&b[i + 1..i + 1 + len]).unwrap())
+    }
+
+    /// read an identifier after the spaces at `i`, and return
+    /// it with its end.
+    fn ident_after<'t>(&self, file: usize, b: &'t [u8], i: usize) -> Result<(&'t str, usize), SVerilogParseError> {
+        let start = i + b[i..].iter()
+            .take_while(|&&c| c == b' ' || c == b'\t').count();
+        let len = b[start..].iter().take_while(|&&c| is_ident_char(c)).count();
+        if len == 0 {
+            return Err(self.error(file, start, "expected a macro name".into()))
+        }
+        Ok((std::str::from_utf8(&b[start..start + len]).unwrap(), start + len))
+    }
+
+    /// handle the conditional directives, and return the end
+    /// of the directive, or None if it is not one.
+    fn conditional(
+        &mut self, file: usize, b: &[u8], i: usize, name: &str
+    ) -> Result<Option<usize>, SVerilogParseError> {
+        let end = i + 1 + name.len();
+        let no_branch = |s: &Self| s.error(
+            file, i, format!("`{} without `ifdef", name));
+        match name {
+            "ifdef" | "ifndef" => {
+                let (m, end) = self.ident_after(file, b, end)?;
+                let parent = self.active();
+                let taken = self.defines.contains_key(m) == (name == "ifdef");
+                self.conds.push(Cond {
+                    parent, active: parent && taken, taken,
+                    in_else: false, loc: (file, i)
+                });
+                Ok(Some(end))
+            }
+            "elsif" => {
+                let (m, end) = self.ident_after(file, b, end)?;
+                let defined = self.defines.contains_key(m);
+                let Some(c) = self.conds.last_mut().filter(|c| !c.in_else) else {
+                    return Err(no_branch(self))
+                };
+                c.active = c.parent && !c.taken && defined;
+                c.taken |= defined;
+                Ok(Some(end))
+            }
+            "else" => {
+                let Some(c) = self.conds.last_mut().filter(|c| !c.in_else) else {
+                    return Err(no_branch(self))
+                };
+                c.active = c.parent && !c.taken;
+                c.taken = true;
+                c.in_else = true;
+                Ok(Some(end))
+            }
+            "endif" => match self.conds.pop() {
+                Some(_) => Ok(Some(end)),
+                None => Err(no_branch(self))
+            },
+            _ => Ok(None)
+        }
+    }
+
+    /// handle an active directive or macro usage at `i`, and
+    /// return its end.
+    fn directive(
+        &mut self, file: usize, b: &[u8], i: usize, name: &str, depth: usize
+    ) -> Result<usize, SVerilogParseError> {
+        let end = i + 1 + name.len();
+        match name {
+            "define" => {
+                let (m, start) = self.ident_after(file, b, end)?;
+                if b.get(start) == Some(&b'(') {
+                    return Err(self.error(
+                        file, start, "macros with arguments are not supported".into()))
+                }
+                // the text continues with a backslash before the newline.
+                let mut text = String::new();
+                let mut j = start;
+                loop {
+                    let e = line_end(b, j);
+                    let line = std::str::from_utf8(&b[j..e]).unwrap();
+                    match line.trim_end().strip_suffix('\\') {
+                        Some(line) if e < b.len() => {
+                            text.push_str(line);
+                            text.push('\n');
+                            j = e + 1;
+                        }
+                        _ => {
+                            // a one-line comment is not in the macro text.
+                            let line = match line.find("//") {
+                                Some(p) => &line[..p],
+                                None => line
+                            };
+                            text.push_str(line);
+                            self.defines.insert(m.into(), text.trim().into());
+                            return Ok(e)
+                        }
+                    }
+                }
+            }
+            "undef" => {
+                let (m, end) = self.ident_after(file, b, end)?;
+                self.defines.remove(m);
+                Ok(end)
+            }
+            "include" => {
+                let start = end + b[end..].iter()
+                    .take_while(|&&c| c == b' ' || c == b'\t').count();
+                let len = match b.get(start) {
+                    Some(b'"') => b[start + 1..].iter()
+                        .take_while(|&&c| c != b'"' && c != b'\n').count(),
+                    _ => return Err(self.error(
+                        file, start, "expected a quoted file name".into()))
+                };
+                let name = std::str::from_utf8(&b[start + 1..start + 1 + len]).unwrap();
+                self.include(file, start, name, depth)?;
+                Ok((start + len + 2).min(b.len()))
+            }
+            "timescale" => {
+                let e = line_end(b, end);
+                let spec = std::str::from_utf8(&b[end..e]).unwrap();
+                let spec = match spec.find("//") {
+                    Some(p) => &spec[..p],
+                    None => spec
+                };
+                let Some((unit, precision)) = spec.split_once('/') else {
+                    return Err(self.error(
+                        file, end, "expected `unit / precision`".into()))
+                };
+                self.timescale = Some((unit.trim().into(), precision.trim().into()));
+                Ok(e)
+            }
+            "celldefine" | "endcelldefine" | "resetall" | "nounconnected_drive" => Ok(end),
+            "default_nettype" | "unconnected_drive" | "line" => Ok(line_end(b, end)),
+            _ => {
+                let Some(text) = self.defines.get(name).cloned() else {
+                    return Err(self.error(
+                        file, i, format!("undefined macro `{}`", name)))
+                };
+                self.expand(file, i, &text, depth + 1)?;
+                Ok(end)
+            }
+        }
+    }
+
+    /// expand the text of a macro used at `offset`.
+    fn expand(
+        &mut self, file: usize, offset: usize, text: &str, depth: usize
+    ) -> Result<(), SVerilogParseError> {
+        if depth > MAX_DEPTH {
+            return Err(self.error(file, offset, "too deep macro expansion".into()))
+        }
+        let b = text.as_bytes();
+        let mut start = 0;
+        let mut i = 0;
+        while i < b.len() {
+            if let Some(e) = skip_token(b, i) {
+                i = e;
+                continue
+            }
+            if b[i] != b'`' {
+                i += 1;
+                continue
+            }
+            self.segments.push(Segment {
+                out: self.out.len(), file, src: offset, expanded: true
+            });
+            self.out.push_str(&text[start..i]);
+            let name = self.name_at(file, b, i)
+                .map_err(|_| self.error(file, offset, "bad macro usage in macro text".into()))?;
+            let Some(inner) = self.defines.get(name).cloned() else {
+                return Err(self.error(
+                    file, offset, format!("undefined macro `{}` in macro text", name)))
+            };
+            self.expand(file, offset, &inner, depth + 1)?;
+            i += 1 + name.len();
+            start = i;
+        }
+        self.segments.push(Segment {
+            out: self.out.len(), file, src: offset, expanded: true
+        });
+        self.out.push_str(&text[start..]);
+        Ok(())
+    }
+
+    /// find and process an included file.
+    fn include(
+        &mut self, file: usize, offset: usize, name: &str, depth: usize
+    ) -> Result<(), SVerilogParseError> {
+        if depth >= MAX_DEPTH {
+            return Err(self.error(file, offset, "too deep includes".into()))
+        }
+        let local_dir = self.files[file].0.as_ref()
+            .and_then(|p| p.parent())
+            .map(Path::to_path_buf);
+        let Some(path) = local_dir.iter().chain(&self.pp.include_dirs)
+            .map(|dir| dir.join(name))
+            .find(|p| p.is_file()) else {
+            return Err(self.error(
+                file, offset, format!("cannot find included file {}", name)))
+        };
+        let text = std::fs::read_to_string(&path).map_err(|e| {
+            self.error(file, offset, format!("cannot read {}: {}", path.display(), e))
+        })?;
+        self.files.push((Some(path), text.into()));
+        self.process(self.files.len() - 1, depth + 1)
+    }
+
+    /// process a source file.
+    fn process(&mut self, file: usize, depth: usize) -> Result<(), SVerilogParseError> {
+        let text = self.files[file].1.clone();
+        let b = text.as_bytes();
+        let mut start = 0;
+        let mut i = 0;
+        while i < b.len() {
+            if let Some(e) = skip_token(b, i) {
+                i = e;
+                continue
+            }
+            if b[i] != b'`' {
+                i += 1;
+                continue
+            }
+            self.emit(file, &text, start, i);
+            let name = self.name_at(file, b, i)?;
+            i = match self.conditional(file, b, i, name)? {
+                Some(end) => end,
+                None if self.active() => self.directive(file, b, i, name, depth)?,
+                None => i + 1 + name.len()
+            };
+            start = i;
+        }
+        self.emit(file, &text, start, b.len());
+        Ok(())
+    }
+
+    fn finish(mut self, file: usize) -> Result<PreprocessedSource, SVerilogParseError> {
+        self.process(file, 0)?;
+        if let Some(&Cond { loc: (file, offset), .. }) = self.conds.last() {
+            return Err(self.error(file, offset, "unterminated `ifdef".into()))
+        }
+        Ok(PreprocessedSource {
+            text: self.out,
+            timescale: self.timescale,
+            defines: self.defines,
+            files: self.files,
+            segments: self.segments
+        })
+    }
+}
+
+impl SVerilogPreprocessor {
+    fn state(&self, path: Option<PathBuf>, text: Arc<str>) -> State<'_> {
+        State {
+            pp: self,
+            out: String::with_capacity(text.len()),
+            timescale: None,
+            defines: self.defines.clone(),
+            files: vec![(path, text)],
+            segments: Vec::new(),
+            conds: Vec::new()
+        }
+    }
+
+    /// Preprocesses a string. The included files are searched
+    /// only in [SVerilogPreprocessor::include_dirs].
+    pub fn preprocess_str(&self, s: &str) -> Result<PreprocessedSource, SVerilogParseError> {
+        self.state(None, s.into()).finish(0)
+    }
+
+    /// Preprocesses a file.
+    pub fn preprocess_file(
+        &self, path: impl AsRef<Path>
+    ) -> Result<PreprocessedSource, SVerilogParseError> {
+        let path = path.as_ref();
+        let text = std::fs::read_to_string(path).map_err(|e| {
+            SVerilogParseError::other(format!("{}", e)).with_path(path)
+        })?;
+        self.state(Some(path.to_path_buf()), text.into()).finish(0)
+    }
+
+    /// Preprocesses and parses a file, with the errors pointing
+    /// to the original files.
+    pub fn parse_file(&self, path: impl AsRef<Path>) -> Result<SVerilog, SVerilogParseError> {
+        self.preprocess_file(path)?.parse()
+    }
+}
@@ -0,0 +1,103 @@
+//! Built-in Verilog gate primitives.
+
+use super::*;
+
+/// A built-in Verilog gate primitive, like `and` or `bufif1`.
+///
+/// A primitive instance connects its terminals by position,
+/// like `nand g1 (y, a, b);`. The terminals get implicit pin
+/// names, given by [GatePrimitive::pin_names]:
+/// * `and`, `or`, `nand`, `nor`, `xor` and `xnor` have an
+///   output `Y` followed by inputs `A1`, `A2`, ...
+/// * `buf` and `not` have outputs followed by an input `A`.
+///   The outputs are `Y1`, `Y2`, ... if there are more than one,
+///   or `Y` otherwise.
+/// * `bufif0`, `bufif1`, `notif0` and `notif1` have an output
+///   `Y`, an input `A` and an enable input `EN`.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+pub enum GatePrimitive {
+    And, Or, Nand, Nor, Xor, Xnor,
+    Buf, Not,
+    Bufif0, Bufif1, Notif0, Notif1,
+}
+
+impl GatePrimitive {
+    /// All the primitives.
+    pub const ALL: [GatePrimitive; 12] = {
+        use GatePrimitive::*;
+        [And, Or, Nand, Nor, Xor, Xnor, Buf, Not, Bufif0, Bufif1, Notif0, Notif1]
+    };
+
+    /// The primitive with a keyword, like `nand`.
+    pub fn from_name(name: &str) -> Option<GatePrimitive> {
+        GatePrimitive::ALL.into_iter().find(|p| p.name() == name)
+    }
+
+    /// The keyword of the primitive.
+    pub fn name(self) -> &'static str {
+        use GatePrimitive::*;
+        match self {
+            And => "and", Or => "or", Nand => "nand",
+            Nor => "nor", Xor => "xor", Xnor => "xnor",
+            Buf => "buf", Not => "not",
+            Bufif0 => "bufif0", Bufif1 => "bufif1",
+            Notif0 => "notif0", Notif1 => "notif1",
+        }
+    }
+
+    /// The implicit pin names of an instance with
+    /// `num_terminals` terminals, in the terminal order.
+    ///
+    /// Returns None if the primitive cannot have this number
+    /// of terminals.
+    pub fn pin_names(self, num_terminals: usize) -> Option<Vec<CompactString>> {
+        use GatePrimitive::*;
+        let indexed = |prefix: &'static str, n: usize| (1..=n)
+            .map(move |i| CompactString::from(format!("{}{}", prefix, i)));
+        match self {
+            And | Or | Nand | Nor | Xor | Xnor if num_terminals >= 2 => Some(
+                std::iter::once("Y".into())
+                    .chain(indexed("A", num_terminals - 1))
+                    .collect()),
+            Buf | Not if num_terminals == 2 => Some(vec!["Y".into(), "A".into()]),
+            Buf | Not if num_terminals > 2 => Some(
+                indexed("Y", num_terminals - 1)
+                    .chain(std::iter::once("A".into()))
+                    .collect()),
+            Bufif0 | Bufif1 | Notif0 | Notif1 if num_terminals == 3 => Some(
+                vec!["Y".into(), "A".into(), "EN".into()]),
+            _ => None
+        }
+    }
+
+    /// The direction of an implicit pin: true for an output,
+    /// false for an input, or None if it is not an implicit pin
+    /// of this primitive.
+    pub fn is_output_pin(self, pin_name: &str) -> Option<bool> {
+        use GatePrimitive::*;
+        let is_indexed = |prefix: &str| pin_name.strip_prefix(prefix)
+            .is_some_and(|i| i.parse::<usize>().is_ok_and(|i| i >= 1));
+        match (self, pin_name) {
+            (_, "Y") => Some(true),
+            (And | Or | Nand | Nor | Xor | Xnor, _) if is_indexed("A") => Some(false),
+            (Buf | Not, "A") => Some(false),
+            (Buf | Not, _) if is_indexed("Y") => Some(true),
+            (Bufif0 | Bufif1 | Notif0 | Notif1, "A" | "EN") => Some(false),
+            _ => None
+        }
+    }
+}
+
+impl<S: AsRef<str>> SVerilogCell<S> {
+    /// The gate primitive of this cell, if the macro is a
+    /// primitive keyword and the connections are positional.
+    ///
+    /// Note that a submodule with the same name as a primitive
+    /// takes precedence in netlist construction.
+    pub fn primitive(&self) -> Option<GatePrimitive> {
+        match self.ordered_ioports.is_empty() {
+            true => None,
+            false => GatePrimitive::from_name(self.macro_name.as_ref())
+        }
+    }
+}
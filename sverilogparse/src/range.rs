@@ -62,6 +62,42 @@ impl ExactSizeIterator for SVerilogRange {
     }
 }
 
+/// An indexed part-select in verilog, like `[base +: width]`
+/// or `[base -: width]`.
+///
+/// The bits selected depend on the direction of the declared
+/// range, so it is kept apart from [SVerilogRange] until it is
+/// resolved by [SVerilogPartSelect::resolve].
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct SVerilogPartSelect {
+    /// The base index.
+    pub base: isize,
+    /// The number of bits, which is positive.
+    pub width: usize,
+    /// Whether the bits are counted upward from the base (`+:`)
+    /// or downward (`-:`).
+    pub up: bool,
+}
+
+impl SVerilogPartSelect {
+    /// Resolve to a range in the direction of the declared range.
+    ///
+    /// E.g., `[2 +: 4]` is `[5:2]` on a descending declaration
+    /// and `[2:5]` on an ascending one.
+    #[inline]
+    pub fn resolve(&self, decl: SVerilogRange) -> SVerilogRange {
+        let w = self.width as isize;
+        let (lo, hi) = match self.up {
+            true => (self.base, self.base + w - 1),
+            false => (self.base - w + 1, self.base)
+        };
+        match decl.0 < decl.1 {
+            true => SVerilogRange(lo, hi),
+            false => SVerilogRange(hi, lo)
+        }
+    }
+}
+
 #[test]
 fn test_range() {
     assert_eq!(SVerilogRange(-2, 99).len(), 102);
@@ -71,4 +107,10 @@ fn test_range() {
                vec![1, 2, 3, 4, 5, 6]);
     assert_eq!(SVerilogRange(4, -3).collect::<Vec<_>>(),
                vec![4, 3, 2, 1, 0, -1, -2, -3]);
+    let ps = SVerilogPartSelect { base: 2, width: 4, up: true };
+    assert_eq!(ps.resolve(SVerilogRange(7, 0)), SVerilogRange(5, 2));
+    assert_eq!(ps.resolve(SVerilogRange(0, 7)), SVerilogRange(2, 5));
+    let ps = SVerilogPartSelect { base: 5, width: 2, up: false };
+    assert_eq!(ps.resolve(SVerilogRange(7, 0)), SVerilogRange(5, 4));
+    assert_eq!(ps.resolve(SVerilogRange(0, 7)), SVerilogRange(4, 5));
 }
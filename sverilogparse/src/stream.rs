@@ -0,0 +1,139 @@
+//! Streaming module-by-module parsing from a reader.
+
+use super::*;
+use std::io::Read;
+
+/// the size of each read from the reader.
+const READ_CHUNK: usize = 1 << 20;
+
+/// An iterator that parses the modules in a structural verilog
+/// source one at a time, from an [io::Read](std::io::Read).
+///
+/// Only the text of the current module is kept in memory.
+/// The iteration ends after the first error.
+///
+/// Created by [SVerilog::iter_modules].
+pub struct SVerilogModuleIter<R> {
+    reader: R,
+    /// the text read but not yet dropped.
+    buf: Vec<u8>,
+    /// the start of the unparsed text in `buf`.
+    start: usize,
+    /// the scan position in `buf`.
+    scan: usize,
+    /// the number of bytes dropped before `buf`.
+    dropped_bytes: usize,
+    /// the number of lines dropped before `buf`.
+    dropped_lines: usize,
+    /// the number of characters dropped in the first line of `buf`.
+    dropped_chars: usize,
+    eof: bool,
+    done: bool,
+}
+
+impl<R: Read> SVerilogModuleIter<R> {
+    #[inline]
+    pub fn new(reader: R) -> SVerilogModuleIter<R> {
+        SVerilogModuleIter {
+            reader,
+            buf: Vec::new(),
+            start: 0, scan: 0,
+            dropped_bytes: 0, dropped_lines: 0, dropped_chars: 0,
+            eof: false, done: false
+        }
+    }
+
+    /// drop the parsed text.
+    fn drop_parsed(&mut self) {
+        let dropped = &self.buf[..self.start];
+        match dropped.iter().rposition(|&c| c == b'\n') {
+            Some(p) => {
+                self.dropped_lines += dropped.iter().filter(|&&c| c == b'\n').count();
+                self.dropped_chars = error::count_chars(&dropped[p + 1..]);
+            }
+            None => self.dropped_chars += error::count_chars(dropped)
+        }
+        self.dropped_bytes += self.start;
+        self.buf.drain(..self.start);
+        self.scan -= self.start;
+        self.start = 0;
+    }
+
+    /// read more text into the buffer.
+    fn read_more(&mut self) -> std::io::Result<()> {
+        let n = self.reader.by_ref()
+            .take(READ_CHUNK as u64)
+            .read_to_end(&mut self.buf)?;
+        self.eof = n == 0;
+        Ok(())
+    }
+
+    /// parse the text in `start..end`, and shift the error
+    /// location to the whole input.
+    fn parse_part(&mut self, end: usize) -> Result<SVerilog, SVerilogParseError> {
+        let r = sverilognom::parse_sverilog_part(&self.buf, self.start, end);
+        self.start = end;
+        r.map_err(|mut e| {
+            e.offset += self.dropped_bytes;
+            if e.line == 1 {
+                e.column += self.dropped_chars;
+            }
+            e.line += self.dropped_lines;
+            self.done = true;
+            e
+        })
+    }
+}
+
+impl<R: Read> Iterator for SVerilogModuleIter<R> {
+    type Item = Result<(CompactString, SVerilogModule), SVerilogParseError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while !self.done {
+            let end = sverilognom::scan_module_end(&self.buf, &mut self.scan, self.eof);
+            let end = match (end, self.eof) {
+                (Some(end), _) => end,
+                // the remaining text without any module.
+                (None, true) => {
+                    self.done = true;
+                    self.buf.len()
+                }
+                (None, false) => {
+                    self.drop_parsed();
+                    if let Err(e) = self.read_more() {
+                        self.done = true;
+                        return Some(Err(SVerilogParseError::other(format!("{}", e))))
+                    }
+                    continue
+                }
+            };
+            match self.parse_part(end) {
+                Err(e) => return Some(Err(e)),
+                Ok(sv) => if let Some(m) = sv.modules.into_iter().next() {
+                    return Some(Ok(m))
+                }
+            }
+        }
+        None
+    }
+}
+
+impl SVerilog {
+    /// Parses the modules one at a time from a reader.
+    /// See [SVerilogModuleIter].
+    ///
+    /// Example:
+    /// ```
+    /// use sverilogparse::SVerilog;
+    ///
+    /// let src = "module a(x); input x; endmodule module b(y); output y; endmodule";
+    /// let names = SVerilog::iter_modules(src.as_bytes())
+    ///     .map(|m| m.map(|(name, _)| name))
+    ///     .collect::<Result<Vec<_>, _>>().expect("parse error");
+    /// assert_eq!(names, ["a", "b"]);
+    /// ```
+    #[inline]
+    pub fn iter_modules<R: Read>(reader: R) -> SVerilogModuleIter<R> {
+        SVerilogModuleIter::new(reader)
+    }
+}
@@ -1,27 +1,81 @@
 use super::*;
-use std::{num::NonZeroUsize, str::FromStr};
-use std::fmt;
+use std::{num::NonZeroUsize, ops::Range, str::FromStr};
 use nom::{
-    IResult,
-    combinator::{ value, map, recognize, opt, cut },
+    error::{ ErrorKind, ParseError, ContextError, context },
+    combinator::{ value, map, map_opt, recognize, opt, cut, not },
     branch::alt,
-    multi::{ fold_many0, many0, many0_count, separated_list0 },
+    multi::{ fold_many0, many0, many0_count, separated_list0, separated_list1 },
     sequence::{ delimited, pair, preceded, terminated, tuple },
     character::{ is_alphanumeric, is_hex_digit },
     // we do not plan to stream verilog. so we
     // only include completes.
-    character::complete::{ one_of, char, satisfy, digit1, multispace0 },
-    bytes::complete::{ tag, take_until, take_till1, take_till, is_not },
+    character::complete::{ one_of, char, satisfy, digit1, multispace0, anychar },
+    bytes::complete::{ tag, take_until, take_till1, take_till, is_not, escaped },
 };
 
+/// the nom error type, which records the expected token and the
+/// innermost construct being parsed.
+///
+/// it is created frequently when backtracking, so it must not
+/// allocate.
+#[derive(Debug)]
+pub(crate) struct NomError<I> {
+    input: I,
+    code: ErrorKind,
+    expected_char: Option<char>,
+    expected: Option<&'static str>,
+    construct: Option<&'static str>,
+}
+
+impl<I> ParseError<I> for NomError<I> {
+    fn from_error_kind(input: I, code: ErrorKind) -> Self {
+        NomError {
+            input, code,
+            expected_char: None, expected: None, construct: None
+        }
+    }
+
+    fn append(_: I, _: ErrorKind, other: Self) -> Self {
+        other
+    }
+
+    fn from_char(input: I, c: char) -> Self {
+        NomError {
+            expected_char: Some(c),
+            ..NomError::from_error_kind(input, ErrorKind::Char)
+        }
+    }
+}
+
+impl<I> ContextError<I> for NomError<I> {
+    fn add_context(_: I, ctx: &'static str, mut other: Self) -> Self {
+        if other.construct.is_none() {
+            other.construct = Some(ctx);
+        }
+        other
+    }
+}
+
+type IResult<I, O> = nom::IResult<I, O, NomError<I>>;
+
+
 fn u82str_unsafe(i: &[u8]) -> &str {
     unsafe { std::str::from_utf8_unchecked(i) }
 }
 
+/// the text type in the AST, which is either owned or borrowed
+/// from the source.
+pub(crate) trait Text<'a>: From<&'a str> + Clone {}
+
+impl<'a, S: From<&'a str> + Clone> Text<'a> for S {}
+
 /// comment: starts with // and ends with a line.
 /// do not use streaming operators here.
 /// performance critical.
-pub fn skip_whitespace_and_comment(mut i: &[u8]) -> IResult<&[u8], ()> {
+///
+/// attributes are regarded as comments if `skip_attrs` is set.
+#[inline]
+fn skip_trivia(mut i: &[u8], skip_attrs: bool) -> IResult<&[u8], ()> {
     (i, _) = multispace0(i)?;
     while i.len() > 0 {
         if i[0] == b'/' {
@@ -34,8 +88,7 @@ pub fn skip_whitespace_and_comment(mut i: &[u8]) -> IResult<&[u8], ()> {
             };
             (i, _) = multispace0(i)?;
         }
-        else if i[0] == b'(' {
-            // currently, we regard attributes as comments.
+        else if i[0] == b'(' && skip_attrs {
             i = match value((), tuple((
                 tag("(*"), take_until("*)"), tag("*)")
             )))(i) as IResult<&[u8], ()> {
@@ -51,6 +104,13 @@ pub fn skip_whitespace_and_comment(mut i: &[u8]) -> IResult<&[u8], ()> {
     Ok((i, ()))
 }
 
+/// skips whitespaces and comments.
+/// attributes are also skipped, except for those before modules
+/// and module items, which are parsed by [attributes].
+pub fn skip_whitespace_and_comment(i: &[u8]) -> IResult<&[u8], ()> {
+    skip_trivia(i, true)
+}
+
 /// a higher-order parser transforming a parser to one that
 /// eats up all spaces.
 pub fn ws<'a, F, O>(inner: F) ->
@@ -61,8 +121,34 @@ where F: FnMut(&'a [u8]) -> IResult<&'a [u8], O> {
               skip_whitespace_and_comment)
 }
 
+/// a higher-order parser transforming a parser to one that
+/// eats up the spaces before it only.
+///
+/// this is used at the end of modules and module items, so that
+/// the attributes after them are left for the next item.
+fn ws_lead<'a, F, O>(inner: F) ->
+impl FnMut(&'a [u8]) -> IResult<&'a [u8], O>
+where F: FnMut(&'a [u8]) -> IResult<&'a [u8], O> {
+    preceded(skip_whitespace_and_comment, inner)
+}
+
+/// a higher-order parser that reports `what` as expected, if
+/// the inner parser fails without consuming anything other than
+/// spaces.
+fn expecting<'a, F, O>(what: &'static str, mut inner: F) ->
+impl FnMut(&'a [u8]) -> IResult<&'a [u8], O>
+where F: FnMut(&'a [u8]) -> IResult<&'a [u8], O> {
+    move |i| inner(i).map_err(|e| e.map(|e| {
+        let start = skip_whitespace_and_comment(i).map_or(i, |(j, _)| j);
+        match e.input.len() >= start.len() {
+            true => NomError { expected_char: None, expected: Some(what), ..e },
+            false => e
+        }
+    }))
+}
+
 /// Parse an identifier.
-fn ident(i: &[u8]) -> IResult<&[u8], CompactString> {
+fn ident<'a, S: Text<'a>>(i: &'a [u8]) -> IResult<&'a [u8], S> {
     map(alt((
         preceded(char('\\'), cut(take_till1(|c| {
             c == b'\n' || c == b'\r' || c == b' ' || c == b'\t'
@@ -73,7 +159,7 @@ fn ident(i: &[u8]) -> IResult<&[u8], CompactString> {
                 !is_alphanumeric(c) && c != b'_' && c != b'$'
             }),
         )),
-    )), |s| CompactString::from(u82str_unsafe(s)))(i)
+    )), |s| S::from(u82str_unsafe(s)))(i)
 }
 
 fn int(input: &[u8]) -> IResult<&[u8], isize> {
@@ -94,7 +180,7 @@ fn uint(input: &[u8]) -> IResult<&[u8], usize> {
 /// parses a constant literal.
 /// if the width of the constant exceeds 128, it will be split to multiple
 /// 128-bit entries.
-fn literal(i: &[u8]) -> IResult<&[u8], Vec<WirexprBasic>> {
+fn literal<S>(i: &[u8]) -> IResult<&[u8], Vec<WirexprBasic<S>>> {
     map(tuple((
         uint,
         char('\''),
@@ -173,20 +259,60 @@ fn literal(i: &[u8]) -> IResult<&[u8], Vec<WirexprBasic>> {
     })(i)
 }
 
-fn wirexpr(i: &[u8]) -> IResult<&[u8], Wirexpr> {
+/// a select in brackets.
+enum Sel {
+    Index(isize),
+    Range(isize, isize),
+    Part(SVerilogPartSelect),
+}
+
+/// parses a select like `[3]`, `[3:0]`, `[0 +: 4]` or `[3 -: 4]`.
+fn select(i: &[u8]) -> IResult<&[u8], Sel> {
+    delimited(
+        char('['),
+        cut(map_opt(pair(ws(int), opt(pair(
+            alt((tag(":"), tag("+:"), tag("-:"))), ws(int)
+        ))), |(l, r)| match r {
+            None => Some(Sel::Index(l)),
+            Some((b":", r)) => Some(Sel::Range(l, r)),
+            Some((_, w)) if w <= 0 => None,
+            Some((op, w)) => Some(Sel::Part(SVerilogPartSelect {
+                base: l, width: w as usize, up: op == b"+:"
+            }))
+        })),
+        cut(char(']'))
+    )(i)
+}
+
+fn wirexpr<'a, S: Text<'a>>(i: &'a [u8]) -> IResult<&'a [u8], Wirexpr<S>> {
     use Wirexpr::*;
     use WirexprBasic::*;
-    alt((
-        // ident[int:int]
-        map(pair(ws(ident), cut(opt(
-            delimited(char('['),
-                      pair(ws(int), opt(preceded(char(':'), ws(int)))),
-                      char(']'))
-        ))), |(name, o)| Basic(match o {
-            None => Full(name),
-            Some((l, None)) => SingleBit(name, l),
-            Some((l, Some(r))) => Slice(name, SVerilogRange(l, r))
-        })),
+    expecting("an expression", alt((
+        // ident[int][int:int]
+        map_opt(pair(ws(ident), many0(select)), |(name, mut selects)| Some(Basic(match selects[..] {
+            [] => Full(name),
+            [Sel::Index(l)] => SingleBit(name, l),
+            [Sel::Range(l, r)] => Slice(name, SVerilogRange(l, r)),
+            _ => {
+                let last = match selects.last() {
+                    Some(Sel::Index(_)) => None,
+                    _ => selects.pop()
+                };
+                // a range must be the last select.
+                let indices = selects.into_iter()
+                    .map(|s| match s {
+                        Sel::Index(i) => Some(i),
+                        _ => None
+                    })
+                    .collect::<Option<Vec<_>>>()?;
+                match last {
+                    None => Select(name, indices, None),
+                    Some(Sel::Range(l, r)) => Select(name, indices, Some(SVerilogRange(l, r))),
+                    Some(Sel::Part(ps)) => PartSelect(name, indices, ps),
+                    Some(Sel::Index(_)) => unreachable!()
+                }
+            }
+        }))),
         // literal
         map(literal, |mut l| {
             if l.len() == 1 {
@@ -210,10 +336,10 @@ fn wirexpr(i: &[u8]) -> IResult<&[u8], Wirexpr> {
             }
             Concat(v)
         })
-    ))(i)
+    )))(i)
 }
 
-fn portdef(i: &[u8]) -> IResult<&[u8], SVerilogPortDef> {
+fn portdef<'a, S: Text<'a>>(i: &'a [u8]) -> IResult<&'a [u8], SVerilogPortDef<S>> {
     use SVerilogPortDef::*;
     alt((
         map(ident, |name| Basic(name)),
@@ -223,142 +349,647 @@ fn portdef(i: &[u8]) -> IResult<&[u8], SVerilogPortDef> {
     ))(i)
 }
 
-fn wiredef_push_<'i>(i: &'i [u8], defs: &mut Vec<SVerilogWireDef>) -> IResult<&'i [u8], ()> {
-    use WireDefType::*;
-    let (i, typ) = ws(alt((
-        value(Input, tag("input")),
-        value(Output, tag("output")),
-        value(InOut, tag("inout")),
-        value(Wire, tag("wire")),
+/// a keyword that is not followed by other identifier characters.
+fn keyword<'a>(k: &'static str) -> impl FnMut(&'a [u8]) -> IResult<&'a [u8], &'a [u8]> {
+    terminated(tag(k), not(satisfy(|c| c.is_alphanumeric() || c == '_' || c == '$')))
+}
+
+/// a parameter value expression, kept as text.
+/// it ends before a `,`, `)` or `;` outside of brackets and strings.
+fn param_value<'a, S: Text<'a>>(i: &'a [u8]) -> IResult<&'a [u8], S> {
+    let mut depth = 0;
+    let mut in_str = false;
+    let mut end = None;
+    for (k, &c) in i.iter().enumerate() {
+        if in_str {
+            if c == b'"' && i[k - 1] != b'\\' {
+                in_str = false;
+            }
+            continue
+        }
+        match c {
+            b'"' => in_str = true,
+            b'(' | b'[' | b'{' => depth += 1,
+            b')' | b']' | b'}' if depth > 0 => depth -= 1,
+            b',' | b')' | b';' if depth == 0 => {
+                end = Some(k);
+                break
+            }
+            _ => {}
+        }
+    }
+    match end.map(|end| u82str_unsafe(&i[..end]).trim()) {
+        Some(v) if !v.is_empty() => Ok((&i[end.unwrap()..], v.into())),
+        _ => Err(nom::Err::Error(NomError::from_error_kind(i, ErrorKind::Verify)))
+    }
+}
+
+/// parses the part of a parameter declaration after the
+/// `parameter` or `localparam` keyword, up to the name.
+fn param_head<'a, S: Text<'a>>(i: &'a [u8]) -> IResult<&'a [u8], (Option<S>, Option<SVerilogRange>)> {
+    pair(
+        opt(ws(map(alt((
+            keyword("integer"), keyword("real"), keyword("realtime"),
+            keyword("time"), keyword("string"), keyword("signed")
+        )), |t| S::from(u82str_unsafe(t))))),
+        opt(map(tuple((
+            ws(char('[')), cut(ws(int)), cut(char(':')), cut(ws(int)), cut(ws(char(']')))
+        )), |(_, l, _, r, _)| SVerilogRange(l, r)))
+    )(i)
+}
+
+/// parses `name = value`.
+fn param_assign<'a, S: Text<'a>>(i: &'a [u8]) -> IResult<&'a [u8], (S, S)> {
+    pair(ws(ident), preceded(ws(char('=')), cut(ws(param_value))))(i)
+}
+
+fn paramdef_push_<'a, S: Text<'a>>(
+    i: &'a [u8], params: &mut Vec<SVerilogParamDef<S>>
+) -> IResult<&'a [u8], ()> {
+    let (i, local) = ws(alt((
+        value(false, keyword("parameter")),
+        value(true, keyword("localparam")),
     )))(i)?;
-    let (i, width) = opt(map(tuple((
-        char('['), ws(int), char(':'), ws(int), char(']')
-    )), |(_, l, _, r, _)| SVerilogRange(l, r)))(i)?;
+    let (i, (typ, width)) = cut(param_head)(i)?;
+    let (i, _) = cut(terminated(
+        separated_list1(char(','), map(param_assign, |(name, value)| {
+            params.push(SVerilogParamDef {
+                name, value, typ: typ.clone(), width, local
+            })
+        })),
+        ws_lead(char(';'))
+    ))(i)?;
+    Ok((i, ()))
+}
+
+/// parses the parameter list in a module header, like
+/// `#(parameter W = 8, D = 2)`.
+fn header_params<'a, S: Text<'a>>(i: &'a [u8]) -> IResult<&'a [u8], Vec<SVerilogParamDef<S>>> {
+    let mut params = Vec::<SVerilogParamDef<S>>::new();
+    let (i, _) = delimited(
+        pair(ws(char('#')), ws(char('('))),
+        cut(separated_list0(char(','), |i| {
+            let (i, local) = opt(ws(alt((
+                value(false, keyword("parameter")),
+                value(true, keyword("localparam")),
+            ))))(i)?;
+            // a parameter without keyword inherits the last one.
+            let (i, (typ, width, local)) = match local {
+                Some(local) => map(param_head, |(typ, width)| {
+                    (typ, width, local)
+                })(i)?,
+                None => (i, params.last()
+                         .map(|p| (p.typ.clone(), p.width, p.local))
+                         .unwrap_or((None, None, false)))
+            };
+            let (i, (name, value)) = param_assign(i)?;
+            params.push(SVerilogParamDef { name, value, typ, width, local });
+            Ok((i, ()))
+        })),
+        cut(ws(char(')')))
+    )(i)?;
+    Ok((i, params))
+}
+
+/// parses the parameter overrides of a cell, like
+/// `#(.W(8), .D(2))` or `#(8, 2)`.
+fn cell_params<'a, S: Text<'a>>(i: &'a [u8]) -> IResult<&'a [u8], Vec<(Option<S>, S)>> {
+    delimited(
+        pair(ws(char('#')), cut(ws(char('(')))),
+        cut(alt((
+            separated_list1(char(','), map(tuple((
+                ws(char('.')), ident,
+                ws(char('(')), ws(param_value), ws(char(')'))
+            )), |(_, name, _, value, _)| (Some(name), value))),
+            separated_list0(char(','), map(ws(param_value), |v| (None, v)))
+        ))),
+        cut(ws(char(')')))
+    )(i)
+}
+
+/// an attribute value, which is a string or a simple constant
+/// or identifier, kept as text.
+fn attr_value<'a, S: Text<'a>>(i: &'a [u8]) -> IResult<&'a [u8], S> {
+    map(alt((
+        recognize(delimited(
+            char('"'),
+            opt(escaped(is_not("\\\""), '\\', anychar)),
+            char('"')
+        )),
+        take_till1(|c: u8| {
+            c == b',' || c == b'*' || c == b')' || c.is_ascii_whitespace()
+        })
+    )), |s| S::from(u82str_unsafe(s)))(i)
+}
+
+/// parses the attribute instances before a module or a module
+/// item, like `(* keep, src = "a.v:1" *)`.
+/// the whitespaces and comments before them are also skipped.
+fn attributes<'a, S: Text<'a>>(i: &'a [u8]) -> IResult<&'a [u8], SVerilogAttrs<S>> {
+    fold_many0(
+        preceded(|i| skip_trivia(i, false), delimited(
+            tag("(*"),
+            separated_list0(char(','), pair(
+                ws(ident),
+                opt(preceded(ws(char('=')), cut(ws(attr_value))))
+            )),
+            cut(tag("*)"))
+        )),
+        Vec::new,
+        |mut attrs, a| {
+            attrs.extend(a);
+            attrs
+        }
+    )(i)
+}
+
+/// parses a port direction keyword.
+fn port_direction(i: &[u8]) -> IResult<&[u8], WireDefType> {
+    use WireDefType::*;
+    ws(alt((
+        value(Input, keyword("input")),
+        value(Output, keyword("output")),
+        value(InOut, keyword("inout")),
+    )))(i)
+}
+
+/// parses the optional net type after a port direction, like
+/// `wire` in `output wire y`. it is ignored.
+fn port_net_type(i: &[u8]) -> IResult<&[u8], ()> {
+    value((), opt(ws(alt((
+        keyword("wire"), keyword("tri"), keyword("wand"), keyword("wor"),
+        keyword("reg"), keyword("logic")
+    )))))(i)
+}
+
+/// parses a range like `[3:0]` in a declaration.
+fn decl_range(i: &[u8]) -> IResult<&[u8], SVerilogRange> {
+    map(tuple((
+        char('['), cut(ws(int)), cut(char(':')), cut(ws(int)), cut(char(']'))
+    )), |(_, l, _, r, _)| SVerilogRange(l, r))(i)
+}
+
+/// the data type in a declaration after the type keyword.
+#[derive(Clone)]
+struct DeclShape {
+    signed: bool,
+    /// the packed dimensions except the last one.
+    packed_dims: Vec<SVerilogRange>,
+    /// the last packed dimension.
+    width: Option<SVerilogRange>,
+}
+
+/// parses the data type like `signed [1:0][7:0]` in a declaration.
+fn decl_shape(i: &[u8]) -> IResult<&[u8], DeclShape> {
+    map(pair(
+        opt(ws(keyword("signed"))),
+        many0(ws(decl_range))
+    ), |(signed, mut packed_dims)| {
+        let width = packed_dims.pop();
+        DeclShape { signed: signed.is_some(), packed_dims, width }
+    })(i)
+}
+
+/// parses a declared name followed by the unpacked dimensions,
+/// like `mem [0:15]`.
+fn decl_name<'a, S: Text<'a>>(i: &'a [u8]) -> IResult<&'a [u8], (S, Vec<SVerilogRange>)> {
+    pair(ws(ident), many0(ws(decl_range)))(i)
+}
+
+fn build_def<S: Clone>(
+    (name, unpacked_dims): (S, Vec<SVerilogRange>),
+    typ: WireDefType, shape: &DeclShape, attrs: &SVerilogAttrs<S>
+) -> SVerilogWireDef<S> {
+    SVerilogWireDef {
+        name, width: shape.width, typ,
+        signed: shape.signed,
+        packed_dims: shape.packed_dims.clone(),
+        unpacked_dims,
+        attrs: attrs.clone()
+    }
+}
+
+fn wiredef_push_<'a, S: Text<'a>>(
+    i: &'a [u8], defs: &mut Vec<SVerilogWireDef<S>>, attrs: &SVerilogAttrs<S>
+) -> IResult<&'a [u8], ()> {
+    use WireDefType::*;
+    let (i, typ) = alt((
+        terminated(port_direction, port_net_type),
+        ws(alt((
+            value(Wire, keyword("wire")),
+            value(Tri, keyword("tri")),
+            value(Wand, keyword("wand")),
+            value(Wor, keyword("wor")),
+            value(Supply0, keyword("supply0")),
+            value(Supply1, keyword("supply1")),
+        )))
+    ))(i)?;
+    let (i, shape) = decl_shape(i)?;
     // println!("after wiredef parsing typ {:?}, rng {:?}, remain: {:?}",
     //          typ, width, u82str_unsafe(i));
-    let build_def = |name| SVerilogWireDef { name, width, typ };
     let (i, ()) = cut(
-        map(ws(ident), |name| defs.push(build_def(name)))
+        map(decl_name, |name| defs.push(build_def(name, typ, &shape, attrs)))
     )(i)?;
     let (i, ()) = fold_many0(
-        preceded(char(','), ws(cut(ident))), || (),
-        |_, name| defs.push(build_def(name))
+        preceded(char(','), cut(decl_name)), || (),
+        |_, name| defs.push(build_def(name, typ, &shape, attrs))
     )(i)?;
-    let (i, _) = ws(cut(char(';')))(i)?;
+    let (i, _) = ws_lead(cut(char(';')))(i)?;
     Ok((i, ()))
 }
 
-fn assign(i: &[u8]) -> IResult<&[u8], SVerilogAssign> {
+/// parses an operand of an assign operator: a unary operation,
+/// a parenthesized expression, or a wire expression.
+fn sexpr_unary<'a, S: Text<'a>>(i: &'a [u8]) -> IResult<&'a [u8], SVerilogExpr<S>> {
+    alt((
+        map(preceded(ws(char('~')), cut(sexpr_unary)),
+            |e| SVerilogExpr::Op(SVerilogOp::Not, vec![e])),
+        delimited(ws(char('(')), cut(sexpr), cut(ws(char(')')))),
+        map(wirexpr, SVerilogExpr::Wire)
+    ))(i)
+}
+
+/// parses a left-associative chain of binary operators.
+fn sexpr_chain<'a, S: Text<'a>>(
+    i: &'a [u8],
+    mut operand: impl FnMut(&'a [u8]) -> IResult<&'a [u8], SVerilogExpr<S>>,
+    op: impl FnMut(&'a [u8]) -> IResult<&'a [u8], SVerilogOp>
+) -> IResult<&'a [u8], SVerilogExpr<S>> {
+    let (i, first) = operand(i)?;
+    let (i, rest) = many0(pair(ws(op), cut(operand)))(i)?;
+    Ok((i, rest.into_iter().fold(first, |l, (op, r)| {
+        SVerilogExpr::Op(op, vec![l, r])
+    })))
+}
+
+fn sexpr_and<'a, S: Text<'a>>(i: &'a [u8]) -> IResult<&'a [u8], SVerilogExpr<S>> {
+    sexpr_chain(i, sexpr_unary, value(
+        SVerilogOp::And, terminated(char('&'), not(char('&')))))
+}
+
+fn sexpr_xor<'a, S: Text<'a>>(i: &'a [u8]) -> IResult<&'a [u8], SVerilogExpr<S>> {
+    sexpr_chain(i, sexpr_and, alt((
+        value(SVerilogOp::Xnor, alt((tag("~^"), tag("^~")))),
+        value(SVerilogOp::Xor, char('^'))
+    )))
+}
+
+fn sexpr_or<'a, S: Text<'a>>(i: &'a [u8]) -> IResult<&'a [u8], SVerilogExpr<S>> {
+    sexpr_chain(i, sexpr_xor, value(
+        SVerilogOp::Or, terminated(char('|'), not(char('|')))))
+}
+
+/// parses the right-hand side expression of an assign.
+///
+/// the operators are in the precedence of verilog, from the
+/// highest: `~`, `&`, `^` (and `~^`), `|` and then `?:`, which
+/// is right-associative.
+fn sexpr<'a, S: Text<'a>>(i: &'a [u8]) -> IResult<&'a [u8], SVerilogExpr<S>> {
+    map(pair(sexpr_or, opt(preceded(
+        ws(char('?')),
+        cut(tuple((sexpr, ws(char(':')), sexpr)))
+    ))), |(s, branches)| match branches {
+        None => s,
+        Some((a, _, b)) => SVerilogExpr::Op(SVerilogOp::Cond, vec![s, a, b])
+    })(i)
+}
+
+fn assign<'a, S: Text<'a>>(i: &'a [u8]) -> IResult<&'a [u8], SVerilogAssign<S>> {
     map(tuple((
-        ws(tag("assign")),
+        ws(keyword("assign")),
         cut(wirexpr),
         ws(cut(char('='))),
-        cut(wirexpr),
-        ws(cut(char(';')))
-    )), |(_, lhs, _, rhs, _)| SVerilogAssign { lhs, rhs })(i)
+        cut(expecting("an expression", sexpr)),
+        ws_lead(cut(char(';')))
+    )), |(_, lhs, _, rhs, _)| SVerilogAssign {
+        lhs, rhs, attrs: Vec::new()
+    })(i)
 }
 
-fn cell(i: &[u8]) -> IResult<&[u8], SVerilogCell> {
-    map(tuple((
-        ws(ident), ident,
-        delimited(ws(char('(')), cut(separated_list0(
-            char(','), map(tuple((
+/// the connections of a cell instance, as (named, positional).
+type CellConns<S> = (Vec<(S, Wirexpr<S>)>, Vec<Option<Wirexpr<S>>>);
+
+/// parses a cell instance after the macro name and parameters,
+/// like `u1 (.A(a), .B(b))`. the instance name is optional for
+/// gate primitives.
+fn cell_instance<'a, S: Text<'a>>(
+    i: &'a [u8], is_gate: bool
+) -> IResult<&'a [u8], (Option<S>, CellConns<S>)> {
+    pair(
+        |i| match is_gate {
+            true => opt(ws(ident))(i),
+            false => map(ws(ident), Some)(i)
+        },
+        delimited(ws(char('(')), cut(alt((
+            // named connections.
+            map(separated_list1(char(','), map(tuple((
                 ws(char('.')), ident,
                 ws(char('(')), opt(wirexpr), ws(char(')'))
             )), |(_, name, _, expr, _)| {
-                expr.map(|e| (name, e)) 
+                expr.map(|e| (name, e))
+            })), |ioports| (ioports.into_iter().flatten().collect(), Vec::new())),
+            // positional connections. `()` has no connection.
+            map(separated_list1(char(','), ws(opt(wirexpr))), |mut ordered| {
+                if let [None] = ordered[..] {
+                    ordered.clear();
+                }
+                (Vec::new(), ordered)
             })
-        )), pair(ws(char(')')), ws(char(';'))))
-    )), |(macro_name, cell_name, ioports)| SVerilogCell {
-        macro_name, cell_name, ioports: ioports.into_iter().filter_map(|port| port).collect(),
-    })(i)
+        ))), cut(ws(char(')'))))
+    )(i)
+}
+
+/// parses a cell statement, which can have several instances
+/// like `nand g1 (y1, a, b), g2 (y2, c, d);`.
+///
+/// unnamed gate primitive instances like `and (y, a, b);` get
+/// an empty cell name.
+fn cell<'a, S: Text<'a>>(i: &'a [u8]) -> IResult<&'a [u8], Vec<SVerilogCell<S>>> {
+    let (i, macro_name) = ws(ident::<&str>)(i)?;
+    let (i, params) = map(opt(cell_params), Option::unwrap_or_default)(i)?;
+    let is_gate = GatePrimitive::from_name(macro_name).is_some();
+    let (mut i, first) = cell_instance(i, is_gate)?;
+    let mut instances = vec![first];
+    while let (j, Some(_)) = opt(ws(char(',')))(i)? {
+        let (j, instance) = cut(|i| cell_instance(i, is_gate))(j)?;
+        instances.push(instance);
+        i = j;
+    }
+    let (i, _) = cut(ws_lead(char(';')))(i)?;
+    Ok((i, instances.into_iter().map(|(cell_name, (ioports, ordered_ioports))| {
+        SVerilogCell {
+            macro_name: S::from(macro_name),
+            cell_name: cell_name.unwrap_or_else(|| S::from("")),
+            params: params.clone(), ioports, ordered_ioports,
+            attrs: Vec::new()
+        }
+    }).collect()))
+}
+
+/// parses a port in the module header, which is either a
+/// Verilog-1995 port like `a` or `.a(b)`, or an ANSI port
+/// declaration like `input wire [3:0] a`.
+///
+/// an ANSI port is put into both the ports and the defs.
+/// `ansi` keeps the direction and data type of the last ANSI
+/// declaration, which are inherited by the ports after it
+/// without a direction, like `b` in `input a, b`.
+fn header_port_push_<'a, S: Text<'a>>(
+    i: &'a [u8],
+    ports: &mut Vec<SVerilogPortDef<S>>,
+    defs: &mut Vec<SVerilogWireDef<S>>,
+    ansi: &mut Option<(WireDefType, DeclShape)>
+) -> IResult<&'a [u8], ()> {
+    let (i, attrs) = attributes(i)?;
+    let (i, decl) = opt(pair(
+        terminated(port_direction, port_net_type),
+        decl_shape
+    ))(i)?;
+    let is_decl = decl.is_some();
+    if is_decl {
+        *ansi = decl;
+    }
+    let Some((typ, shape)) = ansi else {
+        return map(ws(portdef), |p| ports.push(p))(i)
+    };
+    let (i, name) = match is_decl {
+        true => cut(decl_name::<S>)(i)?,
+        false => decl_name(i)?
+    };
+    ports.push(SVerilogPortDef::Basic(name.0.clone()));
+    defs.push(build_def(name, *typ, shape, &attrs));
+    Ok((i, ()))
+}
+
+/// the kind of a module item, for [ModuleSpans].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ItemKind {
+    Def, Param, Assign, Cell
+}
+
+/// the source spans of a module and its items, for the lossless
+/// printing. the spans are byte offsets in the source, from the
+/// first attribute or token to the end of the construct, so that
+/// the comments and whitespaces are outside them.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct ModuleSpans {
+    /// the header from the attributes to the `;`.
+    pub header: Range<usize>,
+    /// the numbers of the params and defs in the header.
+    pub header_params: usize,
+    pub header_defs: usize,
+    /// the body items, with their kinds and the index ranges of
+    /// their nodes. e.g., `wire a, b;` has two defs.
+    pub items: Vec<(Range<usize>, ItemKind, Range<usize>)>,
+    /// the `endmodule` keyword.
+    pub endmodule: Range<usize>,
 }
 
-fn module(i: &[u8]) -> IResult<&[u8], (CompactString, SVerilogModule)> {
-    let (i, (name, ports)) = pair(
-        preceded(ws(tag("module")), cut(ident)),
-        cut(delimited(ws(char('(')),
-                      separated_list0(char(','), ws(portdef)),
-                      pair(ws(char(')')), ws(char(';')))))
+fn module<'a, S: Text<'a>>(i: &'a [u8]) -> IResult<&'a [u8], (S, SVerilogModule<S>)> {
+    module_spanned(i, i, None)
+}
+
+/// parses a module, and records its spans in `source` if
+/// `spans` is given.
+fn module_spanned<'a, S: Text<'a>>(
+    i: &'a [u8], source: &'a [u8], mut spans: Option<&mut ModuleSpans>
+) -> IResult<&'a [u8], (S, SVerilogModule<S>)> {
+    let offset = |i: &[u8]| i.as_ptr() as usize - source.as_ptr() as usize;
+    let (i, ()) = skip_trivia(i, false)?;
+    let header_start = offset(i);
+    let (i, attrs) = attributes(i)?;
+    let (i, (name, params)) = pair(
+        preceded(ws(keyword("module")), cut(ws(ident))),
+        map(opt(header_params), Option::unwrap_or_default),
     )(i)?;
+    let mut params = params;
+    let mut ports = Vec::new();
     let mut defs = Vec::new();
+    let mut ansi = None;
+    let (i, _) = cut(delimited(
+        ws(char('(')),
+        separated_list0(char(','), |i| header_port_push_(
+            i, &mut ports, &mut defs, &mut ansi)),
+        pair(ws(char(')')), ws_lead(char(';')))
+    ))(i)?;
+    if let Some(spans) = spans.as_mut() {
+        spans.header = header_start..offset(i);
+        spans.header_params = params.len();
+        spans.header_defs = defs.len();
+    }
     let mut assigns = Vec::new();
     let mut cells = Vec::new();
     // println!("after header parsing.. at: {:?}", u82str_unsafe(i));
-    let (i, _) = many0_count(alt((
-        |i| wiredef_push_(i, &mut defs),
-        map(assign, |a| assigns.push(a)),
-        map(cell, |c| cells.push(c))
-    )))(i)?;
-    let (i, _) = cut(ws(tag("endmodule")))(i)?;
+    let (i, _) = many0_count(|i| {
+        let (i, ()) = skip_trivia(i, false)?;
+        let start = offset(i);
+        let lens = (defs.len(), params.len(), assigns.len(), cells.len());
+        // attributes before parameters are dropped.
+        let (i, attrs) = attributes(i)?;
+        let r = alt((
+            context("wire", |i| wiredef_push_(i, &mut defs, &attrs)),
+            context("parameter", |i| paramdef_push_(i, &mut params)),
+            map(context("assign", assign), |a| assigns.push(SVerilogAssign {
+                attrs: attrs.clone(), ..a
+            })),
+            map(context("cell", cell), |cs| cells.extend(cs.into_iter().map(|c| {
+                SVerilogCell { attrs: attrs.clone(), ..c }
+            })))
+        ))(i);
+        if let (Ok((rest, ())), Some(spans)) = (&r, spans.as_mut()) {
+            use ItemKind::*;
+            let (kind, nodes) = match lens {
+                (d, _, _, _) if d < defs.len() => (Def, d..defs.len()),
+                (_, p, _, _) if p < params.len() => (Param, p..params.len()),
+                (_, _, a, _) if a < assigns.len() => (Assign, a..assigns.len()),
+                (_, _, _, c) => (Cell, c..cells.len())
+            };
+            spans.items.push((start..offset(rest), kind, nodes));
+        }
+        r
+    })(i)?;
+    let (i, _) = cut(ws_lead(expecting("`endmodule`", tag("endmodule"))))(i)?;
+    if let Some(spans) = spans {
+        spans.endmodule = offset(i) - "endmodule".len()..offset(i);
+    }
     Ok((i, (name, SVerilogModule {
-        ports, defs, assigns, cells
+        ports, params, defs, assigns, cells, attrs
     })))
 }
 
-fn sverilog(i: &[u8]) -> IResult<&[u8], SVerilog> {
-    map(preceded(
-        many0_count(ws(char(';'))),
-        ws(many0(terminated(
-            ws(module),
-            many0_count(ws(char(';'))))))),
+fn sverilog<'a, S: Text<'a>>(i: &'a [u8]) -> IResult<&'a [u8], SVerilog<S>> {
+    sverilog_with(i, module)
+}
+
+/// parses the modules by a module parser.
+fn sverilog_with<'a, S: Text<'a>>(
+    i: &'a [u8],
+    module: impl FnMut(&'a [u8]) -> IResult<&'a [u8], (S, SVerilogModule<S>)>
+) -> IResult<&'a [u8], SVerilog<S>> {
+    map(delimited(
+        many0_count(ws_lead(char(';'))),
+        many0(terminated(
+            context("module", module),
+            many0_count(ws_lead(char(';'))))),
+        skip_whitespace_and_comment),
         |modules| SVerilog { modules })(i)
 }
 
-/// a `Display`able parsing error type, which prints at most
-/// 50 characters after the error position.
-pub(crate) struct ParseError {
-    code: nom::error::ErrorKind,
-    partial_input: String
-}
-
-impl From<nom::Err<nom::error::Error<&[u8]>>> for ParseError {
-    fn from(e: nom::Err<nom::error::Error<&[u8]>>) -> Self {
-        match e {
-            nom::Err::Incomplete(_) => ParseError {
-                code: nom::error::ErrorKind::Fail,
-                partial_input: "<incomplete>".into()
-            },
-            nom::Err::Error(e) | nom::Err::Failure(e) => ParseError {
-                code: e.code,
-                partial_input: std::str::from_utf8(
-                    &e.input[..e.input.len().min(50)]).unwrap()
-                    .to_string()
+/// Scan the tokens roughly from `*i` to find the end of the next
+/// `endmodule` keyword, skipping comments, strings and escaped
+/// identifiers, so that a keyword in them is not regarded as
+/// a module boundary.
+///
+/// `*i` is advanced to the end of the scanned tokens. If `eof`
+/// is not set, a token that might continue after the end of `s`
+/// is left unscanned, so that the scan can be resumed later with
+/// more input.
+pub(crate) fn scan_module_end(s: &[u8], i: &mut usize, eof: bool) -> Option<usize> {
+    let is_word = |c: u8| c.is_ascii_alphanumeric() || c == b'_' || c == b'$';
+    // the end of a token, or None if it is incomplete.
+    let find = |from: usize, pat: &[u8]| s[from.min(s.len())..]
+        .windows(pat.len())
+        .position(|w| w == pat)
+        .map(|p| from + p + pat.len());
+    while *i < s.len() {
+        let start = *i;
+        let end = match s[start] {
+            b'/' if start + 1 == s.len() && !eof => None,
+            b'/' if s[start + 1..].starts_with(b"/") => find(start, b"\n"),
+            b'/' if s[start + 1..].starts_with(b"*") => find(start + 2, b"*/"),
+            b'"' => {
+                let mut j = start + 1;
+                while j < s.len() && s[j] != b'"' {
+                    j += if s[j] == b'\\' { 2 } else { 1 };
+                }
+                (j < s.len()).then_some(j + 1)
             }
+            b'\\' => s[start..].iter()
+                .position(|c| c.is_ascii_whitespace())
+                .map(|p| start + p),
+            c if is_word(c) => s[start..].iter()
+                .position(|&c| !is_word(c))
+                .map(|p| start + p),
+            _ => Some(start + 1)
+        };
+        let end = match end {
+            Some(end) => end,
+            None if eof => s.len(),
+            None => return None
+        };
+        *i = end;
+        if &s[start..end] == b"endmodule" {
+            return Some(end)
         }
     }
+    None
 }
 
-impl From<std::io::Error> for ParseError {
-    fn from(e: std::io::Error) -> Self {
-        ParseError {
-            code: nom::error::ErrorKind::Fail,
-            partial_input: format!("<io error: {}>", e)
+/// convert a nom error to a structured one.
+/// `end` is the end offset in the source of the parsed input.
+fn convert_error(
+    source: &[u8], end: usize, e: nom::Err<NomError<&[u8]>>
+) -> SVerilogParseError {
+    match e {
+        nom::Err::Incomplete(_) => SVerilogParseError::at(
+            source, end, None, "unexpected end of input".into()),
+        nom::Err::Error(e) | nom::Err::Failure(e) => {
+            let mut offset = end - e.input.len();
+            // a missing `;` is reported right after the previous token.
+            if e.expected_char == Some(';') {
+                offset = source[..offset].iter()
+                    .rposition(|c| !c.is_ascii_whitespace())
+                    .map_or(0, |p| p + 1);
+            }
+            let message = match (e.expected_char, e.expected, e.code) {
+                (Some(c), _, _) => format!("expected `{}`", c),
+                (_, Some(what), _) => format!("expected {}", what),
+                (_, _, ErrorKind::Complete) => "unexpected input".into(),
+                (_, _, code) => format!("unexpected input ({})", code.description())
+            };
+            SVerilogParseError::at(source, offset, e.construct, message)
         }
     }
 }
 
-impl From<ParseError> for String {
-    fn from(e: ParseError) -> String {
-        format!("{}", e)
-    }
+/// parse a part `source[start..end]` of the source. the error
+/// locations are in the whole source.
+pub(crate) fn parse_sverilog_part<'a, S: Text<'a>>(
+    source: &'a [u8], start: usize, end: usize
+) -> Result<SVerilog<S>, SVerilogParseError> {
+    let i = &source[start..end];
+    finish(source, end, sverilog(i))
 }
 
-impl fmt::Display for ParseError {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "error {:?} at: {}",
-               self.code,
-               self.partial_input)
+/// convert the result of a parser to a structured one, which
+/// fails if the parser does not consume all the input.
+fn finish<O>(
+    source: &[u8], end: usize, r: IResult<&[u8], O>
+) -> Result<O, SVerilogParseError> {
+    let (rem, o) = r.map_err(|e| convert_error(source, end, e))?;
+    if !rem.is_empty() {
+        return Err(convert_error(source, end, nom::Err::Error(
+            NomError::from_error_kind(rem, ErrorKind::Complete))))
     }
+    Ok(o)
 }
 
-pub(crate) fn parse_sverilog(i: &[u8]) -> Result<SVerilog, ParseError> {
-    let (rem, sv) = sverilog(i)?;
-    if rem.len() > 0 {
-        return Err(nom::Err::Error(nom::error::Error {
-            input: rem,
-            code: nom::error::ErrorKind::Complete
-        }).into())
-    }
-    Ok(sv)
+/// parse the source, and record the spans of the modules for
+/// the lossless printing.
+pub(crate) fn parse_sverilog_spanned<'a, S: Text<'a>>(
+    source: &'a [u8]
+) -> Result<(SVerilog<S>, Vec<ModuleSpans>), SVerilogParseError> {
+    let mut spans = Vec::new();
+    let r = sverilog_with(source, |i| {
+        let mut s = ModuleSpans::default();
+        let r = module_spanned(i, source, Some(&mut s))?;
+        spans.push(s);
+        Ok(r)
+    });
+    let sv = finish(source, source.len(), r)?;
+    Ok((sv, spans))
+}
+
+pub(crate) fn parse_sverilog<'a, S: Text<'a>>(i: &'a [u8]) -> Result<SVerilog<S>, SVerilogParseError> {
+    parse_sverilog_part(i, 0, i.len())
 }
@@ -0,0 +1,21 @@
+// ANSI-style port declarations
+module ansi_top #(parameter W = 4) (
+  input wire [3:0] a, b,
+  input clk,
+  (* keep *) output reg [3:0] q,
+  output y
+);
+  wire [3:0] n;
+  sub u0 (.i(a), .o(n));
+  DFF r0 (.D(n[0]), .CK(clk), .Q(q[0]));
+  BUF b0 (.A(b[1]), .Y(y));
+endmodule
+
+module sub(input [3:0] i, output [3:0] o);
+  assign o = i;
+endmodule
+
+module classic (a, .b(c));
+  input a;
+  output wire c;
+endmodule
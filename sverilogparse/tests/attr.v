@@ -0,0 +1,18 @@
+// attributes on modules and module items.
+
+(* top = 1 *) (* src = "attr.v:3" *)
+module attr_test((* port_attr *) a, b, y);
+  (* src = "attr.v:4" *)
+  input a;
+  input b;
+  (* keep *) output y;
+  (* keep, init = 1'b0 *)
+  wire n1, n2;
+  (* unused = 1 *) parameter P = 1;
+  (* src = "attr.v:12" *) assign y = n2;
+  // an attribute with escaped quotes and comment-like text.
+  (* dont_touch = "true", src = "a \"q\" *) " *)
+  AND2 u1 (.A(a), .B(b), .Y(n1));
+  (* keep *) BUF u2 (.A(n1), .Y(n2)); INV u3 (.A(a));
+  (* trailing *)
+endmodule
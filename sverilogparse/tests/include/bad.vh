@@ -0,0 +1,3 @@
+module bad(a);
+  input a
+endmodule
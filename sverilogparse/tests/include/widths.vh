@@ -0,0 +1,4 @@
+`define WIDE 7 // the msb of the wide width
+`define NARROW \
+  `HALF
+`define HALF 1
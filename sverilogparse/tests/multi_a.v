@@ -0,0 +1,13 @@
+// endmodule in comments /* endmodule */ should not split.
+module leaf_a (a, y);
+  input a;
+  output y;
+  /* endmodule */
+  (* note = "endmodule" *) INV \endmodule (.A(a), .Y(y));
+endmodule
+
+module mid (a, y);
+  input a;
+  output y;
+  leaf_a u0 (.a(a), .y(y));
+endmodule;
@@ -0,0 +1,5 @@
+module top (a, y);
+  input a;
+  output y;
+  mid u_mid (.a(a), .y(y));
+endmodule
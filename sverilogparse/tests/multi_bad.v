@@ -0,0 +1,8 @@
+module ok1 (a);
+  input a;
+endmodule
+
+module bad (a);
+  input a;
+  BUF b0 (.A(a) .Y(a));
+endmodule
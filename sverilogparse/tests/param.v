@@ -0,0 +1,13 @@
+// parameters from a generic netlist.
+module param_test #(parameter WIDTH = 2, DEPTH = 4,
+                    parameter integer MODE = 1) (a, y);
+   input [1:0] a;
+   output y;
+   localparam [3:0] INIT = 4'h8, INIT2 = {2'b10, 2'b01};
+   parameter NAME = "and(2)";
+
+   LUT2 #(.INIT(4'h8)) lut0 (.I0(a[0]), .I1(a[1]), .O(y));
+   RAM #(WIDTH, 16) ram0 (.A(a));
+   sub #( .W ( (WIDTH + 1) * 2 ), .S("a,b") ) u_sub (.x(a));
+   BUF #() b0 (.A(y));
+endmodule
@@ -0,0 +1,19 @@
+`timescale 1ns / 1ps
+`include "widths.vh"
+
+`celldefine
+module pp_top(a, y);
+`ifdef USE_WIDE
+  input [`WIDE:0] a;
+`elsif USE_NARROW
+  input [`NARROW:0] a;
+`else
+  input a;
+`endif
+  output y;
+  // `ifdef in comments is ignored.
+`ifndef NO_BUF
+  BUF u0 (.A(a[0]), .Y(y));
+`endif
+endmodule
+`endcelldefine
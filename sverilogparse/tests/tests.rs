@@ -13,7 +13,7 @@ fn test_simple() {
   println!("Restructure: \n{parsed}");
   assert_eq!(format!("{parsed}"), "\
 module simple(inp1, inp2, ispd_clk, out);
-  input inp1;
+  (* src = \"test attribute\" *) input inp1;
   input inp2;
   input ispd_clk;
   output out;
@@ -54,6 +54,425 @@ endmodule
 ");
 }
 
+#[test]
+fn test_net_types() {
+  clilog::init_stdout_simple_trace();
+  let parsed = SVerilog::parse_str("\
+module power(a, y);
+  input a; output y;
+  supply0 VSS; supply1 VDD, VDDA;
+  tri [1:0] t; wand wa; wor wo;
+  tri0_cell u0(.A(a), .Y(y), .VDD(VDD), .VSS(VSS));
+endmodule
+").expect("parse error");
+  println!("Restructure: \n{parsed}");
+  assert_eq!(format!("{parsed}"), "\
+module power(a, y);
+  input a;
+  output y;
+  supply0 VSS;
+  supply1 VDD;
+  supply1 VDDA;
+  tri [1:0] t;
+  wand wa;
+  wor wo;
+
+  tri0_cell u0(.A(a), .Y(y), .VDD(VDD), .VSS(VSS));
+endmodule
+");
+  let reparsed = SVerilog::parse_str(&format!("{parsed}")).expect("reparse error");
+  assert_eq!(format!("{reparsed}"), format!("{parsed}"));
+}
+
+#[test]
+fn test_multi_dim() {
+  clilog::init_stdout_simple_trace();
+  let parsed = SVerilog::parse_str("\
+module mem(d, q);
+  input signed [7:0] d;
+  output [3:0] q;
+  wire [3:0] mem [0:15], m2 [1:0][0:1];
+  wire signed [1:0][3:0] p;
+  assign mem[3] = d[3:0];
+  assign mem[4][3:2] = 2'b01;
+  assign q = {mem[3][1], m2[1][0][2:0]};
+  assign p[1] = mem[2];
+endmodule
+").expect("parse error");
+  println!("Restructure: \n{parsed}");
+  let (_, m) = &parsed.modules[0];
+  assert!(m.defs[0].signed);
+  assert_eq!(m.defs[2].unpacked_dims, [SVerilogRange(0, 15)]);
+  assert_eq!(m.defs[3].dims().collect::<Vec<_>>(),
+             [SVerilogRange(1, 0), SVerilogRange(0, 1), SVerilogRange(3, 0)]);
+  assert_eq!(m.defs[4].packed_dims, [SVerilogRange(1, 0)]);
+  assert_eq!(m.defs[4].width, Some(SVerilogRange(3, 0)));
+  assert_eq!(format!("{parsed}"), "\
+module mem(d, q);
+  input signed [7:0] d;
+  output [3:0] q;
+  wire [3:0] mem [0:15];
+  wire [3:0] m2 [1:0][0:1];
+  wire signed [1:0][3:0] p;
+
+  assign mem[3] = d[3:0];
+  assign mem[4][3:2] = 2'b01;
+  assign q = {mem[3][1], m2[1][0][2:0]};
+  assign p[1] = mem[2];
+endmodule
+");
+  let reparsed = SVerilog::parse_str(&format!("{parsed}")).expect("reparse error");
+  assert_eq!(format!("{reparsed}"), format!("{parsed}"));
+  // a range can only be the last select.
+  assert!(SVerilog::parse_str(
+    "module bad(a); input a; wire [1:0] m [0:3]; assign a = m[1:0][1]; endmodule"
+  ).is_err());
+}
+
+#[test]
+fn test_part_select() {
+  clilog::init_stdout_simple_trace();
+  let parsed = SVerilog::parse_str("\
+module ps(a, y);
+  input [15:0] a;
+  output [7:0] y;
+  wire [3:0] mem [0:3];
+  assign y[3:0] = a[4 +: 4];
+  assign y[7-:4] = {a[15 -: 2], mem[1][0+:2]};
+endmodule
+").expect("parse error");
+  println!("Restructure: \n{parsed}");
+  assert_eq!(format!("{parsed}"), "\
+module ps(a, y);
+  input [15:0] a;
+  output [7:0] y;
+  wire [3:0] mem [0:3];
+
+  assign y[3:0] = a[4 +: 4];
+  assign y[7 -: 4] = {a[15 -: 2], mem[1][0 +: 2]};
+endmodule
+");
+  let m = &parsed.modules[0].1;
+  assert_eq!(m.assigns[0].rhs, SVerilogExpr::Wire(Wirexpr::Basic(WirexprBasic::PartSelect(
+    "a".into(), vec![], SVerilogPartSelect { base: 4, width: 4, up: true }))));
+  let reparsed = SVerilog::parse_str(&format!("{parsed}")).expect("reparse error");
+  assert_eq!(format!("{reparsed}"), format!("{parsed}"));
+  // the width must be positive.
+  assert!(SVerilog::parse_str(
+    "module bad(a, y); input [3:0] a; output y; assign y = a[0 +: 0]; endmodule"
+  ).is_err());
+}
+
+#[test]
+fn test_assign_ops() {
+  clilog::init_stdout_simple_trace();
+  let parsed = SVerilog::parse_str("\
+module ops(a, b, s, y);
+  input [1:0] a, b;
+  input s;
+  output [1:0] y;
+  wire [1:0] t, u, v;
+  assign t = ~a & b | a ^~ ~b;
+  assign u = s ? a : s ? b : 2'b00;
+  assign v = ~(a | {b[0], s}) ^ b;
+  assign y = t;
+endmodule
+").expect("parse error");
+  println!("Restructure: \n{parsed}");
+  let (_, m) = &parsed.modules[0];
+  use SVerilogExpr::*;
+  assert!(matches!(&m.assigns[0].rhs, Op(SVerilogOp::Or, v) if matches!(v[..], [Op(SVerilogOp::And, _), Op(SVerilogOp::Xnor, _)])));
+  assert!(matches!(&m.assigns[1].rhs, Op(SVerilogOp::Cond, v) if matches!(v[2], Op(SVerilogOp::Cond, _))));
+  assert_eq!(m.assigns[2].rhs.wirexprs().len(), 3);
+  assert!(m.assigns[3].rhs.as_wirexpr().is_some());
+  assert_eq!(format!("{parsed}"), "\
+module ops(a, b, s, y);
+  input [1:0] a;
+  input [1:0] b;
+  input s;
+  output [1:0] y;
+  wire [1:0] t;
+  wire [1:0] u;
+  wire [1:0] v;
+
+  assign t = (~a & b) | (a ~^ ~b);
+  assign u = s ? a : (s ? b : 2'b00);
+  assign v = ~(a | {b[0], s}) ^ b;
+  assign y = t;
+endmodule
+");
+  let reparsed = SVerilog::parse_str(&format!("{parsed}")).expect("reparse error");
+  assert_eq!(format!("{reparsed}"), format!("{parsed}"));
+  // logical operators are not supported.
+  assert!(SVerilog::parse_str(
+    "module bad(a, b, y); input a, b; output y; assign y = a && b; endmodule"
+  ).is_err());
+}
+
+#[test]
+fn test_preprocess() {
+  clilog::init_stdout_simple_trace();
+  let dir = concat!(env!("CARGO_MANIFEST_DIR"), "/tests");
+  let mut pp = SVerilogPreprocessor::default();
+  pp.include_dirs.push(format!("{dir}/include").into());
+  pp.defines.insert("USE_NARROW".into(), "".into());
+  let src = pp.preprocess_file(format!("{dir}/preprocess.v")).expect("preprocess error");
+  assert_eq!(src.timescale, Some(("1ns".into(), "1ps".into())));
+  assert_eq!(src.defines["WIDE"], "7");
+  let parsed = src.parse().expect("parse error");
+  println!("Restructure: \n{parsed}");
+  assert_eq!(format!("{parsed}"), "\
+module pp_top(a, y);
+  input [1:0] a;
+  output y;
+
+  BUF u0(.A(a[0]), .Y(y));
+endmodule
+");
+
+  // the macros defined later take effect.
+  let src = pp.preprocess_str("\
+`define USE_WIDE
+`define NO_BUF
+`include \"preprocess.v\"
+").map(|_| ()).expect_err("preprocess.v is not in the include dirs");
+  assert!(src.message.contains("preprocess.v"), "{src}");
+  pp.include_dirs.push(dir.into());
+  let src = pp.preprocess_str("`define USE_WIDE\n`define NO_BUF\n`include \"preprocess.v\"\n")
+    .expect("preprocess error");
+  let parsed = src.parse().expect("parse error");
+  let (_, m) = &parsed.modules[0];
+  assert_eq!(m.defs[0].width, Some(SVerilogRange(7, 0)));
+  assert!(m.cells.is_empty());
+
+  // the errors point to the original files.
+  let e = pp.preprocess_str("module top(x); input x; endmodule\n`include \"bad.vh\"\n")
+    .expect("preprocess error").parse().expect_err("bad.vh should fail");
+  println!("{e}");
+  assert!(e.path.as_ref().is_some_and(|p| p.ends_with("include/bad.vh")), "{e}");
+  assert_eq!((e.line, e.column), (2, 10));
+  pp.defines.insert("W".into(), "7".into());
+  let e = pp.preprocess_str("module top(a);\n  input `W a;\nendmodule\n")
+    .expect("preprocess error").parse().expect_err("bad width should fail");
+  println!("{e}");
+  assert_eq!((e.line, e.column), (2, 9));
+  let e = pp.preprocess_str("module top;\n`ifdef X\nendmodule\n").expect_err("unterminated");
+  assert_eq!(e.line, 2);
+  let e = pp.preprocess_str("module top;\n  `UNDEFINED\nendmodule\n").expect_err("undefined");
+  assert_eq!((e.line, e.column), (2, 3));
+}
+
+const VERILOG_LOSSLESS: &str = "\
+// a buffer chain.
+(* top *)
+module chain(a, y);
+  input a;   // the input
+  output y;
+  wire n1, n2;
+  wire unused;  /* to be removed */
+
+  /* the first stage */
+  BUF u1(.A(a), .Y(n1));
+  (* keep *) BUF   u2 ( .A(n1), .Y(n2) );
+  assign y = n2;
+endmodule
+
+module other(x); input x; endmodule
+";
+
+#[test]
+fn test_lossless() {
+  clilog::init_stdout_simple_trace();
+  for src in [VERILOG_SIMPLE, VERILOG_VECTOR, VERILOG_ANSI, VERILOG_ASSIGN,
+              VERILOG_PORTDEF, VERILOG_PARAM, VERILOG_ATTR, VERILOG_LOSSLESS] {
+    let lossless = LosslessSVerilog::parse_str(src).expect("parse error");
+    assert_eq!(lossless.print(lossless.sverilog()), src);
+  }
+  let lossless = LosslessSVerilog::parse_str(VERILOG_LOSSLESS).expect("parse error");
+
+  // renaming an instance changes only its line.
+  let mut sv = lossless.sverilog().clone();
+  sv.modules[0].1.cells[1].cell_name = "u_second".into();
+  assert_eq!(lossless.print(&sv), VERILOG_LOSSLESS.replace(
+    "(* keep *) BUF   u2 ( .A(n1), .Y(n2) );",
+    "(* keep *) BUF u_second(.A(n1), .Y(n2));"));
+
+  // removing a def, and adding a cell and an assign.
+  let mut sv = lossless.sverilog().clone();
+  let m = &mut sv.modules[0].1;
+  m.defs.retain(|d| d.name != "unused");
+  let mut cell = m.cells[0].clone();
+  cell.cell_name = "u0".into();
+  m.cells.insert(0, cell);
+  sv.modules[1].1.assigns.push(SVerilogAssign {
+    lhs: Wirexpr::Basic(WirexprBasic::Full("x".into())),
+    rhs: Wirexpr::Basic(WirexprBasic::Literal(1, 0, 0)).into(),
+    attrs: Vec::new()
+  });
+  let printed = lossless.print(&sv);
+  println!("{printed}");
+  assert_eq!(printed, VERILOG_LOSSLESS
+    .replace("  wire unused;  /* to be removed */\n", "")
+    .replace("  BUF u1(", "  BUF u0(.A(a), .Y(n1));\n  BUF u1(")
+    .replace("input x; endmodule", "input x;\n  assign x = 1'b0; endmodule"));
+  assert_eq!(format!("{}", SVerilog::parse_str(&printed).expect("reparse error")),
+             format!("{sv}"));
+
+  let mut sv = lossless.sverilog().clone();
+  sv.modules.pop();
+  assert_eq!(lossless.print(&sv),
+             VERILOG_LOSSLESS.replace("module other(x); input x; endmodule\n", ""));
+
+  // a changed header is reformatted, and the new modules are
+  // put after the others.
+  let lossless = LosslessSVerilog::parse_str(VERILOG_ANSI).expect("parse error");
+  let mut sv = lossless.sverilog().clone();
+  let m = &mut sv.modules[1].1;
+  m.ports.push(SVerilogPortDef::Basic("e".into()));
+  m.defs.push(SVerilogWireDef { name: "e".into(), ..m.defs[0].clone() });
+  sv.modules.push(sv.modules[2].clone());
+  sv.modules[3].0 = "classic2".into();
+  let printed = lossless.print(&sv);
+  println!("{printed}");
+  assert_eq!(printed, VERILOG_ANSI.replace(
+    "module sub(input [3:0] i, output [3:0] o);",
+    "module sub(i, o, e);\n  input [3:0] i;\n  output [3:0] o;\n  input [3:0] e;"
+  ) + "
+module classic2(a, .b(c));
+  input a;
+  output c;
+
+endmodule
+");
+  assert_eq!(format!("{}", SVerilog::parse_str(&printed).expect("reparse error")),
+             format!("{sv}"));
+}
+
+const VERILOG_ANSI: &str = include_str!("ansi.v");
+
+#[test]
+fn test_ansi() {
+  clilog::init_stdout_simple_trace();
+  let parsed = SVerilog::parse_str(VERILOG_ANSI).expect("parse error");
+  println!("Restructure: \n{parsed}");
+  assert_eq!(format!("{parsed}"), "\
+module ansi_top(a, b, clk, q, y);
+  parameter W = 4;
+  input [3:0] a;
+  input [3:0] b;
+  input clk;
+  (* keep *) output [3:0] q;
+  output y;
+  wire [3:0] n;
+
+  sub u0(.i(a), .o(n));
+  DFF r0(.D(n[0]), .CK(clk), .Q(q[0]));
+  BUF b0(.A(b[1]), .Y(y));
+endmodule
+module sub(i, o);
+  input [3:0] i;
+  output [3:0] o;
+
+  assign o = i;
+endmodule
+module classic(a, .b(c));
+  input a;
+  output c;
+
+endmodule
+");
+  let reparsed = SVerilog::parse_str(&format!("{parsed}")).expect("reparse error");
+  assert_eq!(format!("{reparsed}"), format!("{parsed}"));
+}
+
+#[test]
+fn test_positional() {
+  clilog::init_stdout_simple_trace();
+  let parsed = SVerilog::parse_str("\
+module top(a, b, y);
+  input a, b; output y;
+  NAND2 u0 (y, a, b);
+  sub u1 ({a, b}, , 1'b0);
+  sub u2 ();
+endmodule
+").expect("parse error");
+  println!("Restructure: \n{parsed}");
+  assert_eq!(format!("{parsed}"), "\
+module top(a, b, y);
+  input a;
+  input b;
+  output y;
+
+  NAND2 u0(y, a, b);
+  sub u1({a, b}, , 1'b0);
+  sub u2();
+endmodule
+");
+  let cells = &parsed.modules[0].1.cells;
+  assert!(cells[0].ioports.is_empty());
+  assert_eq!(cells[1].ordered_ioports.len(), 3);
+  assert!(cells[1].ordered_ioports[1].is_none());
+  assert!(cells[2].ordered_ioports.is_empty());
+  let reparsed = SVerilog::parse_str(&format!("{parsed}")).expect("reparse error");
+  assert_eq!(format!("{reparsed}"), format!("{parsed}"));
+}
+
+#[test]
+fn test_primitive() {
+  let parsed = SVerilog::parse_str("\
+module top(a, b, y);
+  input a, b; output y;
+  nand g0 (y, a, b);
+  not g1 (.A(a), .Y(y));
+endmodule
+").expect("parse error");
+  let cells = &parsed.modules[0].1.cells;
+  assert_eq!(cells[0].primitive(), Some(GatePrimitive::Nand));
+  // named connections refer to a library cell.
+  assert_eq!(cells[1].primitive(), None);
+
+  assert_eq!(GatePrimitive::from_name("bufif1"), Some(GatePrimitive::Bufif1));
+  assert_eq!(GatePrimitive::from_name("NAND2"), None);
+  assert_eq!(GatePrimitive::Xor.pin_names(4).unwrap(), ["Y", "A1", "A2", "A3"]);
+  assert_eq!(GatePrimitive::Not.pin_names(2).unwrap(), ["Y", "A"]);
+  assert_eq!(GatePrimitive::Buf.pin_names(3).unwrap(), ["Y1", "Y2", "A"]);
+  assert_eq!(GatePrimitive::Notif0.pin_names(3).unwrap(), ["Y", "A", "EN"]);
+  assert_eq!(GatePrimitive::Notif0.pin_names(4), None);
+  assert_eq!(GatePrimitive::And.pin_names(1), None);
+  assert_eq!(GatePrimitive::Buf.is_output_pin("Y2"), Some(true));
+  assert_eq!(GatePrimitive::And.is_output_pin("A10"), Some(false));
+  assert_eq!(GatePrimitive::And.is_output_pin("A0"), None);
+  assert_eq!(GatePrimitive::And.is_output_pin("EN"), None);
+}
+
+#[test]
+fn test_primitive_instances() {
+  let parsed = SVerilog::parse_str("\
+module top(a, b, c, y, z);
+  input a, b, c; output y, z;
+  wire n1, n2;
+  and (n1, a, b);
+  (* keep *) nand g1 (n2, b, c), g2 (y, n1, n2), (z, a, c);
+endmodule
+").expect("parse error");
+  let cells = &parsed.modules[0].1.cells;
+  let names = cells.iter().map(|c| c.cell_name.as_str()).collect::<Vec<_>>();
+  assert_eq!(names, ["", "g1", "g2", ""]);
+  assert!(cells.iter().all(|c| c.primitive().is_some()));
+  assert_eq!(cells[3].macro_name, "nand");
+  assert_eq!(format!("{}", cells[3].ordered_ioports[0].as_ref().unwrap()), "z");
+  assert!(cells[1..].iter().all(|c| c.attrs.len() == 1));
+  assert_eq!(format!("{}", cells[0]), "and (n1, a, b);");
+  let reparsed = SVerilog::parse_str(&format!("{parsed}")).expect("reparse error");
+  assert_eq!(reparsed, parsed);
+
+  // only gate primitives can be unnamed.
+  assert!(SVerilog::parse_str(
+    "module top(a, y); input a; output y; BUF (y, a); endmodule").is_err());
+  assert!(SVerilog::parse_str(
+    "module top(a, y); input a; output y; not g0 (y, a), ; endmodule").is_err());
+}
+
 const VERILOG_ASSIGN: &str = include_str!("assign.v");
 
 #[test]
@@ -123,3 +542,178 @@ module simple(inp1, inp2, ispd_clk, out);
 endmodule
 ");
 }
+
+const VERILOG_PARAM: &str = include_str!("param.v");
+
+#[test]
+fn test_param() {
+  clilog::init_stdout_simple_trace();
+  let parsed = SVerilog::parse_str(VERILOG_PARAM).expect("parse error");
+  println!("Parsed is: {parsed:?}");
+  println!("Restructure: \n{parsed}");
+  assert_eq!(format!("{parsed}"), "\
+module param_test(a, y);
+  parameter WIDTH = 2;
+  parameter DEPTH = 4;
+  parameter integer MODE = 1;
+  localparam [3:0] INIT = 4'h8;
+  localparam [3:0] INIT2 = {2'b10, 2'b01};
+  parameter NAME = \"and(2)\";
+  input [1:0] a;
+  output y;
+
+  LUT2 #(.INIT(4'h8)) lut0(.I0(a[0]), .I1(a[1]), .O(y));
+  RAM #(WIDTH, 16) ram0(.A(a));
+  sub #(.W((WIDTH + 1) * 2), .S(\"a,b\")) u_sub(.x(a));
+  BUF b0(.A(y));
+endmodule
+");
+  let reparsed = SVerilog::parse_str(&format!("{parsed}")).expect("reparse error");
+  assert_eq!(format!("{reparsed}"), format!("{parsed}"));
+}
+
+const VERILOG_ATTR: &str = include_str!("attr.v");
+
+#[test]
+fn test_attr() {
+  clilog::init_stdout_simple_trace();
+  let parsed = SVerilog::parse_str(VERILOG_ATTR).expect("parse error");
+  println!("Parsed is: {parsed:?}");
+  println!("Restructure: \n{parsed}");
+  assert_eq!(format!("{parsed}"), "\
+(* top = 1, src = \"attr.v:3\" *) module attr_test(a, b, y);
+  parameter P = 1;
+  (* src = \"attr.v:4\" *) input a;
+  input b;
+  (* keep *) output y;
+  (* keep, init = 1'b0 *) wire n1;
+  (* keep, init = 1'b0 *) wire n2;
+
+  (* src = \"attr.v:12\" *) assign y = n2;
+  (* dont_touch = \"true\", src = \"a \\\"q\\\" *) \" *) AND2 u1(.A(a), .B(b), .Y(n1));
+  (* keep *) BUF u2(.A(n1), .Y(n2));
+  INV u3(.A(a));
+endmodule
+");
+  let m = &parsed.modules[0].1;
+  assert_eq!(m.defs[3].attrs, vec![
+    ("keep".into(), None), ("init".into(), Some("1'b0".into()))
+  ]);
+  assert_eq!(m.cells[1].attrs, vec![("keep".into(), None)]);
+  let reparsed = SVerilog::parse_str(&format!("{parsed}")).expect("reparse error");
+  assert_eq!(format!("{reparsed}"), format!("{parsed}"));
+}
+
+#[test]
+fn test_borrowed() {
+  clilog::init_stdout_simple_trace();
+  let owned = SVerilog::parse_str(VERILOG_ATTR).expect("parse error");
+  let borrowed = SVerilog::parse_borrowed(VERILOG_ATTR).expect("parse error");
+  // the names refer to the source.
+  let (name, m) = &borrowed.modules[0];
+  let range = VERILOG_ATTR.as_bytes().as_ptr_range();
+  assert!(range.contains(&name.as_ptr()));
+  assert!(range.contains(&m.cells[0].cell_name.as_ptr()));
+  assert_eq!(format!("{borrowed}"), format!("{owned}"));
+  assert_eq!(format!("{}", borrowed.into_owned()), format!("{owned}"));
+}
+
+#[test]
+fn test_parse_error() {
+  let src = "module top(a, y);\n  input a;\n  output y;\n  AND2 u1 (.A(a) .B(y));\nendmodule\n";
+  let e = SVerilog::parse_str(src).unwrap_err();
+  assert_eq!((e.line, e.column, e.construct), (4, 18, Some("cell")));
+  assert_eq!(e.offset, src.find(".B").unwrap());
+  assert_eq!(format!("{e}"), "\
+error: expected `)` in cell
+ --> <input>:4:18
+  |
+4 |   AND2 u1 (.A(a) .B(y));
+  |                  ^");
+
+  // a missing `;` is reported after the previous token.
+  let e = SVerilog::parse_str("module top(a);\n  input a\n  wire b;\nendmodule\n").unwrap_err();
+  assert_eq!((e.line, e.column, e.construct), (2, 10, Some("wire")));
+  let e = SVerilog::parse_str("module top(a);\n  assign a = ;\nendmodule\n").unwrap_err();
+  assert_eq!(e.message, "expected an expression");
+  assert_eq!(e.construct, Some("assign"));
+  let e = SVerilog::parse_str("module top(a);\nendmodule\nmodul x;").unwrap_err();
+  assert_eq!((e.line, e.column, e.construct), (3, 1, None));
+
+  // long lines are clipped, and non-ASCII text near the error
+  // position does not break the snippet.
+  let src = format!("module top(a); wire {}; wire /* é */ [3:0 b; endmodule", "x".repeat(200));
+  let e = SVerilog::parse_str(&src).unwrap_err();
+  let pos = src.find(" b;").unwrap() + 1;
+  assert_eq!((e.line, e.column), (1, src[..pos].chars().count() + 1));
+  let snippet = format!("{e}");
+  let snippet = snippet.lines().collect::<Vec<_>>();
+  assert!(snippet[3].starts_with("1 | ...xxx"));
+  assert!(snippet[3].ends_with("endmodule"));
+  let pos = snippet[3].find(" b;").unwrap() + 1;
+  assert_eq!(snippet[4].find('^'), Some(snippet[3][..pos].chars().count()));
+  let e = SVerilog::parse_u8slice(b"module top(a); \xff\xfe\xe4\xbd").unwrap_err();
+  assert_eq!(e.line, 1);
+}
+
+fn test_path(name: &str) -> String {
+  format!("{}/tests/{}", env!("CARGO_MANIFEST_DIR"), name)
+}
+
+#[test]
+fn test_parse_files() {
+  clilog::init_stdout_simple_trace();
+  let parsed = SVerilog::parse_files(&[test_path("multi_a.v"), test_path("multi_b.v")])
+    .expect("parse error");
+  let names = parsed.modules.iter().map(|(n, _)| n.as_str()).collect::<Vec<_>>();
+  assert_eq!(names, ["leaf_a", "mid", "top"]);
+  let whole = SVerilog::parse_str(&format!(
+    "{}{}", include_str!("multi_a.v"), include_str!("multi_b.v")
+  )).expect("parse error");
+  assert_eq!(format!("{parsed}"), format!("{whole}"));
+
+  // duplicate modules across files.
+  let e = SVerilog::parse_files(&[test_path("multi_a.v"), test_path("multi_a.v")])
+    .unwrap_err();
+  assert!(e.message.starts_with("duplicate module leaf_a"));
+
+  // error locations are in the whole file.
+  let e = SVerilog::parse_files(&[test_path("multi_b.v"), test_path("multi_bad.v")])
+    .unwrap_err();
+  assert_eq!(e.path.as_deref(), Some(std::path::Path::new(&test_path("multi_bad.v"))));
+  assert_eq!((e.line, e.column, e.construct), (7, 17, Some("cell")));
+}
+
+/// a reader returning at most 3 bytes at a time.
+struct SlowReader<'a>(&'a [u8]);
+
+impl std::io::Read for SlowReader<'_> {
+  fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+    let n = buf.len().min(self.0.len()).min(3);
+    buf[..n].copy_from_slice(&self.0[..n]);
+    self.0 = &self.0[n..];
+    Ok(n)
+  }
+}
+
+#[test]
+fn test_iter_modules() {
+  clilog::init_stdout_simple_trace();
+  let src = format!("{}{}{}", include_str!("multi_a.v"), VERILOG_ATTR,
+                    include_str!("multi_b.v").trim_end());
+  let whole = SVerilog::parse_str(&src).expect("parse error");
+  let modules = SVerilog::iter_modules(SlowReader(src.as_bytes()))
+    .collect::<Result<Vec<_>, _>>().expect("parse error");
+  assert_eq!(format!("{}", SVerilog { modules }), format!("{whole}"));
+
+  // the error locations are in the whole input.
+  let src = format!("{}{}", include_str!("multi_b.v"), include_str!("multi_bad.v"));
+  let e_whole = SVerilog::parse_str(&src).unwrap_err();
+  let mut iter = SVerilog::iter_modules(SlowReader(src.as_bytes()));
+  assert_eq!(iter.next().unwrap().unwrap().0, "top");
+  assert_eq!(iter.next().unwrap().unwrap().0, "ok1");
+  let e = iter.next().unwrap().unwrap_err();
+  assert!(iter.next().is_none());
+  assert_eq!((e.offset, e.line, e.column), (e_whole.offset, e_whole.line, e_whole.column));
+  assert_eq!(format!("{e}"), format!("{e_whole}"));
+}